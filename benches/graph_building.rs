@@ -0,0 +1,54 @@
+//! Serial against parallel graph building, over a large set of transactions derived from the
+//! test fixtures. Run with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::Value;
+use solconnect::{build_transaction_graph, GraphBuilder, GraphOptions};
+
+const TRANSACTIONS: usize = 20_000;
+const WALLETS: usize = 5_000;
+
+/// Copies of the fixtures with their first two accounts replaced by wallets from a fixed pool,
+/// so the graph grows shared nodes and edges like a real crawl does.
+fn transactions() -> Vec<Value> {
+    let fixtures: Vec<Value> = [
+        include_str!("../tests/fixtures/transactions/sol_transfer.json"),
+        include_str!("../tests/fixtures/transactions/multi_account.json"),
+        include_str!("../tests/fixtures/transactions/versioned_loaded_addresses.json"),
+        include_str!("../tests/fixtures/transactions/parsed_transfers.json"),
+    ].iter().map(|json| serde_json::from_str(json).expect("fixture is valid JSON")).collect();
+
+    (0..TRANSACTIONS).map(|i| {
+        let mut transaction = fixtures[i % fixtures.len()].clone();
+        let message = &mut transaction["transaction"]["message"];
+        for (k, key) in message["accountKeys"].as_array_mut().expect("account keys").iter_mut().take(2).enumerate() {
+            let wallet = Value::from(format!("Wallet{}", (i * 7 + k * 13) % WALLETS));
+            match key.get_mut("pubkey") {
+                Some(pubkey) => *pubkey = wallet,
+                None => *key = wallet,
+            }
+        }
+        transaction["transaction"]["signatures"] = serde_json::json!([format!("sig{}", i)]);
+        transaction
+    }).collect()
+}
+
+fn graph_building(c: &mut Criterion) {
+    let transactions = transactions();
+    let options = GraphOptions { directed: true, ..GraphOptions::default() };
+
+    let mut group = c.benchmark_group("graph_building");
+    group.sample_size(10);
+    group.bench_function("serial", |b| b.iter(|| {
+        let mut builder = GraphBuilder::new(&options);
+        for transaction in &transactions {
+            builder.add(transaction);
+        }
+        builder.finish()
+    }));
+    group.bench_function("parallel", |b| b.iter(|| build_transaction_graph(&transactions, &options)));
+    group.finish();
+}
+
+criterion_group!(benches, graph_building);
+criterion_main!(benches);
@@ -0,0 +1,434 @@
+//! On-disk cache of `getTransaction` results, in a directory or, with the `sqlite` feature, a database.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+use crate::graph::Graph;
+#[cfg(feature = "sqlite")]
+use crate::store::SqliteStore;
+
+/// `$XDG_CACHE_HOME/solconnect`, falling back to `~/.cache/solconnect`.
+pub fn default_cache_dir() -> PathBuf {
+    if let Ok(dir) = env::var("XDG_CACHE_HOME") {
+        return PathBuf::from(dir).join("solconnect");
+    }
+    match env::var("HOME") {
+        Ok(home) => PathBuf::from(home).join(".cache").join("solconnect"),
+        Err(_) => PathBuf::from(".solconnect-cache"),
+    }
+}
+
+/// The clusters that keep separate caches under the cache directory: a signature names a
+/// transaction of one cluster only, so their entries must not mix.
+pub const CACHE_CLUSTERS: &[&str] = &["mainnet", "devnet", "testnet", "localnet"];
+
+/// How many entries of each kind a cache directory holds, and the disk space they take.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheUsage {
+    pub transactions: u64,
+    pub histories: u64,
+    pub domains: u64,
+    pub bytes: u64,
+}
+
+impl CacheUsage {
+    pub fn entries(&self) -> u64 {
+        self.transactions + self.histories + self.domains
+    }
+
+    fn count(&mut self, kind: EntryKind, bytes: u64) {
+        match kind {
+            EntryKind::Transaction => self.transactions += 1,
+            EntryKind::History => self.histories += 1,
+            EntryKind::Domain => self.domains += 1,
+        }
+        self.bytes += bytes;
+    }
+
+    pub fn add(&mut self, other: &CacheUsage) {
+        self.transactions += other.transactions;
+        self.histories += other.histories;
+        self.domains += other.domains;
+        self.bytes += other.bytes;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum EntryKind {
+    Transaction,
+    History,
+    Domain,
+}
+
+impl EntryKind {
+    /// Whether `key`, a file name without its extension, names an entry of this kind: a
+    /// transaction is stored under its signature, a history or domain under its address.
+    fn is_key(self, key: &str) -> bool {
+        match self {
+            EntryKind::Transaction => Signature::from_str(key).is_ok(),
+            EntryKind::History | EntryKind::Domain => Pubkey::from_str(key).is_ok(),
+        }
+    }
+}
+
+/// Calls `visit` with the kind, path and metadata of every entry of the cache directory `dir`:
+/// the transactions at its top, and the files under `history/` and `names/`. Only JSON files named
+/// after a signature or an address count; other files, such as the caches of other clusters kept
+/// in subdirectories or anything else a shared directory holds, are skipped.
+fn visit_entries(dir: &Path, mut visit: impl FnMut(EntryKind, &Path, &fs::Metadata) -> io::Result<()>) -> io::Result<()> {
+    let kinds = [
+        (dir.to_path_buf(), EntryKind::Transaction),
+        (dir.join("history"), EntryKind::History),
+        (dir.join("names"), EntryKind::Domain),
+    ];
+    for (kind_dir, kind) in kinds {
+        let entries = match fs::read_dir(&kind_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let metadata = entry.metadata()?;
+            let key = name.strip_suffix(".json.gz").or_else(|| name.strip_suffix(".json"));
+            if metadata.is_file() && key.is_some_and(|key| kind.is_key(key)) {
+                visit(kind, &entry.path(), &metadata)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The entries of the cache directory `dir`, of either format; nothing if it doesn't exist.
+pub fn directory_usage(dir: &Path) -> io::Result<CacheUsage> {
+    let mut usage = CacheUsage::default();
+    visit_entries(dir, |kind, _, metadata| {
+        usage.count(kind, metadata.len());
+        Ok(())
+    })?;
+    Ok(usage)
+}
+
+/// Deletes the entries of the cache directory `dir` that were last written more than
+/// `older_than` ago, or all of them, and returns what was deleted.
+pub fn clear_directory(dir: &Path, older_than: Option<Duration>) -> io::Result<CacheUsage> {
+    let cutoff = older_than.and_then(|age| SystemTime::now().checked_sub(age));
+    let mut removed = CacheUsage::default();
+    visit_entries(dir, |kind, path, metadata| {
+        if let Some(cutoff) = cutoff {
+            if metadata.modified()? >= cutoff {
+                return Ok(());
+            }
+        }
+        fs::remove_file(path)?;
+        removed.count(kind, metadata.len());
+        Ok(())
+    })?;
+    Ok(removed)
+}
+
+/// How the entries of a cache directory are written. Entries are read in either format, so a cache
+/// filled with one setting stays usable with the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Each entry is gzipped into a `.json.gz` file, a fraction of the size of the JSON
+    #[default]
+    Gzip,
+}
+
+impl Compression {
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "json",
+            Compression::Gzip => "json.gz",
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Compression::None => Compression::Gzip,
+            Compression::Gzip => Compression::None,
+        }
+    }
+
+    fn encode(self, json: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(json),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&json)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    fn decode(self, contents: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(contents),
+            Compression::Gzip => {
+                let mut json = Vec::new();
+                GzDecoder::new(contents.as_slice()).read_to_end(&mut json)?;
+                Ok(json)
+            }
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            other => Err(format!("unknown cache compression: {}", other)),
+        }
+    }
+}
+
+/// Finalized transactions never change, so their getTransaction results are stored as one JSON file per signature.
+/// The signature history of each fetched address is kept alongside, under `history/`, so a graph can be
+/// rebuilt later without any RPC calls. Resolved `.sol` domains are kept under `names/`.
+/// Entries are gzipped unless the cache is opened with [`Compression::None`]. A cache opened with
+/// `open_database` keeps all of this in a [`SqliteStore`] instead.
+pub struct TransactionCache {
+    backend: Backend,
+    compression: Compression,
+}
+
+enum Backend {
+    Dir(PathBuf),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore),
+}
+
+#[cfg(feature = "sqlite")]
+fn database_error(error: rusqlite::Error) -> std::io::Error {
+    std::io::Error::other(error)
+}
+
+/// Writes to a temporary file first so an interrupted run never leaves a truncated entry behind.
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(tmp, path)
+}
+
+/// The file of the entry at `base` when written with `compression`.
+fn entry_file(base: &Path, compression: Compression) -> PathBuf {
+    let mut file = base.as_os_str().to_owned();
+    file.push(".");
+    file.push(compression.extension());
+    file.into()
+}
+
+impl TransactionCache {
+    pub fn open(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(dir.join("history"))?;
+        fs::create_dir_all(dir.join("names"))?;
+        Ok(TransactionCache { backend: Backend::Dir(dir), compression: Compression::default() })
+    }
+
+    /// Writes new directory entries with `compression`. A database ignores it.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Opens or creates a SQLite database at `path` as the cache.
+    #[cfg(feature = "sqlite")]
+    pub fn open_database(path: &Path) -> std::io::Result<Self> {
+        Ok(TransactionCache { backend: Backend::Sqlite(SqliteStore::open(path).map_err(database_error)?), compression: Compression::default() })
+    }
+
+    /// The cache directory, or the database file.
+    pub fn location(&self) -> &Path {
+        match self.backend {
+            Backend::Dir(ref dir) => dir,
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.path(),
+        }
+    }
+
+    /// Reads the JSON of the entry at `base`, in whichever format it was written.
+    fn read_entry(&self, base: &Path) -> Option<Vec<u8>> {
+        [self.compression, self.compression.other()].into_iter()
+            .find_map(|compression| fs::read(entry_file(base, compression)).ok().map(|contents| (compression, contents)))
+            .and_then(|(compression, contents)| compression.decode(contents).ok())
+    }
+
+    /// Writes the entry at `base` in the configured format, replacing one in the other format,
+    /// which would otherwise hide the update from a cache opened with the other setting.
+    fn write_entry(&self, base: &Path, json: Vec<u8>) -> std::io::Result<()> {
+        write_atomically(&entry_file(base, self.compression), &self.compression.encode(json)?)?;
+        match fs::remove_file(entry_file(base, self.compression.other())) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// The cached signatures of `address`, newest first.
+    pub fn get_history(&self, address: &str) -> Option<Vec<String>> {
+        match self.backend {
+            Backend::Dir(ref dir) => {
+                let contents = self.read_entry(&dir.join("history").join(address))?;
+                serde_json::from_slice(&contents).ok()
+            }
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.get_history(address).ok().flatten(),
+        }
+    }
+
+    /// Adds newly fetched signatures, newest first, in front of those already cached for `address`.
+    pub fn merge_history(&self, address: &str, signatures: &[String]) -> std::io::Result<()> {
+        match self.backend {
+            Backend::Dir(ref dir) => {
+                let mut merged = signatures.to_vec();
+                let fetched: HashSet<&String> = signatures.iter().collect();
+                if let Some(cached) = self.get_history(address) {
+                    merged.extend(cached.into_iter().filter(|signature| !fetched.contains(signature)));
+                }
+                self.write_entry(&dir.join("history").join(address), serde_json::to_vec(&merged)?)
+            }
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.merge_history(address, signatures).map_err(database_error),
+        }
+    }
+
+    /// The cached `.sol` domain of `address`: `Some(None)` when it is known to have none.
+    pub fn get_domain(&self, address: &str) -> Option<Option<String>> {
+        match self.backend {
+            Backend::Dir(ref dir) => {
+                let contents = self.read_entry(&dir.join("names").join(address))?;
+                serde_json::from_slice(&contents).ok()
+            }
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.get_domain(address).ok().flatten(),
+        }
+    }
+
+    pub fn put_domain(&self, address: &str, domain: Option<&str>) -> std::io::Result<()> {
+        match self.backend {
+            Backend::Dir(ref dir) => self.write_entry(&dir.join("names").join(address), serde_json::to_vec(&domain)?),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.put_domain(address, domain).map_err(database_error),
+        }
+    }
+
+    pub fn get(&self, signature: &str) -> Option<Value> {
+        match self.backend {
+            Backend::Dir(ref dir) => {
+                let contents = self.read_entry(&dir.join(signature))?;
+                serde_json::from_slice(&contents).ok()
+            }
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.get(signature).ok().flatten(),
+        }
+    }
+
+    pub fn put(&self, signature: &str, transaction: &Value) -> std::io::Result<()> {
+        match self.backend {
+            Backend::Dir(ref dir) => self.write_entry(&dir.join(signature), serde_json::to_vec(transaction)?),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.put(signature, transaction).map_err(database_error),
+        }
+    }
+
+    /// Stores the graph built from the cached data next to it, so it can be queried or reloaded
+    /// with `--load-graph`. Only a database keeps graphs; a cache directory ignores them.
+    pub fn record_graph(&self, graph: &Graph, directed: bool) -> std::io::Result<()> {
+        match self.backend {
+            Backend::Dir(_) => {
+                let _ = (graph, directed);
+                Ok(())
+            }
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(ref store) => store.save_graph(graph, directed).map_err(database_error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_history_keeps_newest_first_without_duplicates() {
+        let dir = env::temp_dir().join(format!("solconnect-cache-{}", std::process::id()));
+        let cache = TransactionCache::open(dir.clone()).unwrap();
+        let signatures = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        cache.merge_history("A", &signatures(&["s2", "s1"])).unwrap();
+        cache.merge_history("A", &signatures(&["s4", "s3", "s2"])).unwrap();
+        assert_eq!(cache.get_history("A"), Some(signatures(&["s4", "s3", "s2", "s1"])));
+        assert_eq!(cache.get_history("B"), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn usage_counts_and_clear_removes_only_entries() {
+        let dir = env::temp_dir().join(format!("solconnect-cache-usage-{}", std::process::id()));
+        let cache = TransactionCache::open(dir.clone()).unwrap();
+        let (s1, s2) = (Signature::from([1; 64]).to_string(), Signature::from([2; 64]).to_string());
+        let address = Pubkey::new_unique().to_string();
+        cache.put(&s1, &serde_json::json!({ "slot": 1 })).unwrap();
+        cache.put(&s2, &serde_json::json!({ "slot": 2 })).unwrap();
+        cache.merge_history(&address, &[s2.clone(), s1.clone()]).unwrap();
+        cache.put_domain(&address, Some("alice.sol")).unwrap();
+        fs::write(dir.join("repl_history"), "path A B\n").unwrap();
+        // Files that only look like entries, as a cache directory shared with other data may hold
+        fs::write(dir.join("package.json"), "{}").unwrap();
+        fs::write(dir.join("history").join("notes.json.gz"), "").unwrap();
+        fs::write(dir.join("names").join(format!("{}.json", s1)), "null").unwrap();
+
+        let usage = directory_usage(&dir).unwrap();
+        assert_eq!((usage.transactions, usage.histories, usage.domains, usage.entries()), (2, 1, 1, 4));
+        assert!(usage.bytes > 0);
+
+        assert_eq!(clear_directory(&dir, Some(Duration::from_secs(3600))).unwrap(), CacheUsage::default());
+        assert_eq!(clear_directory(&dir, None).unwrap(), usage);
+        assert_eq!(directory_usage(&dir).unwrap(), CacheUsage::default());
+        assert!(dir.join("repl_history").is_file());
+        assert!(dir.join("package.json").is_file());
+        assert!(dir.join("history").join("notes.json.gz").is_file());
+        assert!(dir.join("names").join(format!("{}.json", s1)).is_file());
+        assert_eq!(directory_usage(&dir.join("missing")).unwrap(), CacheUsage::default());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn entries_are_read_whichever_compression_wrote_them() {
+        let dir = env::temp_dir().join(format!("solconnect-cache-compression-{}", std::process::id()));
+        let transaction = serde_json::json!({ "slot": 5, "meta": { "err": null } });
+
+        let gzip = TransactionCache::open(dir.clone()).unwrap();
+        gzip.put("s1", &transaction).unwrap();
+        assert!(dir.join("s1.json.gz").is_file());
+        assert_eq!(gzip.get("s1"), Some(transaction.clone()));
+
+        let plain = TransactionCache::open(dir.clone()).unwrap().with_compression(Compression::None);
+        assert_eq!(plain.get("s1"), Some(transaction.clone()));
+        plain.merge_history("A", &["s1".to_string()]).unwrap();
+        gzip.merge_history("A", &["s2".to_string()]).unwrap();
+        assert!(!dir.join("history").join("A.json").exists());
+        assert_eq!(plain.get_history("A"), Some(vec!["s2".to_string(), "s1".to_string()]));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
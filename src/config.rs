@@ -0,0 +1,150 @@
+//! Defaults for command-line options read from a `solconnect.toml` file.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Deserializer};
+
+use crate::error::SolConnectError;
+
+/// The config file name looked up in the working directory and the user config directory.
+pub const CONFIG_FILE_NAME: &str = "solconnect.toml";
+
+/// Defaults for the shared command-line options. Keys are the long flag names, e.g.
+/// `rpc-url = "https://..."` or `concurrency = 20`. Values only stand in for flags that aren't
+/// given, and are validated the same way as the flags they replace.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// One endpoint, or a list of endpoints to fail over between
+    #[serde(deserialize_with = "one_or_many")]
+    pub rpc_url: Vec<String>,
+    pub cluster: Option<String>,
+    pub commitment: Option<String>,
+    pub encoding: Option<String>,
+    pub provider: Option<String>,
+    pub api_key: Option<String>,
+    pub auth_header: Option<String>,
+    pub max_signatures: Option<usize>,
+    pub concurrency: Option<usize>,
+    pub batch_size: Option<usize>,
+    pub max_retries: Option<u32>,
+    /// Seconds, fractions allowed
+    pub timeout: Option<f64>,
+    pub proxy: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_compression: Option<String>,
+    pub labels: Option<PathBuf>,
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// `$XDG_CONFIG_HOME/solconnect/solconnect.toml`, falling back to `~/.config/solconnect/solconnect.toml`.
+pub fn user_config_file() -> Option<PathBuf> {
+    let dir = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(dir.join("solconnect").join(CONFIG_FILE_NAME))
+}
+
+impl Config {
+    pub fn load(file: &Path) -> Result<Self, SolConnectError> {
+        let contents = fs::read_to_string(file)?;
+        toml::from_str(&contents).map_err(|e| SolConnectError::Parse(format!("config file {}: {}", file.display(), e)))
+    }
+
+    /// The first config file found, in the working directory and then in the user config
+    /// directory, together with its path. `Ok(None)` if there is none.
+    pub fn find() -> Result<Option<(PathBuf, Self)>, SolConnectError> {
+        let candidates = std::iter::once(PathBuf::from(CONFIG_FILE_NAME)).chain(user_config_file());
+        for file in candidates {
+            if file.is_file() {
+                let config = Config::load(&file)?;
+                return Ok(Some((file, config)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The flags that take the place of the option `name` when given on the command line, besides
+    /// the option's own flag: a configured endpoint is no default for `--cluster`, nor a proxy
+    /// for `--no-proxy`.
+    pub fn superseded_by(name: &str) -> &'static [&'static str] {
+        match name {
+            "rpc-url" => &["cluster"],
+            "cluster" => &["rpc-url"],
+            "proxy" => &["no-proxy"],
+            "cache-dir" => &["db", "no-cache"],
+            _ => &[],
+        }
+    }
+
+    /// The configured options as (long flag name, value) pairs, one pair per endpoint for `rpc-url`.
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        let mut options: Vec<(&'static str, String)> = self.rpc_url.iter().map(|url| ("rpc-url", url.clone())).collect();
+        let mut add = |name: &'static str, value: Option<String>| options.extend(value.map(|value| (name, value)));
+        add("cluster", self.cluster.clone());
+        add("commitment", self.commitment.clone());
+        add("encoding", self.encoding.clone());
+        add("provider", self.provider.clone());
+        add("api-key", self.api_key.clone());
+        add("auth-header", self.auth_header.clone());
+        add("max-signatures", self.max_signatures.map(|n| n.to_string()));
+        add("concurrency", self.concurrency.map(|n| n.to_string()));
+        add("batch-size", self.batch_size.map(|n| n.to_string()));
+        add("max-retries", self.max_retries.map(|n| n.to_string()));
+        add("timeout", self.timeout.map(|n| n.to_string()));
+        add("proxy", self.proxy.clone());
+        add("cache-dir", self.cache_dir.as_ref().map(|dir| dir.display().to_string()));
+        add("cache-compression", self.cache_compression.clone());
+        add("labels", self.labels.as_ref().map(|file| file.display().to_string()));
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_values_become_flag_defaults() {
+        let config: Config = toml::from_str(r#"
+            rpc-url = "https://rpc.example.com"
+            concurrency = 20
+            cache-dir = "/data/solconnect"
+        "#).unwrap();
+        assert_eq!(config.options(), vec![
+            ("rpc-url", "https://rpc.example.com".to_string()),
+            ("concurrency", "20".to_string()),
+            ("cache-dir", "/data/solconnect".to_string()),
+        ]);
+
+        let config: Config = toml::from_str(r#"rpc-url = ["https://a.example.com", "https://b.example.com"]"#).unwrap();
+        assert_eq!(config.rpc_url.len(), 2);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("concurency = 20").is_err());
+        assert!(toml::from_str::<Config>("concurrency = \"many\"").is_err());
+        assert_eq!(toml::from_str::<Config>("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn timeouts_can_be_fractional() {
+        assert_eq!(toml::from_str::<Config>("timeout = 2.5").unwrap().options(), vec![("timeout", "2.5".to_string())]);
+        assert_eq!(toml::from_str::<Config>("timeout = 30").unwrap().timeout, Some(30.0));
+    }
+}
@@ -0,0 +1,113 @@
+//! The error type returned by the RPC and provider helpers.
+
+use std::sync::Arc;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite::Error as WsError;
+
+/// JSON-RPC error codes that indicate a temporary node condition rather than a bad request:
+/// block not available, node behind, slot skipped or missing after a snapshot jump, block status
+/// not yet available, and minimum context slot not reached. Another try, or another endpoint, can
+/// succeed where the first failed.
+const RETRYABLE_RPC_CODES: [i64; 5] = [-32004, -32005, -32007, -32014, -32016];
+
+/// Errors are cheap to clone so a failed batch request can report the same cause for every
+/// transaction in the batch.
+#[derive(Clone, Debug, Error)]
+pub enum SolConnectError {
+    /// The request never got a response: connection failure, timeout, TLS error
+    #[error("network error: {0}")]
+    Network(Arc<reqwest::Error>),
+    /// The server answered with a non-success HTTP status
+    #[error("HTTP {0}")]
+    Http(reqwest::StatusCode),
+    /// The node returned a JSON-RPC error object
+    #[error("RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    /// A response or file could not be decoded into the expected shape
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
+    /// No owner is registered for a `.sol` domain given in place of an address
+    #[error("{0} is not a registered .sol domain")]
+    DomainNotFound(String),
+    /// A client setting, such as an auth header, was rejected before any request was sent
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    /// `getTransaction` returned null, usually because the node has pruned the transaction
+    #[error("transaction {0} not found")]
+    TransactionNotFound(String),
+    #[error("I/O error: {0}")]
+    Io(Arc<std::io::Error>),
+    /// The WebSocket connection for a subscription failed or was closed by the node
+    #[error("WebSocket error: {0}")]
+    WebSocket(Arc<WsError>),
+}
+
+impl SolConnectError {
+    /// Whether the failure is likely transient and worth retrying with backoff.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SolConnectError::Network(_) => true,
+            SolConnectError::WebSocket(error) => match error.as_ref() {
+                WsError::Io(_) | WsError::ConnectionClosed | WsError::AlreadyClosed => true,
+                WsError::Http(response) => response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS.as_u16() || response.status().is_server_error(),
+                _ => false,
+            },
+            SolConnectError::Http(status) => *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            SolConnectError::Rpc { code, .. } => RETRYABLE_RPC_CODES.contains(code),
+            _ => false,
+        }
+    }
+
+    /// Whether the server turned the request away for going over its rate limit.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, SolConnectError::Http(status) if *status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+    }
+}
+
+impl From<reqwest::Error> for SolConnectError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            SolConnectError::Parse(error.to_string())
+        } else {
+            SolConnectError::Network(Arc::new(error))
+        }
+    }
+}
+
+impl From<std::io::Error> for SolConnectError {
+    fn from(error: std::io::Error) -> Self {
+        SolConnectError::Io(Arc::new(error))
+    }
+}
+
+impl From<WsError> for SolConnectError {
+    fn from(error: WsError) -> Self {
+        SolConnectError::WebSocket(Arc::new(error))
+    }
+}
+
+impl From<serde_json::Error> for SolConnectError {
+    fn from(error: serde_json::Error) -> Self {
+        SolConnectError::Parse(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_failures_are_retryable() {
+        assert!(SolConnectError::Http(reqwest::StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert!(SolConnectError::Http(reqwest::StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(!SolConnectError::Http(reqwest::StatusCode::FORBIDDEN).is_retryable());
+        assert!(SolConnectError::Http(reqwest::StatusCode::TOO_MANY_REQUESTS).is_rate_limited());
+        assert!(!SolConnectError::Http(reqwest::StatusCode::BAD_GATEWAY).is_rate_limited());
+        assert!(SolConnectError::Rpc { code: -32005, message: "Node is behind".to_string() }.is_retryable());
+        assert!(SolConnectError::Rpc { code: -32007, message: "Slot 1 was skipped, or missing due to ledger jump to recent snapshot".to_string() }.is_retryable());
+        assert!(!SolConnectError::Rpc { code: -32602, message: "Invalid params".to_string() }.is_retryable());
+        assert!(!SolConnectError::InvalidAddress("abc".to_string()).is_retryable());
+    }
+}
@@ -0,0 +1,982 @@
+//! Output formats for analysis results: the JSON result document, Graphviz DOT, GEXF, GraphML,
+//! CSV and adjacency matrices.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+use crate::error::SolConnectError;
+use crate::flow::Flow;
+use crate::graph::{add_edge, Counterparty, Edge, Graph, Neighbor, NodeKind, NodeKinds};
+use crate::labels::Labels;
+use crate::stats::{degrees, DegreeDistribution, NodeDegree};
+
+/// The `--format json` result document.
+#[derive(Serialize)]
+pub struct AnalysisResult<'a> {
+    pub addresses: &'a [String],
+    pub directed: bool,
+    pub node_count: usize,
+    /// Whether every input address is linked to every other, directly or through other inputs
+    pub connected: bool,
+    pub pairs: Vec<PairResult<'a>>,
+    /// Every edge used by at least one path, with its transaction count
+    pub path_edges: Vec<PathEdge<'a>>,
+    /// The highest-degree nodes, when `--stats` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hubs: Option<&'a [NodeDegree]>,
+    /// How the degrees of all nodes are spread, when `--stats` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degree_distribution: Option<&'a DegreeDistribution>,
+    /// Names of the labelled addresses that appear on a path
+    pub labels: BTreeMap<&'a str, &'a str>,
+    /// Nodes kept out of the path searches by `--max-node-degree`
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub excluded_hubs: &'a [String],
+}
+
+/// The paths found between one pair of input addresses.
+#[derive(Serialize)]
+pub struct PairResult<'a> {
+    pub address1: &'a str,
+    pub address2: &'a str,
+    pub paths: &'a [Vec<String>],
+    /// With `--temporal`, the block time of every hop of every path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hop_times: Option<Vec<Vec<i64>>>,
+    /// With `--timeline`, when the transactions behind every hop of every path took place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hop_windows: Option<Vec<Vec<HopWindow<'a>>>>,
+    /// With `--fees`, the fees of the transactions behind every hop of every path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hop_fees: Option<Vec<Vec<HopFees<'a>>>>,
+}
+
+/// One line of `--format ndjson` output: a single path between a pair of input addresses, with
+/// the edges it uses. Emitted as soon as the search for its pair is done.
+#[derive(Serialize)]
+pub struct PathLine<'a> {
+    pub address1: &'a str,
+    pub address2: &'a str,
+    /// `None` on the one line of a pair without any path, so that every pair shows up
+    pub path: Option<&'a [String]>,
+    /// With `--temporal`, the block time of every hop
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hop_times: Option<Vec<i64>>,
+    /// With `--timeline`, when the transactions behind every hop took place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hop_windows: Option<Vec<HopWindow<'a>>>,
+    /// With `--fees`, the fees of the transactions behind every hop
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hop_fees: Option<Vec<HopFees<'a>>>,
+    pub path_edges: Vec<PathEdge<'a>>,
+}
+
+/// One line of `--cycles --format ndjson` output.
+#[derive(Serialize)]
+pub struct CycleLine<'a> {
+    pub cycle: &'a [String],
+    pub cycle_edges: Vec<PathEdge<'a>>,
+}
+
+/// The `--cycles --format json` result document.
+#[derive(Serialize)]
+pub struct CycleResult<'a> {
+    pub addresses: &'a [String],
+    pub node_count: usize,
+    /// Each cycle starts and ends at the same query address
+    pub cycles: &'a [Vec<String>],
+    /// Every edge used by at least one cycle, with its transaction count
+    pub cycle_edges: Vec<PathEdge<'a>>,
+    /// Names of the labelled addresses that appear on a cycle
+    pub labels: BTreeMap<&'a str, &'a str>,
+    /// Nodes kept out of the search by `--max-node-degree`
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub excluded_hubs: &'a [String],
+}
+
+/// One line of `watch --format json` output: a new connection between two watched addresses, or
+/// a shorter one than was known.
+#[derive(Serialize)]
+pub struct WatchAlert<'a> {
+    pub address1: &'a str,
+    pub address2: &'a str,
+    /// The transaction whose edges completed the path
+    pub signature: &'a str,
+    pub path: &'a [String],
+    pub path_edges: Vec<PathEdge<'a>>,
+}
+
+/// The `--common --format json` result document.
+#[derive(Serialize)]
+pub struct CommonResult<'a> {
+    pub addresses: &'a [String],
+    pub directed: bool,
+    pub node_count: usize,
+    pub counterparties: &'a [Counterparty],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hubs: Option<&'a [NodeDegree]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degree_distribution: Option<&'a DegreeDistribution>,
+}
+
+/// Where one input address sits among the components of the graph.
+#[derive(Serialize)]
+pub struct AddressComponent<'a> {
+    pub address: &'a str,
+    /// Index of its component, largest first; `None` if the address isn't in the graph
+    pub component: Option<usize>,
+    /// The number of addresses in that component
+    pub size: usize,
+}
+
+/// Whether a pair of input addresses share a component, which a path between them needs. This is
+/// also one line of `--components --format ndjson` output.
+#[derive(Serialize)]
+pub struct ComponentPair<'a> {
+    pub address1: &'a str,
+    pub address2: &'a str,
+    pub component1: Option<usize>,
+    pub component2: Option<usize>,
+    pub same_component: bool,
+}
+
+/// The `--components --format json` result document.
+#[derive(Serialize)]
+pub struct ComponentsResult<'a> {
+    pub addresses: &'a [String],
+    pub directed: bool,
+    pub node_count: usize,
+    /// The size of every component, largest first
+    pub component_sizes: Vec<usize>,
+    pub memberships: Vec<AddressComponent<'a>>,
+    pub pairs: Vec<ComponentPair<'a>>,
+}
+
+/// The value that moved between one pair of input addresses.
+#[derive(Serialize)]
+pub struct PairFlows<'a> {
+    pub address1: &'a str,
+    pub address2: &'a str,
+    pub flows: Vec<Flow>,
+}
+
+/// The `--flow --format json` result document.
+#[derive(Serialize)]
+pub struct FlowResult<'a> {
+    pub addresses: &'a [String],
+    pub node_count: usize,
+    pub pairs: Vec<PairFlows<'a>>,
+}
+
+/// The `neighbors --format json` result document.
+#[derive(Serialize)]
+pub struct NeighborsResult<'a> {
+    pub address: &'a str,
+    pub directed: bool,
+    pub neighbors: &'a [Neighbor],
+    /// The addresses at each hop distance, when `--degree` is above 1
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rings: Vec<Ring<'a>>,
+}
+
+/// The addresses exactly `degree` hops away from the queried address.
+#[derive(Serialize)]
+pub struct Ring<'a> {
+    pub degree: usize,
+    pub count: usize,
+    pub addresses: &'a [String],
+}
+
+/// Version of the [`GraphDocument`] format, bumped whenever a change would break reading older files.
+pub const GRAPH_FORMAT_VERSION: u32 = 1;
+
+/// The saved-graph format written by `--save-graph` and `export --format json`: every node and edge of the graph.
+#[derive(Serialize)]
+pub struct GraphDocument<'a> {
+    pub version: u32,
+    pub directed: bool,
+    pub nodes: Vec<&'a str>,
+    pub edges: Vec<PathEdge<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct PathEdge<'a> {
+    pub source: &'a str,
+    pub target: &'a str,
+    #[serde(flatten)]
+    pub edge: &'a Edge,
+}
+
+/// The owned counterpart of [`GraphDocument`], read back by [`load_graph`].
+#[derive(Deserialize)]
+struct SavedGraph {
+    version: u32,
+    directed: bool,
+    nodes: Vec<String>,
+    edges: Vec<SavedEdge>,
+}
+
+#[derive(Deserialize)]
+struct SavedEdge {
+    source: String,
+    target: String,
+    #[serde(flatten)]
+    edge: Edge,
+}
+
+/// The distinct edges traversed by `paths`, in the order they are first used.
+pub fn path_edges<'a>(graph: &'a Graph, paths: &'a [Vec<String>]) -> Vec<PathEdge<'a>> {
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for path in paths {
+        for hop in path.windows(2) {
+            if let Some(edge) = graph.get(&hop[0]).and_then(|n| n.get(&hop[1])) {
+                if seen.insert((&hop[0], &hop[1])) {
+                    edges.push(PathEdge { source: &hop[0], target: &hop[1], edge });
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// The span of time over which the two addresses of one hop interacted.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HopWindow<'a> {
+    pub source: &'a str,
+    pub target: &'a str,
+    /// Transactions behind the hop, including those without a block time
+    pub count: u32,
+    /// Block times of the first and last of them that had one
+    pub first_time: Option<i64>,
+    pub last_time: Option<i64>,
+}
+
+/// The active window of every hop along `path`. A hop missing from the graph is left out.
+pub fn hop_windows<'a>(graph: &'a Graph, path: &'a [String]) -> Vec<HopWindow<'a>> {
+    path.windows(2)
+        .filter_map(|hop| {
+            let edge = graph.get(&hop[0])?.get(&hop[1])?;
+            let window = edge.active_window();
+            Some(HopWindow {
+                source: &hop[0],
+                target: &hop[1],
+                count: edge.count,
+                first_time: window.map(|(first, _)| first),
+                last_time: window.map(|(_, last)| last),
+            })
+        })
+        .collect()
+}
+
+/// The fee paid by one transaction behind a hop.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TransactionFee<'a> {
+    pub signature: &'a str,
+    /// `None` when the fee isn't known, such as for a transaction missing from the cache
+    pub lamports: Option<u64>,
+}
+
+/// The fees paid by the transactions recorded on one hop.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HopFees<'a> {
+    pub source: &'a str,
+    pub target: &'a str,
+    /// Transactions behind the hop, of which only the first [`MAX_EDGE_SIGNATURES`](crate::MAX_EDGE_SIGNATURES) are recorded
+    pub count: u32,
+    pub transactions: Vec<TransactionFee<'a>>,
+    /// The known fees, added up
+    pub lamports: u64,
+}
+
+/// The fees of the transactions recorded on every hop along `path`, as `fee` tells them from a
+/// signature. A hop missing from the graph is left out.
+pub fn hop_fees<'a>(graph: &'a Graph, path: &'a [String], fee: impl Fn(&str) -> Option<u64>) -> Vec<HopFees<'a>> {
+    path.windows(2)
+        .filter_map(|hop| {
+            let edge = graph.get(&hop[0])?.get(&hop[1])?;
+            let transactions: Vec<TransactionFee> = edge.signatures.iter()
+                .map(|signature| TransactionFee { signature, lamports: fee(signature) })
+                .collect();
+            let lamports = transactions.iter().filter_map(|transaction| transaction.lamports).sum();
+            Some(HopFees { source: &hop[0], target: &hop[1], count: edge.count, transactions, lamports })
+        })
+        .collect()
+}
+
+/// The part of `graph` that `paths` run through: their addresses, and the edges of their hops with
+/// everything recorded on them. An undirected edge keeps both of its stored directions.
+pub fn path_subgraph(graph: &Graph, paths: &[Vec<String>], directed: bool) -> Graph {
+    let mut subgraph = Graph::new();
+    for path in paths {
+        for address in path {
+            subgraph.entry(address.clone()).or_default();
+        }
+        for hop in path.windows(2) {
+            let Some(edge) = graph.get(&hop[0]).and_then(|n| n.get(&hop[1])) else {
+                continue;
+            };
+            subgraph.entry(hop[0].clone()).or_default().insert(hop[1].clone(), edge.clone());
+            if !directed {
+                subgraph.entry(hop[1].clone()).or_default().insert(hop[0].clone(), edge.clone());
+            }
+        }
+    }
+    subgraph
+}
+
+/// The names of the labelled addresses that appear on any of `paths`.
+pub fn path_labels<'a>(paths: &'a [Vec<String>], labels: &'a Labels) -> BTreeMap<&'a str, &'a str> {
+    paths.iter().flatten()
+        .filter_map(|address| labels.get(address).map(|name| (address.as_str(), name)))
+        .collect()
+}
+
+/// Abbreviates a base58 address to its first and last four characters.
+pub fn short_address(address: &str) -> String {
+    if address.len() <= 8 {
+        return address.to_string();
+    }
+    format!("{}..{}", &address[..4], &address[address.len() - 4..])
+}
+
+pub fn lamports_to_sol(lamports: u64) -> f64 {
+    lamports as f64 / LAMPORTS_PER_SOL as f64
+}
+
+/// The kind of `address`: what its transactions showed, or else a guess from its label, since
+/// the built-in labels name the common programs and sysvars.
+fn node_kind(kinds: &NodeKinds, labels: &Labels, address: &str) -> NodeKind {
+    if let Some(kind) = kinds.get(address) {
+        return *kind;
+    }
+    let named_program = labels.get(address).is_some_and(|name| name.contains("Program") || name.contains("Sysvar"));
+    if named_program || address.starts_with("Sysvar") {
+        NodeKind::Program
+    } else {
+        NodeKind::Unknown
+    }
+}
+
+/// The DOT shape and fill color for each kind of node.
+fn kind_style(kind: NodeKind) -> Option<&'static str> {
+    match kind {
+        NodeKind::Wallet => Some("style=filled, fillcolor=lightblue"),
+        NodeKind::TokenAccount => Some("shape=note, style=filled, fillcolor=palegreen"),
+        NodeKind::Mint => Some("shape=hexagon, style=filled, fillcolor=plum"),
+        NodeKind::Program => Some("shape=box, style=filled, fillcolor=lightgrey"),
+        NodeKind::Unknown => None,
+    }
+}
+
+/// Renders the graph as DOT. Nodes are shaped and colored by kind (see [`NodeKind`]), query
+/// addresses are gold double circles, nodes on a discovered path are outlined in red, and
+/// labelled addresses show their name above the abbreviated address.
+pub fn graph_to_dot(graph: &Graph, directed: bool, queries: &[&str], paths: &[Vec<String>], labels: &Labels, kinds: &NodeKinds) -> String {
+    let on_path: HashSet<&str> = paths.iter().flatten().map(String::as_str).collect();
+    let mut nodes: Vec<&String> = graph.keys().collect();
+    nodes.sort();
+
+    let (kind, connector) = if directed { ("digraph", "->") } else { ("graph", "--") };
+    let mut dot = format!("{} solconnect {{\n    node [shape=ellipse, fontsize=10];\n", kind);
+    for node in &nodes {
+        let mut attributes = Vec::new();
+        if let Some(name) = labels.get(node) {
+            attributes.push(format!("label=\"{}\\n{}\"", name.replace('"', "\\\""), short_address(node)));
+        }
+        if queries.contains(&node.as_str()) {
+            attributes.push("shape=doublecircle, style=filled, fillcolor=gold".to_string());
+        } else if let Some(style) = kind_style(node_kind(kinds, labels, node)) {
+            attributes.push(style.to_string());
+        }
+        if on_path.contains(node.as_str()) && !queries.contains(&node.as_str()) {
+            attributes.push("color=red, penwidth=2".to_string());
+        }
+        if !attributes.is_empty() {
+            let _ = writeln!(dot, "    \"{}\" [{}];", node, attributes.join(", "));
+        }
+    }
+    for node in &nodes {
+        // Undirected edges are stored in both directions, so only write them from the smaller endpoint
+        let mut neighbors: Vec<(&String, &Edge)> = graph[*node].iter().filter(|(n, _)| directed || node.as_str() < n.as_str()).collect();
+        neighbors.sort_by(|a, b| a.0.cmp(b.0));
+        for (neighbor, edge) in neighbors {
+            let width = 1.0 + (edge.count as f64).ln();
+            let mut label = edge.count.to_string();
+            if edge.lamports > 0 {
+                let _ = write!(label, " / {} SOL", lamports_to_sol(edge.lamports));
+            }
+            for (mint, amount) in &edge.tokens {
+                let mint_name = labels.get(mint).map(|name| name.replace('"', "\\\"")).unwrap_or_else(|| short_address(mint));
+                let _ = write!(label, " / {} {}", amount, mint_name);
+            }
+            let _ = writeln!(dot, "    \"{}\" {} \"{}\" [label=\"{}\", penwidth={:.2}];", node, connector, neighbor, label, width);
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Lists the whole graph with sorted nodes and edges. Undirected edges appear once, from the smaller endpoint.
+pub fn graph_document(graph: &Graph, directed: bool) -> GraphDocument<'_> {
+    let mut nodes: Vec<&str> = graph.keys().map(String::as_str).collect();
+    nodes.sort_unstable();
+    let mut edges = Vec::new();
+    for node in &nodes {
+        let mut neighbors: Vec<(&String, &Edge)> = graph[*node].iter().filter(|(n, _)| directed || *node < n.as_str()).collect();
+        neighbors.sort_by(|a, b| a.0.cmp(b.0));
+        for (neighbor, edge) in neighbors {
+            edges.push(PathEdge { source: node, target: neighbor, edge });
+        }
+    }
+    GraphDocument { version: GRAPH_FORMAT_VERSION, directed, nodes, edges }
+}
+
+/// Writes the graph to `file` as a [`GraphDocument`].
+pub fn save_graph(file: &Path, graph: &Graph, directed: bool) -> Result<(), SolConnectError> {
+    let document = serde_json::to_vec_pretty(&graph_document(graph, directed))?;
+    fs::write(file, document)?;
+    Ok(())
+}
+
+/// Whether `file` is a SQLite database rather than a saved graph document.
+fn is_database(contents: &[u8]) -> bool {
+    contents.starts_with(b"SQLite format 3\0")
+}
+
+/// Reads a graph written by [`save_graph`], returning it with whether it is directed. With the
+/// `sqlite` feature, `file` may also be a crawl database, which yields the graph last stored in it.
+pub fn load_graph(file: &Path) -> Result<(Graph, bool), SolConnectError> {
+    let contents = fs::read(file)?;
+    if is_database(&contents) {
+        return load_database_graph(file);
+    }
+    let saved: SavedGraph = serde_json::from_slice(&contents)?;
+    if saved.version != GRAPH_FORMAT_VERSION {
+        return Err(SolConnectError::Parse(format!(
+            "{} has graph format version {}, but this build reads version {}", file.display(), saved.version, GRAPH_FORMAT_VERSION
+        )));
+    }
+
+    let mut graph = Graph::new();
+    for node in saved.nodes {
+        graph.entry(node).or_default();
+    }
+    for SavedEdge { source, target, edge } in &saved.edges {
+        add_edge(&mut graph, source, target, edge, saved.directed);
+    }
+    Ok((graph, saved.directed))
+}
+
+#[cfg(feature = "sqlite")]
+fn load_database_graph(file: &Path) -> Result<(Graph, bool), SolConnectError> {
+    crate::store::SqliteStore::open(file)
+        .and_then(|store| store.load_graph())
+        .map_err(|e| SolConnectError::Parse(format!("couldn't read {}: {}", file.display(), e)))?
+        .ok_or_else(|| SolConnectError::Parse(format!("{} holds no graph yet; run an analysis with --db first", file.display())))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn load_database_graph(file: &Path) -> Result<(Graph, bool), SolConnectError> {
+    Err(SolConnectError::Config(format!("{} is a SQLite database, but this build lacks the sqlite feature", file.display())))
+}
+
+/// Escapes the characters that can't appear verbatim in an XML attribute value.
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders the graph as GEXF 1.3 for Gephi. Every node carries a `role` attribute (`query`, `path`
+/// or `other`) to color by, and its degree; labelled nodes use their name as the node label.
+/// Edges are weighted by transaction count and carry the SOL moved, in lamports.
+pub fn graph_to_gexf(graph: &Graph, directed: bool, queries: &[&str], paths: &[Vec<String>], labels: &Labels) -> String {
+    let on_path: HashSet<&str> = paths.iter().flatten().map(String::as_str).collect();
+    let node_degrees = degrees(graph, directed);
+    let document = graph_document(graph, directed);
+
+    let mut gexf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+    gexf.push_str("  <meta>\n    <creator>solconnect</creator>\n  </meta>\n");
+    let _ = writeln!(gexf, "  <graph defaultedgetype=\"{}\" mode=\"static\">", if directed { "directed" } else { "undirected" });
+    gexf.push_str("    <attributes class=\"node\">\n      <attribute id=\"0\" title=\"role\" type=\"string\"/>\n      <attribute id=\"1\" title=\"degree\" type=\"integer\"/>\n    </attributes>\n");
+    gexf.push_str("    <attributes class=\"edge\">\n      <attribute id=\"0\" title=\"lamports\" type=\"long\"/>\n    </attributes>\n");
+
+    gexf.push_str("    <nodes>\n");
+    for node in &document.nodes {
+        let role = if queries.contains(node) {
+            "query"
+        } else if on_path.contains(node) {
+            "path"
+        } else {
+            "other"
+        };
+        let label = labels.get(node).map(str::to_string).unwrap_or_else(|| short_address(node));
+        let _ = writeln!(
+            gexf,
+            "      <node id=\"{}\" label=\"{}\"><attvalues><attvalue for=\"0\" value=\"{}\"/><attvalue for=\"1\" value=\"{}\"/></attvalues></node>",
+            xml_escape(node), xml_escape(&label), role, node_degrees.get(node).copied().unwrap_or_default()
+        );
+    }
+    gexf.push_str("    </nodes>\n    <edges>\n");
+    for (id, edge) in document.edges.iter().enumerate() {
+        let _ = writeln!(
+            gexf,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" weight=\"{}\"><attvalues><attvalue for=\"0\" value=\"{}\"/></attvalues></edge>",
+            id, xml_escape(edge.source), xml_escape(edge.target), edge.edge.count, edge.edge.lamports
+        );
+    }
+    gexf.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    gexf
+}
+
+/// Renders the graph as GraphML. Nodes are identified by address and carry a `label` (their name
+/// when labelled) and a `role` (`query`, `path` or `other`); edges carry their transaction count as
+/// `weight` and the SOL moved as `lamports`.
+pub fn graph_to_graphml(graph: &Graph, directed: bool, queries: &[&str], paths: &[Vec<String>], labels: &Labels) -> String {
+    let on_path: HashSet<&str> = paths.iter().flatten().map(String::as_str).collect();
+    let document = graph_document(graph, directed);
+
+    let mut graphml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    graphml.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    graphml.push_str("  <key id=\"role\" for=\"node\" attr.name=\"role\" attr.type=\"string\"/>\n");
+    graphml.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n");
+    graphml.push_str("  <key id=\"lamports\" for=\"edge\" attr.name=\"lamports\" attr.type=\"long\"/>\n");
+    let _ = writeln!(graphml, "  <graph id=\"solconnect\" edgedefault=\"{}\">", if directed { "directed" } else { "undirected" });
+    for node in &document.nodes {
+        let role = if queries.contains(node) {
+            "query"
+        } else if on_path.contains(node) {
+            "path"
+        } else {
+            "other"
+        };
+        let label = labels.get(node).map(str::to_string).unwrap_or_else(|| short_address(node));
+        let _ = writeln!(
+            graphml,
+            "    <node id=\"{}\"><data key=\"label\">{}</data><data key=\"role\">{}</data></node>",
+            xml_escape(node), xml_escape(&label), role
+        );
+    }
+    for edge in &document.edges {
+        let _ = writeln!(
+            graphml,
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data><data key=\"lamports\">{}</data></edge>",
+            xml_escape(edge.source), xml_escape(edge.target), edge.edge.count, edge.edge.lamports
+        );
+    }
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
+/// The node-link document of `--sankey`, in the shape d3-sankey takes: links refer to nodes by
+/// their index. Plotly's `sankey` trace takes the same lists once split into its arrays.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SankeyDocument<'a> {
+    /// What the link values measure: `SOL`, or the mint of the token
+    pub unit: &'a str,
+    pub nodes: Vec<SankeyNode<'a>>,
+    pub links: Vec<SankeyLink>,
+    /// Hops left out because they would have closed a cycle, which Sankey layouts can't draw
+    #[serde(skip)]
+    pub circular: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SankeyNode<'a> {
+    /// The label of the address, or its abbreviation
+    pub name: String,
+    pub address: &'a str,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SankeyLink {
+    pub source: usize,
+    pub target: usize,
+    pub value: f64,
+}
+
+/// The flows along the hops of `paths` in a directed graph as a Sankey diagram: one link per hop
+/// that moved SOL, or the token of `mint` when given, worth the amount moved. Hops are taken in
+/// the order the paths first use them, so with ranked paths a hop that would close a cycle is
+/// the one on the weaker path, and it is left out.
+pub fn sankey_document<'a>(graph: &'a Graph, paths: &'a [Vec<String>], labels: &Labels, mint: Option<&'a str>) -> SankeyDocument<'a> {
+    let mut document = SankeyDocument { unit: mint.unwrap_or("SOL"), nodes: Vec::new(), links: Vec::new(), circular: 0 };
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    // Node index -> the nodes its links lead to
+    let mut downstream: Vec<Vec<usize>> = Vec::new();
+    for hop in path_edges(graph, paths) {
+        let value = match mint {
+            Some(mint) => hop.edge.tokens.get(mint).copied().unwrap_or_default(),
+            None => lamports_to_sol(hop.edge.lamports),
+        };
+        if value <= 0.0 {
+            continue;
+        }
+        let mut node = |address: &'a str| {
+            *index.entry(address).or_insert_with(|| {
+                let name = labels.get(address).map(str::to_string).unwrap_or_else(|| short_address(address));
+                document.nodes.push(SankeyNode { name, address });
+                downstream.push(Vec::new());
+                document.nodes.len() - 1
+            })
+        };
+        let (source, target) = (node(hop.source), node(hop.target));
+        if leads_to(&downstream, target, source) {
+            document.circular += 1;
+            continue;
+        }
+        downstream[source].push(target);
+        document.links.push(SankeyLink { source, target, value });
+    }
+    document
+}
+
+/// Whether the links of `downstream` lead from `from` to `to`.
+fn leads_to(downstream: &[Vec<usize>], from: usize, to: usize) -> bool {
+    let mut seen = vec![false; downstream.len()];
+    let mut stack = vec![from];
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if !std::mem::replace(&mut seen[node], true) {
+            stack.extend(&downstream[node]);
+        }
+    }
+    false
+}
+
+/// Graphs with more nodes than this are written as sparse matrices unless dense is asked for.
+pub const DENSE_MATRIX_MAX_NODES: usize = 2000;
+
+/// How [`write_matrix`] lays out the adjacency matrix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatrixFormat {
+    /// One CSV row of transaction counts per node, for `numpy.loadtxt(file, delimiter=",")`
+    Dense,
+    /// One `row,col,weight,lamports` CSV line per edge, for `scipy.sparse.coo_matrix` or pandas
+    Sparse,
+}
+
+impl MatrixFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatrixFormat::Dense => "dense",
+            MatrixFormat::Sparse => "sparse",
+        }
+    }
+
+    /// Dense for small graphs, sparse once a dense matrix would mostly be zeros.
+    pub fn for_graph(graph: &Graph) -> Self {
+        if graph.len() > DENSE_MATRIX_MAX_NODES {
+            MatrixFormat::Sparse
+        } else {
+            MatrixFormat::Dense
+        }
+    }
+}
+
+impl FromStr for MatrixFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "dense" => Ok(MatrixFormat::Dense),
+            "sparse" => Ok(MatrixFormat::Sparse),
+            other => Err(format!("unknown matrix format: {}", other)),
+        }
+    }
+}
+
+/// The adjacency matrix of the graph, with rows and columns in address order. Entries are
+/// transaction counts; an undirected graph gives a symmetric matrix.
+pub fn graph_to_matrix(graph: &Graph, format: MatrixFormat) -> String {
+    let nodes = graph_document(graph, true).nodes;
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+    let mut matrix = String::new();
+    match format {
+        MatrixFormat::Dense => {
+            for node in &nodes {
+                let mut row = vec![0u32; nodes.len()];
+                for (neighbor, edge) in &graph[*node] {
+                    row[index[neighbor.as_str()]] = edge.count;
+                }
+                let row: Vec<String> = row.iter().map(u32::to_string).collect();
+                let _ = writeln!(matrix, "{}", row.join(","));
+            }
+        }
+        MatrixFormat::Sparse => {
+            matrix.push_str("row,col,weight,lamports\n");
+            for edge in graph_document(graph, true).edges {
+                let _ = writeln!(matrix, "{},{},{},{}", index[edge.source], index[edge.target], edge.edge.count, edge.edge.lamports);
+            }
+        }
+    }
+    matrix
+}
+
+/// The `index,address` CSV mapping matrix rows and columns to addresses.
+pub fn matrix_index(graph: &Graph) -> String {
+    let mut csv = String::from("index,address\n");
+    for (i, node) in graph_document(graph, true).nodes.iter().enumerate() {
+        let _ = writeln!(csv, "{},{}", i, node);
+    }
+    csv
+}
+
+/// Where [`write_matrix`] puts the index of `file`: next to it, as `<stem>.nodes.csv`.
+pub fn matrix_index_file(file: &Path) -> PathBuf {
+    let stem = file.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "matrix".to_string());
+    file.with_file_name(format!("{}.nodes.csv", stem))
+}
+
+/// Writes the adjacency matrix to `file` and its index to [`matrix_index_file`], returning the latter.
+pub fn write_matrix(file: &Path, graph: &Graph, format: MatrixFormat) -> Result<PathBuf, SolConnectError> {
+    let index_file = matrix_index_file(file);
+    fs::write(file, graph_to_matrix(graph, format))?;
+    fs::write(&index_file, matrix_index(graph))?;
+    Ok(index_file)
+}
+
+/// The graph as a CSV edge list with a `source,target,weight,lamports` header, where the weight is
+/// the transaction count. Undirected edges appear once, from the smaller endpoint.
+pub fn graph_to_csv(graph: &Graph, directed: bool) -> String {
+    let mut csv = String::from("source,target,weight,lamports\n");
+    for edge in graph_document(graph, directed).edges {
+        let _ = writeln!(csv, "{},{},{},{}", edge.source, edge.target, edge.edge.count, edge.edge.lamports);
+    }
+    csv
+}
+
+/// One CSV row per path: its endpoints, its number of hops, then every address along it in
+/// `node1`, `node2`, ... columns. Shorter paths leave the trailing columns empty.
+pub fn paths_to_csv(paths: &[Vec<String>]) -> String {
+    let width = paths.iter().map(Vec::len).max().unwrap_or(0);
+    let mut csv = String::from("source,target,hops");
+    for n in 1..=width {
+        let _ = write!(csv, ",node{}", n);
+    }
+    csv.push('\n');
+    for path in paths {
+        let (Some(source), Some(target)) = (path.first(), path.last()) else {
+            continue;
+        };
+        let _ = write!(csv, "{},{},{}", source, target, path.len() - 1);
+        for n in 0..width {
+            let _ = write!(csv, ",{}", path.get(n).map(String::as_str).unwrap_or_default());
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Writes `graph_to_dot` output to `file`.
+pub fn write_dot(
+    file: &Path,
+    graph: &Graph,
+    directed: bool,
+    queries: &[&str],
+    paths: &[Vec<String>],
+    labels: &Labels,
+    kinds: &NodeKinds,
+) -> std::io::Result<()> {
+    fs::write(file, graph_to_dot(graph, directed, queries, paths, labels, kinds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_styles_nodes_by_kind_and_role() {
+        let mut graph = Graph::new();
+        for (a, b) in [("Query", "Wallet"), ("Wallet", "TokenProgram"), ("Wallet", "SysvarRent111111111111111111111111111111111"), ("Wallet", "Other")] {
+            add_edge(&mut graph, a, b, &Edge { count: 1, ..Edge::default() }, false);
+        }
+        let kinds = NodeKinds::from([
+            ("Wallet".to_string(), NodeKind::Wallet),
+            ("TokenProgram".to_string(), NodeKind::Program),
+            ("Query".to_string(), NodeKind::Wallet),
+        ]);
+        let paths = [vec!["Query".to_string(), "Wallet".to_string()]];
+
+        let dot = graph_to_dot(&graph, false, &["Query"], &paths, &Labels::default(), &kinds);
+        assert!(dot.contains(r#""Query" [shape=doublecircle, style=filled, fillcolor=gold];"#));
+        assert!(dot.contains(r#""Wallet" [style=filled, fillcolor=lightblue, color=red, penwidth=2];"#));
+        assert!(dot.contains(r#""TokenProgram" [shape=box, style=filled, fillcolor=lightgrey];"#));
+        // Sysvars are recognized without having been seen as a program
+        assert!(dot.contains(r#""SysvarRent111111111111111111111111111111111" [shape=box"#));
+        assert!(!dot.contains(r#""Other" ["#));
+    }
+
+    #[test]
+    fn path_subgraphs_keep_only_the_hops_of_the_paths() {
+        let path = |nodes: &[&str]| nodes.iter().map(|n| n.to_string()).collect::<Vec<String>>();
+        let mut graph = Graph::new();
+        for (a, b) in [("A", "B"), ("B", "C"), ("A", "X"), ("X", "Y"), ("B", "Y")] {
+            add_edge(&mut graph, a, b, &Edge { count: 1, ..Edge::default() }, false);
+        }
+        let paths = [path(&["A", "B", "C"]), path(&["A", "X", "Y"])];
+
+        let subgraph = path_subgraph(&graph, &paths, false);
+        let mut nodes: Vec<&str> = subgraph.keys().map(String::as_str).collect();
+        nodes.sort_unstable();
+        assert_eq!(nodes, ["A", "B", "C", "X", "Y"]);
+        assert!(subgraph["C"].contains_key("B"));
+        assert!(!subgraph["B"].contains_key("Y"));
+        assert_eq!(graph_document(&subgraph, false).edges.len(), 4);
+
+        let mut directed = Graph::new();
+        add_edge(&mut directed, "A", "B", &Edge { count: 3, ..Edge::default() }, true);
+        add_edge(&mut directed, "B", "A", &Edge { count: 1, ..Edge::default() }, true);
+        let subgraph = path_subgraph(&directed, &[path(&["A", "B"])], true);
+        assert_eq!(subgraph["A"]["B"].count, 3);
+        assert!(subgraph["B"].is_empty());
+    }
+
+    #[test]
+    fn hop_windows_span_the_first_and_last_transaction() {
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "A", "B", &Edge { count: 3, times: vec![100, 250, 900], ..Edge::default() }, true);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, true);
+        let path = ["A".to_string(), "B".to_string(), "C".to_string()];
+
+        assert_eq!(hop_windows(&graph, &path), vec![
+            HopWindow { source: "A", target: "B", count: 3, first_time: Some(100), last_time: Some(900) },
+            HopWindow { source: "B", target: "C", count: 1, first_time: None, last_time: None },
+        ]);
+        assert!(hop_windows(&graph, &path[1..].iter().rev().cloned().collect::<Vec<_>>()).is_empty());
+    }
+
+    #[test]
+    fn hop_fees_add_up_the_known_fees_of_each_hop() {
+        let mut graph = Graph::new();
+        let signatures = vec!["sig1".to_string(), "sig2".to_string(), "sig3".to_string()];
+        add_edge(&mut graph, "A", "B", &Edge { count: 12, signatures, ..Edge::default() }, true);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, true);
+        let path = ["A".to_string(), "B".to_string(), "C".to_string()];
+        let fees = HashMap::from([("sig1", 5000), ("sig3", 10_000)]);
+
+        let hops = hop_fees(&graph, &path, |signature| fees.get(signature).copied());
+        assert_eq!(hops.len(), 2);
+        assert_eq!((hops[0].count, hops[0].lamports), (12, 15_000));
+        assert_eq!(hops[0].transactions[1], TransactionFee { signature: "sig2", lamports: None });
+        assert_eq!((hops[1].lamports, hops[1].transactions.len()), (0, 0));
+    }
+
+    #[test]
+    fn sankey_links_carry_the_amounts_and_skip_cycles() {
+        let sol = LAMPORTS_PER_SOL;
+        let mut graph = Graph::new();
+        let tokens = BTreeMap::from([("USDC".to_string(), 25.0)]);
+        add_edge(&mut graph, "A", "B", &Edge { count: 1, lamports: 3 * sol, tokens: tokens.clone(), ..Edge::default() }, true);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, lamports: sol / 2, ..Edge::default() }, true);
+        add_edge(&mut graph, "C", "B", &Edge { count: 1, lamports: sol, ..Edge::default() }, true);
+        add_edge(&mut graph, "C", "D", &Edge { count: 1, tokens, ..Edge::default() }, true);
+        add_edge(&mut graph, "A", "C", &Edge { count: 1, lamports: 2 * sol, ..Edge::default() }, true);
+        let path = |nodes: &[&str]| nodes.iter().map(|n| n.to_string()).collect::<Vec<String>>();
+        let paths = [path(&["A", "B", "C", "D"]), path(&["A", "C", "B"])];
+
+        let sankey = sankey_document(&graph, &paths, &Labels::default(), None);
+        let links: Vec<(&str, &str, f64)> = sankey.links.iter()
+            .map(|link| (sankey.nodes[link.source].address, sankey.nodes[link.target].address, link.value))
+            .collect();
+        assert_eq!(links, vec![("A", "B", 3.0), ("B", "C", 0.5), ("A", "C", 2.0)]);
+        assert_eq!((sankey.unit, sankey.circular), ("SOL", 1));
+
+        let sankey = sankey_document(&graph, &paths, &Labels::default(), Some("USDC"));
+        assert_eq!(sankey.links, vec![SankeyLink { source: 0, target: 1, value: 25.0 }, SankeyLink { source: 2, target: 3, value: 25.0 }]);
+        let json = serde_json::to_value(&sankey).unwrap();
+        assert_eq!(json["nodes"][3], serde_json::json!({ "name": "D", "address": "D" }));
+        assert!(json.get("circular").is_none());
+    }
+
+    #[test]
+    fn graphml_has_node_ids_and_weighted_edges() {
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "A", "B", &Edge { count: 2, lamports: 7, ..Edge::default() }, true);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, true);
+
+        let graphml = graph_to_graphml(&graph, true, &["A"], &[], &Labels::default());
+        assert!(graphml.contains(r#"<graph id="solconnect" edgedefault="directed">"#));
+        assert!(graphml.contains(r#"<node id="A"><data key="label">A</data><data key="role">query</data></node>"#));
+        assert!(graphml.contains(r#"<edge source="A" target="B"><data key="weight">2</data><data key="lamports">7</data></edge>"#));
+        assert_eq!(graphml.matches("<node ").count(), 3);
+        assert_eq!(graphml.matches("<edge ").count(), 2);
+    }
+
+    #[test]
+    fn adjacency_matrices_share_one_index() {
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "A", "B", &Edge { count: 2, lamports: 7, ..Edge::default() }, false);
+        add_edge(&mut graph, "C", "B", &Edge { count: 1, ..Edge::default() }, false);
+
+        assert_eq!(graph_to_matrix(&graph, MatrixFormat::Dense), "0,2,0\n2,0,1\n0,1,0\n");
+        assert_eq!(graph_to_matrix(&graph, MatrixFormat::Sparse), "row,col,weight,lamports\n0,1,2,7\n1,0,2,7\n1,2,1,0\n2,1,1,0\n");
+        assert_eq!(matrix_index(&graph), "index,address\n0,A\n1,B\n2,C\n");
+        assert_eq!(matrix_index_file(Path::new("out/graph.csv")), Path::new("out/graph.nodes.csv"));
+        assert_eq!(MatrixFormat::for_graph(&graph), MatrixFormat::Dense);
+    }
+
+    #[test]
+    fn gexf_marks_query_and_path_nodes() {
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "A", "B", &Edge { count: 2, ..Edge::default() }, false);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, false);
+        add_edge(&mut graph, "B", "D", &Edge { count: 1, ..Edge::default() }, false);
+        let labels = Labels::builtin().with_domains([("D".to_string(), "a<b>.sol".to_string())]);
+
+        let gexf = graph_to_gexf(&graph, false, &["A", "C"], &[vec!["A".to_string(), "B".to_string(), "C".to_string()]], &labels);
+        assert!(gexf.contains(r#"<graph defaultedgetype="undirected""#));
+        assert!(gexf.contains(r#"<node id="A" label="A"><attvalues><attvalue for="0" value="query"/><attvalue for="1" value="1"/>"#));
+        assert!(gexf.contains(r#"<node id="B" label="B"><attvalues><attvalue for="0" value="path"/><attvalue for="1" value="3"/>"#));
+        assert!(gexf.contains(r#"label="a&lt;b&gt;.sol"><attvalues><attvalue for="0" value="other"/>"#));
+        assert!(gexf.contains(r#"<edge id="0" source="A" target="B" weight="2">"#));
+        assert_eq!(gexf.matches("<edge ").count(), 3);
+    }
+
+    #[test]
+    fn csv_lists_each_edge_once_and_pads_short_paths() {
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "B", "A", &Edge { count: 3, lamports: 10, ..Edge::default() }, false);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, false);
+        assert_eq!(graph_to_csv(&graph, false), "source,target,weight,lamports\nA,B,3,10\nB,C,1,0\n");
+
+        let paths = vec![vec!["A".to_string(), "B".to_string(), "C".to_string()], vec!["A".to_string(), "C".to_string()]];
+        assert_eq!(paths_to_csv(&paths), "source,target,hops,node1,node2,node3\nA,C,2,A,B,C\nA,C,1,A,C,\n");
+    }
+
+    #[test]
+    fn saved_graphs_load_back_unchanged() {
+        let mut graph = Graph::new();
+        let edge = Edge {
+            count: 2,
+            lamports: 1_500,
+            tokens: BTreeMap::from([("USDC".to_string(), 2.5)]),
+            signatures: vec!["sig1".to_string(), "sig2".to_string()],
+            times: vec![1_700_000_000, 1_700_000_060],
+            swaps: std::collections::BTreeSet::from(["Dex".to_string()]),
+            nfts: std::collections::BTreeSet::from(["Nft".to_string()]),
+            readonly: 1,
+        };
+        add_edge(&mut graph, "A", "B", &edge, false);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, false);
+        graph.entry("Lonely".to_string()).or_default();
+
+        let file = std::env::temp_dir().join(format!("solconnect-graph-{}.json", std::process::id()));
+        save_graph(&file, &graph, false).unwrap();
+        let (loaded, directed) = load_graph(&file).unwrap();
+
+        fs::write(&file, r#"{ "version": 999, "directed": true, "nodes": [], "edges": [] }"#).unwrap();
+        let error = load_graph(&file).unwrap_err();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(loaded, graph);
+        assert!(!directed);
+        assert!(matches!(error, SolConnectError::Parse(_)));
+    }
+}
@@ -0,0 +1,164 @@
+//! How much value moved between two addresses, directly and through intermediaries.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use serde::Serialize;
+
+use crate::export::lamports_to_sol;
+use crate::graph::{Edge, Graph};
+
+/// Residual capacities below this are treated as exhausted, so float rounding can't keep
+/// a path search going forever.
+const EPSILON: f64 = 1e-9;
+
+/// The value of one asset that moved between a pair of addresses, in SOL or in the token's UI
+/// amount. `direct_*` counts transfers between the two addresses themselves; `routed_*` is the
+/// maximum flow through the whole directed graph, which includes the direct transfers and is an
+/// upper bound on what can have reached the other address through intermediaries.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Flow {
+    /// The token mint, or `None` for SOL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mint: Option<String>,
+    pub direct_out: f64,
+    pub direct_in: f64,
+    pub net_direct: f64,
+    pub routed_out: f64,
+    pub routed_in: f64,
+    pub net_routed: f64,
+}
+
+/// Edmonds-Karp maximum flow from `source` to `sink`, with each directed edge's capacity given by
+/// `capacity`. Augmenting paths are found breadth-first over edges with capacity left.
+fn max_flow(graph: &Graph, source: &str, sink: &str, capacity: &dyn Fn(&Edge) -> f64) -> f64 {
+    if source == sink {
+        return 0.0;
+    }
+    let mut residual: HashMap<(&str, &str), f64> = HashMap::new();
+    let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (from, neighbors) in graph {
+        for (to, edge) in neighbors {
+            let amount = capacity(edge);
+            if amount <= EPSILON {
+                continue;
+            }
+            *residual.entry((from, to)).or_default() += amount;
+            residual.entry((to, from)).or_default();
+            adjacent.entry(from).or_default().push(to);
+            adjacent.entry(to).or_default().push(from);
+        }
+    }
+
+    let mut total = 0.0;
+    loop {
+        let mut parents: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            if node == sink {
+                break;
+            }
+            for &next in adjacent.get(node).into_iter().flatten() {
+                if next != source && !parents.contains_key(next) && residual[&(node, next)] > EPSILON {
+                    parents.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        if !parents.contains_key(sink) {
+            return total;
+        }
+
+        let mut bottleneck = f64::INFINITY;
+        let mut node = sink;
+        while let Some(&parent) = parents.get(node) {
+            bottleneck = bottleneck.min(residual[&(parent, node)]);
+            node = parent;
+        }
+        let mut node = sink;
+        while let Some(&parent) = parents.get(node) {
+            *residual.get_mut(&(parent, node)).expect("edge on path") -= bottleneck;
+            *residual.get_mut(&(node, parent)).expect("reverse edge on path") += bottleneck;
+            node = parent;
+        }
+        total += bottleneck;
+    }
+}
+
+fn flow_of(graph: &Graph, a: &str, b: &str, mint: Option<String>, amount: &dyn Fn(&Edge) -> f64) -> Flow {
+    let direct = |from: &str, to: &str| graph.get(from).and_then(|n| n.get(to)).map_or(0.0, amount);
+    let (direct_out, direct_in) = (direct(a, b), direct(b, a));
+    let (routed_out, routed_in) = (max_flow(graph, a, b, amount), max_flow(graph, b, a, amount));
+    Flow {
+        mint,
+        direct_out,
+        direct_in,
+        net_direct: direct_out - direct_in,
+        routed_out,
+        routed_in,
+        net_routed: routed_out - routed_in,
+    }
+}
+
+/// The flow of SOL and of every token mint between `a` and `b` in a directed graph whose edges
+/// point from sender to receiver. SOL comes first, then the mints in address order; a mint is
+/// listed when any edge of the graph moved it.
+pub fn pair_flows(graph: &Graph, a: &str, b: &str) -> Vec<Flow> {
+    let mints: BTreeSet<&str> = graph.values().flat_map(|n| n.values()).flat_map(|e| e.tokens.keys()).map(String::as_str).collect();
+    let mut flows = vec![flow_of(graph, a, b, None, &|edge| lamports_to_sol(edge.lamports))];
+    for mint in mints {
+        let flow = flow_of(graph, a, b, Some(mint.to_string()), &|edge| edge.tokens.get(mint).copied().unwrap_or_default());
+        if flow.routed_out > EPSILON || flow.routed_in > EPSILON {
+            flows.push(flow);
+        }
+    }
+    flows
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use super::*;
+    use crate::graph::add_edge;
+
+    fn sol(graph: &mut Graph, from: &str, to: &str, sol: u64) {
+        add_edge(graph, from, to, &Edge { count: 1, lamports: sol * 1_000_000_000, ..Edge::default() }, true);
+    }
+
+    #[test]
+    fn routed_flow_is_limited_by_each_route_bottleneck() {
+        let mut graph = Graph::new();
+        sol(&mut graph, "A", "B", 1);
+        // A -> X -> B carries at most 2, A -> Y -> Z -> B at most 3
+        sol(&mut graph, "A", "X", 5);
+        sol(&mut graph, "X", "B", 2);
+        sol(&mut graph, "A", "Y", 3);
+        sol(&mut graph, "Y", "Z", 4);
+        sol(&mut graph, "Z", "B", 10);
+        sol(&mut graph, "B", "A", 4);
+
+        let flows = pair_flows(&graph, "A", "B");
+        assert_eq!(flows, vec![Flow {
+            mint: None,
+            direct_out: 1.0,
+            direct_in: 4.0,
+            net_direct: -3.0,
+            routed_out: 6.0,
+            routed_in: 4.0,
+            net_routed: 2.0,
+        }]);
+    }
+
+    #[test]
+    fn token_flows_are_reported_per_mint() {
+        let mut graph = Graph::new();
+        let tokens = |amount: f64| Edge { count: 1, tokens: BTreeMap::from([("USDC".to_string(), amount)]), ..Edge::default() };
+        add_edge(&mut graph, "A", "M", &tokens(50.0), true);
+        add_edge(&mut graph, "M", "B", &tokens(20.0), true);
+        add_edge(&mut graph, "C", "D", &Edge { count: 1, tokens: BTreeMap::from([("BONK".to_string(), 1.0)]), ..Edge::default() }, true);
+
+        let flows = pair_flows(&graph, "A", "B");
+        assert_eq!(flows.len(), 2);
+        assert_eq!(flows[0].routed_out, 0.0);
+        assert_eq!(flows[1].mint.as_deref(), Some("USDC"));
+        assert_eq!((flows[1].direct_out, flows[1].routed_out, flows[1].net_routed), (0.0, 20.0, 20.0));
+    }
+}
@@ -0,0 +1,1550 @@
+//! The connection graph built from fetched transactions.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+use crate::provider::{Transfer, TransferKind};
+
+/// What is known about the link between two addresses.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Edge {
+    /// Number of transactions in which the two addresses appeared together
+    pub count: u32,
+    /// SOL moved along the edge, attributed from balance changes
+    pub lamports: u64,
+    /// SPL token amounts moved along the edge, keyed by mint
+    pub tokens: BTreeMap<String, f64>,
+    /// Signatures of the first transactions that created the edge, at most [`MAX_EDGE_SIGNATURES`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<String>,
+    /// Distinct block times of the transactions behind the edge, in ascending order.
+    /// Transactions without a `blockTime` contribute none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub times: Vec<i64>,
+    /// The DEX programs invoked by the transactions behind the edge. A hop through one is most
+    /// likely a swap, which links the two addresses less directly than a transfer.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub swaps: BTreeSet<String>,
+    /// The mints of the NFTs moved along the edge: tokens with no decimals moved one at a time
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub nfts: BTreeSet<String>,
+    /// Transactions behind the edge in which the second address was only referenced, read-only,
+    /// such as a program or a config account the first one used
+    #[serde(skip_serializing_if = "is_zero")]
+    pub readonly: u32,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+/// The distinct values of two ascending slices, in ascending order.
+fn merge_sorted(left: &[i64], right: &[i64]) -> Vec<i64> {
+    let mut merged: Vec<i64> = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.iter().peekable(), right.iter().peekable());
+    loop {
+        let next = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) if l <= r => left.next(),
+            (_, Some(_)) => right.next(),
+            (Some(_), None) => left.next(),
+            (None, None) => break,
+        };
+        if let Some(&next) = next.filter(|next| merged.last() != Some(next)) {
+            merged.push(next);
+        }
+    }
+    merged
+}
+
+/// How many signatures an edge keeps as evidence. Hub edges can be backed by thousands of
+/// transactions, and a few of them are enough to show why two addresses are linked.
+pub const MAX_EDGE_SIGNATURES: usize = 10;
+
+/// The mint of wrapped SOL, whose token accounts hold SOL as an SPL token.
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Programs and sysvars that nearly every transaction references. Left in, they become hubs
+/// linking every address to every other through edges that say nothing about either.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "11111111111111111111111111111111",
+    "ComputeBudget111111111111111111111111111111",
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+    "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+    "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+    "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo",
+    "AddressLookupTab1e1111111111111111111111111",
+    "Vote111111111111111111111111111111111111111",
+    "Stake11111111111111111111111111111111111111",
+    "SysvarRent111111111111111111111111111111111",
+    "SysvarC1ock11111111111111111111111111111111",
+    "Sysvar1nstructions1111111111111111111111111",
+    "SysvarRecentB1ockHashes11111111111111111111",
+    "SysvarS1otHashes111111111111111111111111111",
+    "SysvarStakeHistory1111111111111111111111111",
+    "SysvarEpochSchedu1e111111111111111111111111",
+];
+
+impl Edge {
+    pub fn merge(&mut self, other: &Edge) {
+        self.count += other.count;
+        self.lamports += other.lamports;
+        for (mint, amount) in &other.tokens {
+            *self.tokens.entry(mint.clone()).or_default() += amount;
+        }
+        for signature in &other.signatures {
+            if self.signatures.len() >= MAX_EDGE_SIGNATURES {
+                break;
+            }
+            if !self.signatures.contains(signature) {
+                self.signatures.push(signature.clone());
+            }
+        }
+        // Both sides are sorted already, and a single transaction brings at most one time
+        match other.times.as_slice() {
+            [] => {}
+            [time] => {
+                if let Err(index) = self.times.binary_search(time) {
+                    self.times.insert(index, *time);
+                }
+            }
+            times => self.times = merge_sorted(&self.times, times),
+        }
+        self.swaps.extend(other.swaps.iter().cloned());
+        self.nfts.extend(other.nfts.iter().cloned());
+        self.readonly += other.readonly;
+    }
+
+    /// The block times of the first and last transactions behind the edge, or `None` if none
+    /// of them had one.
+    pub fn active_window(&self) -> Option<(i64, i64)> {
+        Some((*self.times.first()?, *self.times.last()?))
+    }
+
+    /// The earliest transaction time on the edge that is not before `after`, if there is one.
+    pub fn first_time_from(&self, after: i64) -> Option<i64> {
+        self.times.get(self.times.partition_point(|&time| time < after)).copied()
+    }
+}
+
+/// Adjacency map from each address to the addresses it is connected to. In directed mode
+/// only outbound (sender -> receiver) edges are stored; otherwise every edge appears in both directions.
+pub type Graph = HashMap<String, HashMap<String, Edge>>;
+
+/// What kind of account a node is, as far as its transactions tell. Variants are ordered by how
+/// strong the evidence is: an address seen as an instruction's program is a program even if it
+/// also appears where a wallet could.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeKind {
+    Unknown,
+    /// Signed a transaction or owns a token account
+    Wallet,
+    TokenAccount,
+    Mint,
+    /// Invoked as an instruction's program
+    Program,
+}
+
+/// The kinds learned for the addresses of a graph; addresses without an entry are unknown.
+pub type NodeKinds = HashMap<String, NodeKind>;
+
+/// Controls which transactions and edges `build_transaction_graph` keeps.
+#[derive(Default)]
+pub struct GraphOptions {
+    pub directed: bool,
+    /// Edges that moved fewer lamports than this within a transaction are dropped, unless they moved tokens
+    pub min_lamports: u64,
+    /// Edges that moved less than this amount of every token within a transaction are dropped,
+    /// unless they moved SOL
+    pub min_token_amount: f64,
+    /// When set, only token transfers of this mint produce edges
+    pub mint: Option<String>,
+    /// When set, only transactions invoking this program produce edges
+    pub program: Option<String>,
+    /// Inclusive blockTime bounds in unix seconds
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Inclusive slot bounds; a transaction without a slot is outside any bound
+    pub min_slot: Option<u64>,
+    pub max_slot: Option<u64>,
+    /// Whether transactions with a null blockTime pass a time bound
+    pub include_unknown_time: bool,
+    /// Whether transactions that failed (non-null `meta.err`) still produce edges
+    pub include_failed: bool,
+    /// Keep token accounts as separate nodes instead of merging them into their owner wallets
+    pub keep_token_accounts: bool,
+    /// Keep wrapped SOL a token of its own instead of counting its transfers as SOL
+    pub keep_wsol: bool,
+    /// Addresses no edge may start or end at, such as [`DEFAULT_EXCLUDES`]
+    pub exclude: HashSet<String>,
+    /// Programs whose transactions are tagged as swaps on every edge they create, such as the
+    /// DEXes of [`Labels::dex_programs`](crate::Labels::dex_programs)
+    pub swap_programs: HashSet<String>,
+    /// Only NFT transfers produce edges
+    pub nfts_only: bool,
+    /// Accounts a transaction only read are left out of the edges from its first account
+    pub writable_only: bool,
+}
+
+impl GraphOptions {
+    /// Whether one transaction's edge moved enough value to be kept: at least `min_lamports` of
+    /// SOL, or at least `min_token_amount` of some token. Each minimum only judges its own asset,
+    /// so with only one of them set, edges that moved the other asset are kept.
+    pub fn meets_minimums(&self, edge: &Edge) -> bool {
+        if edge.tokens.is_empty() {
+            return edge.lamports >= self.min_lamports;
+        }
+        let sol = edge.lamports > 0 && edge.lamports >= self.min_lamports;
+        sol || edge.tokens.values().any(|amount| *amount >= self.min_token_amount)
+    }
+
+    pub fn in_time_range(&self, block_time: Option<i64>) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        match block_time {
+            Some(time) => self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time <= to),
+            None => self.include_unknown_time,
+        }
+    }
+
+    pub fn in_slot_range(&self, slot: Option<u64>) -> bool {
+        if self.min_slot.is_none() && self.max_slot.is_none() {
+            return true;
+        }
+        slot.is_some_and(|slot| self.min_slot.is_none_or(|min| slot >= min) && self.max_slot.is_none_or(|max| slot <= max))
+    }
+}
+
+/// Merges `edge` into the graph, mirroring it onto `to -> from` unless the graph is directed.
+pub fn add_edge(graph: &mut Graph, from: &str, to: &str, edge: &Edge, directed: bool) {
+    graph.entry(from.to_string()).or_default().entry(to.to_string()).or_default().merge(edge);
+    let reverse = graph.entry(to.to_string()).or_default();
+    if !directed {
+        reverse.entry(from.to_string()).or_default().merge(edge);
+    }
+}
+
+/// Merges every edge of `other` into `graph`. Both hold their edges the way they are stored, so
+/// nothing is mirrored.
+pub fn merge_graphs(graph: &mut Graph, other: Graph) {
+    if graph.is_empty() {
+        *graph = other;
+        return;
+    }
+    for (from, neighbors) in other {
+        let edges = graph.entry(from).or_default();
+        for (to, edge) in neighbors {
+            match edges.entry(to) {
+                Entry::Occupied(mut existing) => existing.get_mut().merge(&edge),
+                Entry::Vacant(slot) => {
+                    slot.insert(edge);
+                }
+            }
+        }
+    }
+}
+
+fn merge_kinds(kinds: &mut NodeKinds, other: NodeKinds) {
+    if kinds.is_empty() {
+        *kinds = other;
+        return;
+    }
+    for (address, kind) in other {
+        let known = kinds.entry(address).or_insert(kind);
+        *known = (*known).max(kind);
+    }
+}
+
+/// Splits the lamport balance changes of a transaction into (sender, receiver, amount) transfers.
+/// The fee is added back to the fee payer so it isn't mistaken for SOL sent to another account.
+/// The accounts at the `ignored` indices are left out, as if their balances hadn't changed.
+fn sol_transfers(accounts: &[String], meta: &Value, ignored: &HashSet<usize>) -> Vec<(usize, usize, u64)> {
+    let balances = |key: &str| -> Vec<i128> {
+        meta.get(key).and_then(|b| b.as_array())
+            .map(|b| b.iter().map(|v| v.as_u64().unwrap_or_default() as i128).collect())
+            .unwrap_or_default()
+    };
+    let pre = balances("preBalances");
+    let post = balances("postBalances");
+    if pre.len() != accounts.len() || post.len() != accounts.len() {
+        return Vec::new();
+    }
+
+    let mut deltas: Vec<i128> = post.iter().zip(&pre).map(|(post, pre)| post - pre).collect();
+    for &index in ignored {
+        if let Some(delta) = deltas.get_mut(index) {
+            *delta = 0;
+        }
+    }
+    if let Some(payer) = deltas.first_mut() {
+        *payer += meta.get("fee").and_then(|f| f.as_u64()).unwrap_or_default() as i128;
+    }
+
+    attribute_transfers(&deltas).into_iter().map(|(s, r, amount)| (s, r, amount as u64)).collect()
+}
+
+/// Pairs every account whose balance decreased with every account whose balance increased.
+/// Each receiver's gain is split across the senders in proportion to how much each of them lost.
+fn attribute_transfers(deltas: &[i128]) -> Vec<(usize, usize, u128)> {
+    let total_sent: i128 = deltas.iter().filter(|d| **d < 0).map(|d| -d).sum();
+    let mut transfers = Vec::new();
+    for (sender, sent) in deltas.iter().enumerate().filter(|(_, d)| **d < 0) {
+        for (receiver, received) in deltas.iter().enumerate().filter(|(_, d)| **d > 0) {
+            let amount = received * -sent / total_sent;
+            if amount > 0 {
+                transfers.push((sender, receiver, amount as u128));
+            }
+        }
+    }
+    transfers
+}
+
+/// Owner-level SPL token transfers derived from meta.preTokenBalances/postTokenBalances,
+/// as (sender owner, receiver owner, mint, ui amount).
+fn token_transfers(meta: &Value) -> Vec<(String, String, String, f64)> {
+    // (owner, mint) -> raw balance change, plus the decimals of each mint
+    let mut deltas: BTreeMap<(String, String), i128> = BTreeMap::new();
+    let mut decimals: HashMap<String, i32> = HashMap::new();
+    for (key, sign) in [("preTokenBalances", -1), ("postTokenBalances", 1)] {
+        let balances = meta.get(key).and_then(|b| b.as_array()).map(Vec::as_slice).unwrap_or_default();
+        for balance in balances {
+            let owner = balance.get("owner").and_then(|o| o.as_str());
+            let mint = balance.get("mint").and_then(|m| m.as_str());
+            let amount = balance.get("uiTokenAmount").and_then(|a| a.get("amount")).and_then(|a| a.as_str())
+                .and_then(|a| a.parse::<i128>().ok());
+            if let (Some(owner), Some(mint), Some(amount)) = (owner, mint, amount) {
+                *deltas.entry((owner.to_string(), mint.to_string())).or_default() += sign * amount;
+                let mint_decimals = balance["uiTokenAmount"].get("decimals").and_then(|d| d.as_i64()).unwrap_or_default();
+                decimals.insert(mint.to_string(), mint_decimals as i32);
+            }
+        }
+    }
+
+    let mut transfers = Vec::new();
+    for (mint, mint_decimals) in &decimals {
+        let (owners, mint_deltas): (Vec<&String>, Vec<i128>) = deltas.iter()
+            .filter(|((_, m), _)| m == mint)
+            .map(|((owner, _), delta)| (owner, *delta))
+            .unzip();
+        for (sender, receiver, amount) in attribute_transfers(&mint_deltas) {
+            let ui_amount = amount as f64 / 10f64.powi(*mint_decimals);
+            transfers.push((owners[sender].clone(), owners[receiver].clone(), mint.clone(), ui_amount));
+        }
+    }
+    transfers
+}
+
+/// The indices of the wrapped SOL token accounts in the token balances of a transaction. Their
+/// lamport balances move with their wSOL balances.
+fn wsol_accounts(meta: &Value) -> HashSet<usize> {
+    ["preTokenBalances", "postTokenBalances"].iter()
+        .flat_map(|key| meta.get(*key).and_then(|b| b.as_array()).into_iter().flatten())
+        .filter(|balance| balance.get("mint").and_then(|m| m.as_str()) == Some(WSOL_MINT))
+        .filter_map(|balance| balance.get("accountIndex").and_then(|i| i.as_u64()).map(|i| i as usize))
+        .collect()
+}
+
+/// A wrapped SOL amount in lamports.
+fn wsol_lamports(amount: f64) -> u64 {
+    (amount * LAMPORTS_PER_SOL as f64).round() as u64
+}
+
+/// The decimals of every mint in the token balances of a transaction.
+fn mint_decimals(meta: &Value) -> HashMap<&str, i64> {
+    let mut decimals = HashMap::new();
+    for key in ["preTokenBalances", "postTokenBalances"] {
+        for balance in meta.get(key).and_then(|b| b.as_array()).into_iter().flatten() {
+            let mint = balance.get("mint").and_then(|m| m.as_str());
+            let mint_decimals = balance.get("uiTokenAmount").and_then(|a| a.get("decimals")).and_then(|d| d.as_i64());
+            if let (Some(mint), Some(mint_decimals)) = (mint, mint_decimals) {
+                decimals.insert(mint, mint_decimals);
+            }
+        }
+    }
+    decimals
+}
+
+/// Whether a transfer of `amount` of a token with `decimals` moved an NFT. Without the mint's
+/// supply at hand, a token that can't be split, moved one at a time, is taken for one.
+fn is_nft_transfer(decimals: Option<i64>, amount: f64) -> bool {
+    decimals == Some(0) && amount == 1.0
+}
+
+/// The top-level instructions of a transaction followed by its inner ones.
+fn instructions(transaction: &Value) -> impl Iterator<Item = &Value> {
+    let outer = transaction.get("transaction")
+        .and_then(|t| t.get("message"))
+        .and_then(|m| m.get("instructions"))
+        .and_then(|i| i.as_array())
+        .into_iter()
+        .flatten();
+    let inner = transaction.get("meta")
+        .and_then(|m| m.get("innerInstructions"))
+        .and_then(|i| i.as_array())
+        .into_iter()
+        .flatten()
+        .flat_map(|group| group.get("instructions").and_then(|i| i.as_array()).into_iter().flatten());
+    outer.chain(inner)
+}
+
+/// The program an instruction invokes: an index into the account list with `json` encoding,
+/// the address itself with `jsonParsed`.
+fn program_id<'a>(instruction: &'a Value, accounts: &'a [String]) -> Option<&'a str> {
+    match instruction.get("programIdIndex").and_then(|i| i.as_u64()) {
+        Some(index) => accounts.get(index as usize).map(String::as_str),
+        None => instruction.get("programId").and_then(|p| p.as_str()),
+    }
+}
+
+/// Whether any top-level or inner instruction of the transaction invokes `program`.
+fn invokes_program(transaction: &Value, accounts: &[String], program: &str) -> bool {
+    instructions(transaction).any(|instruction| program_id(instruction, accounts) == Some(program))
+}
+
+/// The full account list of a transaction, in the order instruction and balance indexes refer to:
+/// the static `accountKeys`, then the writable and readonly addresses loaded from lookup tables.
+/// With `jsonParsed` encoding the keys are objects and already include the loaded addresses.
+fn account_keys(transaction: &Value) -> Option<Vec<String>> {
+    let strings = |keys: Option<&Value>| -> Vec<String> {
+        keys.and_then(|k| k.as_array()).into_iter().flatten()
+            .filter_map(|key| key.as_str().or_else(|| key.get("pubkey").and_then(|p| p.as_str())).map(|s| s.to_string()))
+            .collect()
+    };
+
+    let static_keys = transaction.get("transaction")
+        .and_then(|t| t.get("message"))
+        .and_then(|m| m.get("accountKeys"))?;
+    let mut accounts = strings(Some(static_keys));
+    if static_keys.get(0).is_some_and(Value::is_object) {
+        return Some(accounts);
+    }
+
+    let loaded = transaction.get("meta").and_then(|m| m.get("loadedAddresses"));
+    accounts.extend(strings(loaded.and_then(|l| l.get("writable"))));
+    accounts.extend(strings(loaded.and_then(|l| l.get("readonly"))));
+    Some(accounts)
+}
+
+/// What a transaction allowed an account to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct AccountRole {
+    signer: bool,
+    writable: bool,
+}
+
+/// The role of each of `accounts`, the keys of `transaction` in [`account_keys`] order, as the
+/// message header tells them (or, for `jsonParsed` keys, the keys themselves). Loaded addresses
+/// are never signers. An account whose role isn't known is taken to be a writable non-signer.
+fn account_roles(transaction: &Value, accounts: &[String]) -> Vec<AccountRole> {
+    let unknown = AccountRole { signer: false, writable: true };
+    let message = transaction.get("transaction").and_then(|t| t.get("message"));
+    let static_keys = message.and_then(|m| m.get("accountKeys")).and_then(|k| k.as_array()).map(Vec::as_slice).unwrap_or_default();
+    if static_keys.first().is_some_and(Value::is_object) {
+        let flag = |key: &Value, name: &str| key.get(name).and_then(|f| f.as_bool());
+        return static_keys.iter()
+            .map(|key| AccountRole { signer: flag(key, "signer").unwrap_or(false), writable: flag(key, "writable").unwrap_or(true) })
+            .collect();
+    }
+
+    let mut roles = vec![unknown; accounts.len()];
+    let Some(header) = message.and_then(|m| m.get("header")) else {
+        return roles;
+    };
+    let count = |name: &str| header.get(name).and_then(|n| n.as_u64()).unwrap_or_default() as usize;
+    let (signers, readonly_signers, readonly_unsigned) =
+        (count("numRequiredSignatures"), count("numReadonlySignedAccounts"), count("numReadonlyUnsignedAccounts"));
+    for (index, role) in roles.iter_mut().enumerate().take(static_keys.len()) {
+        role.signer = index < signers;
+        role.writable = if role.signer { index < signers.saturating_sub(readonly_signers) } else { index < static_keys.len().saturating_sub(readonly_unsigned) };
+    }
+    let loaded_writable = transaction.get("meta")
+        .and_then(|m| m.get("loadedAddresses"))
+        .and_then(|l| l.get("writable"))
+        .and_then(|w| w.as_array())
+        .map_or(0, Vec::len);
+    for role in roles.iter_mut().skip(static_keys.len() + loaded_writable) {
+        role.writable = false;
+    }
+    roles
+}
+
+/// What a transfer read from a parsed instruction moved.
+enum ParsedAmount {
+    Lamports(u64),
+    /// A token's UI amount
+    Token { mint: String, amount: f64 },
+}
+
+/// The transfers of a transaction fetched with `jsonParsed` encoding, as (source, destination,
+/// amount), read from its system `transfer` and `createAccount` and SPL token `transfer` and
+/// `transferChecked` instructions, inner ones included. Token accounts are replaced by the owners
+/// named in the token balances, like the balance-based token transfers.
+///
+/// `None` when the transaction wasn't fetched with `jsonParsed`, or when any of its instructions
+/// couldn't be parsed: such an instruction may have moved value the parsed ones don't show, so the
+/// balance changes are the better source.
+fn parsed_transfers(transaction: &Value, accounts: &[String]) -> Option<Vec<(String, String, ParsedAmount)>> {
+    let instructions: Vec<&Value> = instructions(transaction).collect();
+    if instructions.is_empty() || instructions.iter().any(|instruction| instruction.get("parsed").is_none()) {
+        return None;
+    }
+
+    // Token account -> (owner, mint, decimals)
+    let mut token_accounts: HashMap<&str, (&str, &str, i32)> = HashMap::new();
+    let meta = transaction.get("meta");
+    for key in ["preTokenBalances", "postTokenBalances"] {
+        for balance in meta.and_then(|m| m.get(key)).and_then(|b| b.as_array()).into_iter().flatten() {
+            let account = balance.get("accountIndex").and_then(|i| i.as_u64()).and_then(|i| accounts.get(i as usize));
+            let owner = balance.get("owner").and_then(|o| o.as_str());
+            let mint = balance.get("mint").and_then(|m| m.as_str());
+            let decimals = balance.get("uiTokenAmount").and_then(|a| a.get("decimals")).and_then(|d| d.as_i64()).unwrap_or_default();
+            if let (Some(account), Some(owner), Some(mint)) = (account, owner, mint) {
+                token_accounts.insert(account, (owner, mint, decimals as i32));
+            }
+        }
+    }
+
+    let mut transfers = Vec::new();
+    for instruction in instructions {
+        let parsed = &instruction["parsed"];
+        let info = &parsed["info"];
+        let text = |key: &str| info.get(key).and_then(|v| v.as_str());
+        let program = instruction.get("program").and_then(|p| p.as_str());
+        match (program, parsed.get("type").and_then(|t| t.as_str())) {
+            (Some("system"), Some("transfer" | "transferWithSeed" | "createAccount" | "createAccountWithSeed")) => {
+                let destination = text("destination").or_else(|| text("newAccount"))?;
+                let lamports = info.get("lamports").and_then(|l| l.as_u64())?;
+                transfers.push((text("source")?.to_string(), destination.to_string(), ParsedAmount::Lamports(lamports)));
+            }
+            (Some("spl-token"), Some(kind @ ("transfer" | "transferChecked"))) => {
+                let (source, destination) = (text("source")?, text("destination")?);
+                let (from, to) = (token_accounts.get(source), token_accounts.get(destination));
+                let known = from.or(to);
+                // A plain `transfer` names neither mint nor decimals; the token balances do
+                let mint = text("mint").or(known.map(|account| account.1))?;
+                let (raw, decimals) = match kind {
+                    "transferChecked" => (
+                        info.get("tokenAmount").and_then(|a| a.get("amount")).and_then(|a| a.as_str()),
+                        info.get("tokenAmount").and_then(|a| a.get("decimals")).and_then(|d| d.as_i64()).map(|d| d as i32),
+                    ),
+                    _ => (text("amount"), known.map(|account| account.2)),
+                };
+                let amount = raw?.parse::<u128>().ok()? as f64 / 10f64.powi(decimals?);
+                let owner = |account: &str, known: Option<&(&str, &str, i32)>| known.map_or(account, |known| known.0).to_string();
+                transfers.push((owner(source, from), owner(destination, to), ParsedAmount::Token { mint: mint.to_string(), amount }));
+            }
+            _ => {}
+        }
+    }
+    Some(transfers)
+}
+
+/// Edges contributed by a single transaction: the first account to every other account,
+/// any sender -> receiver pairs that SOL moved between, and owner -> owner token transfers.
+/// Transfers come from the parsed instructions of a `jsonParsed` transaction, and otherwise from
+/// the balance changes. Wrapped SOL counts as SOL unless `options.keep_wsol` is set or a mint
+/// was asked for; the lamport balances of wSOL accounts already carry it, so only the parsed
+/// transfers have amounts to convert.
+fn transaction_edges(transaction: &Value, options: &GraphOptions, owners: &HashMap<String, String>) -> HashMap<(String, String), Edge> {
+    let mut edges: HashMap<(String, String), Edge> = HashMap::new();
+    let meta = transaction.get("meta");
+
+    if !options.in_time_range(transaction.get("blockTime").and_then(|t| t.as_i64())) {
+        return edges;
+    }
+    if !options.in_slot_range(transaction.get("slot").and_then(|s| s.as_u64())) {
+        return edges;
+    }
+
+    // A failed transaction's instructions were reverted, so it links nothing unless asked for
+    let failed = meta.and_then(|m| m.get("err")).is_some_and(|err| !err.is_null());
+    if failed && !options.include_failed {
+        return edges;
+    }
+
+    let accounts = match account_keys(transaction) {
+        Some(accounts) => accounts,
+        None => return edges,
+    };
+
+    if let Some(ref program) = options.program {
+        if !invokes_program(transaction, &accounts, program) {
+            return edges;
+        }
+    }
+
+    let swaps: BTreeSet<String> = instructions(transaction)
+        .filter_map(|instruction| program_id(instruction, &accounts))
+        .filter(|program| options.swap_programs.contains(*program))
+        .map(str::to_string)
+        .collect();
+    let parsed = parsed_transfers(transaction, &accounts);
+    let roles = account_roles(transaction, &accounts);
+    let owner = |account: &String| owners.get(account).cloned().unwrap_or_else(|| account.clone());
+    let accounts: Vec<String> = accounts.iter().map(owner).collect();
+    let merge_wsol = !options.keep_wsol && options.mint.is_none();
+
+    if options.mint.is_none() && !options.nfts_only {
+        if let Some(sender) = accounts.first() {
+            for (receiver, role) in accounts.iter().zip(&roles).skip(1).filter(|(receiver, _)| *receiver != sender) {
+                if options.writable_only && !role.writable {
+                    continue;
+                }
+                // An owner merged from several of the accounts is written if any of them is
+                let readonly = u32::from(!role.writable);
+                let edge = edges.entry((sender.clone(), receiver.clone())).or_insert(Edge { count: 1, readonly, ..Edge::default() });
+                edge.readonly = edge.readonly.min(readonly);
+            }
+        }
+
+        let transfers: Vec<(String, String, u64)> = match parsed {
+            Some(ref transfers) => transfers.iter()
+                .filter_map(|(source, destination, amount)| match amount {
+                    ParsedAmount::Lamports(lamports) => Some((owner(source), owner(destination), *lamports)),
+                    ParsedAmount::Token { mint, amount } if merge_wsol && mint == WSOL_MINT => {
+                        Some((owner(source), owner(destination), wsol_lamports(*amount)))
+                    }
+                    ParsedAmount::Token { .. } => None,
+                })
+                .collect(),
+            None => {
+                // Kept apart, wSOL moves between token accounts must not show up as SOL too
+                let ignored = match meta {
+                    Some(meta) if !merge_wsol => wsol_accounts(meta),
+                    _ => HashSet::new(),
+                };
+                meta.map(|m| sol_transfers(&accounts, m, &ignored)).unwrap_or_default().into_iter()
+                    .map(|(sender, receiver, lamports)| (accounts[sender].clone(), accounts[receiver].clone(), lamports))
+                    .collect()
+            }
+        };
+        for (sender, receiver, lamports) in transfers {
+            // Rent refunded from a closed token account to its own owner is not a link
+            if sender == receiver {
+                continue;
+            }
+            let edge = edges.entry((sender, receiver)).or_insert(Edge { count: 1, ..Edge::default() });
+            edge.lamports += lamports;
+        }
+    }
+
+    let token_transfers: Vec<(String, String, String, f64)> = match parsed {
+        Some(transfers) => transfers.into_iter()
+            .filter_map(|(source, destination, amount)| match amount {
+                ParsedAmount::Token { mint, amount } => Some((source, destination, mint, amount)),
+                ParsedAmount::Lamports(_) => None,
+            })
+            .collect(),
+        None => meta.map(token_transfers).unwrap_or_default(),
+    };
+    let decimals = meta.map(mint_decimals).unwrap_or_default();
+    for (sender, receiver, mint, amount) in token_transfers {
+        if sender == receiver || (merge_wsol && mint == WSOL_MINT) {
+            continue;
+        }
+        if options.mint.as_ref().is_some_and(|m| *m != mint) {
+            continue;
+        }
+        let nft = is_nft_transfer(decimals.get(mint.as_str()).copied(), amount);
+        if options.nfts_only && !nft {
+            continue;
+        }
+        let edge = edges.entry((sender, receiver)).or_insert(Edge { count: 1, ..Edge::default() });
+        if nft {
+            edge.nfts.insert(mint.clone());
+        }
+        *edge.tokens.entry(mint).or_default() += amount;
+    }
+
+    edges.retain(|(from, to), _| !options.exclude.contains(from) && !options.exclude.contains(to));
+
+    let signature = transaction.get("transaction")
+        .and_then(|t| t.get("signatures"))
+        .and_then(|s| s.get(0))
+        .and_then(|s| s.as_str());
+    let block_time = transaction.get("blockTime").and_then(|t| t.as_i64());
+    for edge in edges.values_mut() {
+        edge.times.extend(block_time);
+        edge.swaps.extend(swaps.iter().cloned());
+    }
+    if let Some(signature) = signature {
+        for edge in edges.values_mut() {
+            edge.signatures.push(signature.to_string());
+        }
+    }
+
+    edges
+}
+
+/// Adds the owner wallet of every token account referenced in the token balances of `transaction` to `owners`.
+fn record_token_account_owners(transaction: &Value, owners: &mut HashMap<String, String>) {
+    let (Some(accounts), Some(meta)) = (account_keys(transaction), transaction.get("meta")) else {
+        return;
+    };
+    for key in ["preTokenBalances", "postTokenBalances"] {
+        for balance in meta.get(key).and_then(|b| b.as_array()).into_iter().flatten() {
+            let account = balance.get("accountIndex").and_then(|i| i.as_u64()).and_then(|i| accounts.get(i as usize));
+            let owner = balance.get("owner").and_then(|o| o.as_str());
+            if let (Some(account), Some(owner)) = (account, owner) {
+                owners.insert(account.clone(), owner.to_string());
+            }
+        }
+    }
+}
+
+/// Learns what the accounts of a transaction are from the roles they play in it: signers and token
+/// owners are wallets, token balance accounts and mints are what they say, and instruction
+/// program ids are programs.
+fn record_node_kinds(transaction: &Value, kinds: &mut NodeKinds) {
+    let Some(accounts) = account_keys(transaction) else {
+        return;
+    };
+    let mut mark = |address: &str, kind: NodeKind| {
+        let known = kinds.entry(address.to_string()).or_insert(kind);
+        *known = (*known).max(kind);
+    };
+
+    for (account, role) in accounts.iter().zip(account_roles(transaction, &accounts)) {
+        if role.signer {
+            mark(account, NodeKind::Wallet);
+        }
+    }
+
+    let meta = transaction.get("meta");
+    for key in ["preTokenBalances", "postTokenBalances"] {
+        for balance in meta.and_then(|m| m.get(key)).and_then(|b| b.as_array()).into_iter().flatten() {
+            if let Some(account) = balance.get("accountIndex").and_then(|i| i.as_u64()).and_then(|i| accounts.get(i as usize)) {
+                mark(account, NodeKind::TokenAccount);
+            }
+            if let Some(mint) = balance.get("mint").and_then(|m| m.as_str()) {
+                mark(mint, NodeKind::Mint);
+            }
+            if let Some(owner) = balance.get("owner").and_then(|o| o.as_str()) {
+                mark(owner, NodeKind::Wallet);
+            }
+        }
+    }
+
+    for instruction in instructions(transaction) {
+        if let Some(program) = program_id(instruction, &accounts) {
+            mark(program, NodeKind::Program);
+        }
+    }
+}
+
+/// Adds the edges of one transaction that meet the minimums of `options` to `graph`, returning
+/// how many were dropped for falling short.
+fn add_transaction_edges(graph: &mut Graph, transaction: &Value, options: &GraphOptions, owners: &HashMap<String, String>) -> usize {
+    let mut dropped = 0;
+    for ((from, to), edge) in transaction_edges(transaction, options, owners) {
+        if options.meets_minimums(&edge) {
+            add_edge(graph, &from, &to, &edge, options.directed);
+        } else {
+            dropped += 1;
+        }
+    }
+    dropped
+}
+
+/// Builds the connection graph one `getTransaction` result at a time, so that each transaction
+/// can be dropped as soon as it has been added and memory stays proportional to the graph.
+/// Transactions that are already in memory can be added in batches with [`add_all`](Self::add_all),
+/// which spreads the work across threads.
+///
+/// Token accounts are merged into their owner wallets unless `options.keep_token_accounts` is set.
+/// Owners are learned from the token balances of the transactions added so far; a token account
+/// that appeared before its owner was known is merged when the graph is finished.
+/// The kind of every account is learned along the way, see [`finish_with_kinds`](Self::finish_with_kinds).
+pub struct GraphBuilder<'a> {
+    options: &'a GraphOptions,
+    graph: Graph,
+    owners: HashMap<String, String>,
+    kinds: NodeKinds,
+    dropped: usize,
+}
+
+impl<'a> GraphBuilder<'a> {
+    pub fn new(options: &'a GraphOptions) -> Self {
+        GraphBuilder { options, graph: Graph::new(), owners: HashMap::new(), kinds: NodeKinds::new(), dropped: 0 }
+    }
+
+    /// How many single-transaction edges have been left out so far for moving less than the
+    /// minimum SOL or token amount.
+    pub fn dropped_edges(&self) -> usize {
+        self.dropped
+    }
+
+    /// Folds the edges of one transaction into the graph.
+    pub fn add(&mut self, transaction: &Value) {
+        record_node_kinds(transaction, &mut self.kinds);
+        if !self.options.keep_token_accounts {
+            record_token_account_owners(transaction, &mut self.owners);
+        }
+        self.dropped += add_transaction_edges(&mut self.graph, transaction, self.options, &self.owners);
+    }
+
+    /// Folds a batch of transactions into the graph in parallel: each thread builds the edges of a
+    /// run of transactions into a graph of its own, and the partial graphs are merged in order, so
+    /// counts, amounts and edge signatures come out as if the transactions were added one by one.
+    /// Token account owners learned anywhere in the batch apply to all of it.
+    pub fn add_all(&mut self, transactions: &[Value]) {
+        // One run per thread: merging partial graphs costs about as much as building them
+        let run = transactions.len().div_ceil(rayon::current_num_threads()).max(1);
+        if !self.options.keep_token_accounts {
+            let owners = transactions.par_chunks(run)
+                .map(|run| {
+                    let mut owners = HashMap::new();
+                    for transaction in run {
+                        record_token_account_owners(transaction, &mut owners);
+                    }
+                    owners
+                })
+                .reduce(HashMap::new, |mut owners, later| {
+                    owners.extend(later);
+                    owners
+                });
+            self.owners.extend(owners);
+        }
+
+        let (options, owners) = (self.options, &self.owners);
+        let (graph, kinds, dropped) = transactions.par_chunks(run)
+            .map(|run| {
+                let (mut graph, mut kinds, mut dropped) = (Graph::new(), NodeKinds::new(), 0);
+                for transaction in run {
+                    record_node_kinds(transaction, &mut kinds);
+                    dropped += add_transaction_edges(&mut graph, transaction, options, owners);
+                }
+                (graph, kinds, dropped)
+            })
+            .reduce(|| (Graph::new(), NodeKinds::new(), 0), |(mut graph, mut kinds, dropped), (later_graph, later_kinds, later_dropped)| {
+                merge_graphs(&mut graph, later_graph);
+                merge_kinds(&mut kinds, later_kinds);
+                (graph, kinds, dropped + later_dropped)
+            });
+        merge_graphs(&mut self.graph, graph);
+        merge_kinds(&mut self.kinds, kinds);
+        self.dropped += dropped;
+    }
+
+    pub fn finish(self) -> Graph {
+        self.finish_with_kinds().0
+    }
+
+    /// The finished graph, along with the kinds learned for its accounts.
+    pub fn finish_with_kinds(self) -> (Graph, NodeKinds) {
+        if !self.graph.keys().any(|node| self.owners.contains_key(node)) {
+            return (self.graph, self.kinds);
+        }
+
+        let owner = |account: &'_ String| self.owners.get(account).cloned().unwrap_or_else(|| account.clone());
+        let mut merged = Graph::new();
+        for (from, neighbors) in &self.graph {
+            let from = owner(from);
+            merged.entry(from.clone()).or_default();
+            for (to, edge) in neighbors {
+                let to = owner(to);
+                // Both directions of an undirected edge are already stored, so nothing is mirrored here
+                if from != to {
+                    add_edge(&mut merged, &from, &to, edge, true);
+                }
+            }
+        }
+        (merged, self.kinds)
+    }
+}
+
+/// Builds the connection graph from `getTransaction` results held in memory, in parallel with
+/// [`GraphBuilder::add_all`]. Unlike adding them to a [`GraphBuilder`] one by one, every token
+/// account owner is known before the first edge is built.
+pub fn build_transaction_graph(transactions: &[Value], options: &GraphOptions) -> Graph {
+    let mut builder = GraphBuilder::new(options);
+    builder.add_all(transactions);
+    builder.finish()
+}
+
+/// Builds the graph from decoded transfers, such as those returned by a
+/// [`TransferProvider`](crate::provider::TransferProvider). A transaction counts once per
+/// sender/receiver pair no matter how many transfers it contains between them.
+/// Wrapped SOL transfers count as SOL unless `options.keep_wsol` is set or a mint was asked for.
+pub fn build_transfer_graph(transfers: &[Transfer], options: &GraphOptions) -> Graph {
+    let mut edges: BTreeMap<(&str, &str, &str), Edge> = BTreeMap::new();
+    let mut block_times: HashMap<&str, i64> = HashMap::new();
+
+    for transfer in transfers {
+        if transfer.from == transfer.to || !options.in_time_range(transfer.block_time) {
+            continue;
+        }
+        if options.exclude.contains(&transfer.from) || options.exclude.contains(&transfer.to) {
+            continue;
+        }
+        match &transfer.kind {
+            TransferKind::Sol { lamports } if options.mint.is_none() && !options.nfts_only => {
+                let edge = edges.entry((&transfer.signature, &transfer.from, &transfer.to)).or_default();
+                edge.lamports += lamports;
+            }
+            TransferKind::Token { mint, amount } if mint == WSOL_MINT && !options.keep_wsol && options.mint.is_none() && !options.nfts_only => {
+                let edge = edges.entry((&transfer.signature, &transfer.from, &transfer.to)).or_default();
+                edge.lamports += wsol_lamports(*amount);
+            }
+            TransferKind::Token { mint, amount } if options.mint.as_ref().is_none_or(|m| m == mint) && !options.nfts_only => {
+                let edge = edges.entry((&transfer.signature, &transfer.from, &transfer.to)).or_default();
+                *edge.tokens.entry(mint.clone()).or_default() += amount;
+            }
+            TransferKind::Nft { mint } if options.mint.as_ref().is_none_or(|m| m == mint) => {
+                let edge = edges.entry((&transfer.signature, &transfer.from, &transfer.to)).or_default();
+                *edge.tokens.entry(mint.clone()).or_default() += 1.0;
+                edge.nfts.insert(mint.clone());
+            }
+            _ => {}
+        }
+        if let Some(time) = transfer.block_time {
+            block_times.insert(transfer.signature.as_str(), time);
+        }
+    }
+
+    let mut graph = Graph::new();
+    for ((signature, from, to), mut edge) in edges {
+        edge.count = 1;
+        edge.signatures.push(signature.to_string());
+        edge.times.extend(block_times.get(signature));
+        if options.meets_minimums(&edge) {
+            add_edge(&mut graph, from, to, &edge, options.directed);
+        }
+    }
+    graph
+}
+
+/// An address that every query address transacted with directly.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Counterparty {
+    pub address: String,
+    /// Transactions linking the counterparty to the query addresses, summed over all of them
+    pub count: u32,
+}
+
+/// An address directly linked to a queried one, with everything known about the link.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Neighbor {
+    pub address: String,
+    #[serde(flatten)]
+    pub edge: Edge,
+}
+
+/// The edges linking `address` to each of its direct neighbors. In a directed graph outbound
+/// and inbound edges are merged, since either is a direct interaction.
+fn neighbor_edges<'a>(graph: &'a Graph, address: &str, directed: bool) -> HashMap<&'a str, Edge> {
+    let mut edges: HashMap<&str, Edge> = HashMap::new();
+    for (neighbor, edge) in graph.get(address).into_iter().flatten() {
+        edges.entry(neighbor).or_default().merge(edge);
+    }
+    if directed {
+        for (node, node_edges) in graph {
+            if let Some(edge) = node_edges.get(address) {
+                edges.entry(node).or_default().merge(edge);
+            }
+        }
+    }
+    edges
+}
+
+/// The direct neighbors of `address`, busiest first.
+pub fn neighbors(graph: &Graph, address: &str, directed: bool) -> Vec<Neighbor> {
+    let mut neighbors: Vec<Neighbor> = neighbor_edges(graph, address, directed).into_iter()
+        .map(|(neighbor, edge)| Neighbor { address: neighbor.to_string(), edge })
+        .collect();
+    neighbors.sort_by(|a, b| b.edge.count.cmp(&a.edge.count).then_with(|| a.address.cmp(&b.address)));
+    neighbors
+}
+
+/// The addresses, other than the queries themselves, that are directly linked to every one of
+/// `addresses`, ordered by combined transaction count with the busiest first.
+pub fn common_counterparties(graph: &Graph, addresses: &[&str], directed: bool) -> Vec<Counterparty> {
+    let mut common: Option<HashMap<&str, u32>> = None;
+    for address in addresses {
+        let counts: HashMap<&str, u32> = neighbor_edges(graph, address, directed).into_iter()
+            .map(|(neighbor, edge)| (neighbor, edge.count))
+            .collect();
+        common = Some(match common {
+            None => counts,
+            Some(common) => common.into_iter()
+                .filter_map(|(neighbor, total)| counts.get(neighbor).map(|count| (neighbor, total + count)))
+                .collect(),
+        });
+    }
+
+    let mut counterparties: Vec<Counterparty> = common.unwrap_or_default().into_iter()
+        .filter(|(neighbor, _)| !addresses.contains(neighbor))
+        .map(|(address, count)| Counterparty { address: address.to_string(), count })
+        .collect();
+    counterparties.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.address.cmp(&b.address)));
+    counterparties
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::find_paths;
+
+    fn path(nodes: &[&str]) -> Vec<String> {
+        nodes.iter().map(|n| n.to_string()).collect()
+    }
+
+    const SENDER: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+
+    fn fixture(json: &str) -> Value {
+        serde_json::from_str(json).expect("fixture is valid JSON")
+    }
+
+    /// Every stored edge as (from, to, count, lamports), sorted.
+    fn edge_list(graph: &Graph) -> Vec<(&str, &str, u32, u64)> {
+        let mut edges: Vec<_> = graph.iter()
+            .flat_map(|(from, neighbors)| neighbors.iter().map(move |(to, edge)| (from.as_str(), to.as_str(), edge.count, edge.lamports)))
+            .collect();
+        edges.sort_unstable();
+        edges
+    }
+
+    fn directed() -> GraphOptions {
+        GraphOptions { directed: true, ..GraphOptions::default() }
+    }
+
+    #[test]
+    fn sol_transfer_links_the_sender_to_every_other_account() {
+        let transactions = [fixture(include_str!("../tests/fixtures/transactions/sol_transfer.json"))];
+        let receiver = "HN7cABqLq46Es1jh92dQQisAq662SmxELLLsHHe4YWrH";
+
+        let graph = build_transaction_graph(&transactions, &directed());
+        assert_eq!(edge_list(&graph), vec![
+            (SENDER, SYSTEM_PROGRAM, 1, 0),
+            (SENDER, receiver, 1, 1_000_000_000),
+        ]);
+        assert_eq!(graph[SENDER][receiver].signatures, [transactions[0]["transaction"]["signatures"][0].as_str().unwrap()]);
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+        assert_eq!(edge_list(&graph).len(), 4);
+        assert_eq!(graph[receiver][SENDER].lamports, 1_000_000_000);
+    }
+
+    #[test]
+    fn excluded_addresses_get_no_edges() {
+        let transactions = [fixture(include_str!("../tests/fixtures/transactions/versioned_loaded_addresses.json"))];
+        let options = GraphOptions {
+            exclude: DEFAULT_EXCLUDES.iter().map(|address| address.to_string()).collect(),
+            ..directed()
+        };
+
+        let graph = build_transaction_graph(&transactions, &options);
+        assert_eq!(edge_list(&graph), vec![(SENDER, "3h1zGmCwsRJnVk5BuRNMLsPaQu1y2aqXqXDWYCgrp5UG", 1, 500_000_000)]);
+        assert!(DEFAULT_EXCLUDES.iter().all(|address| crate::is_valid_pubkey(address)));
+    }
+
+    #[test]
+    fn multi_account_transaction_has_no_edges_between_receivers() {
+        let transactions = [fixture(include_str!("../tests/fixtures/transactions/multi_account.json"))];
+        let first = "2ojv9BAiHUrvsm9gxDe7fJSzbNZSJcxZvf8dqmWGHG8S";
+        let second = "DRpbCBMxVnDK7maPM5tGv6MvB3v1sRMC86PZ8okm21hy";
+
+        let graph = build_transaction_graph(&transactions, &directed());
+        assert_eq!(edge_list(&graph), vec![
+            (SENDER, SYSTEM_PROGRAM, 1, 0),
+            (SENDER, first, 1, 1_000_000_000),
+            (SENDER, second, 1, 2_000_000_000),
+        ]);
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+        assert!(!graph[first].contains_key(second));
+    }
+
+    #[test]
+    fn versioned_transaction_links_loaded_addresses() {
+        let transactions = [fixture(include_str!("../tests/fixtures/transactions/versioned_loaded_addresses.json"))];
+
+        let graph = build_transaction_graph(&transactions, &directed());
+        assert_eq!(edge_list(&graph), vec![
+            (SENDER, SYSTEM_PROGRAM, 1, 0),
+            (SENDER, "3h1zGmCwsRJnVk5BuRNMLsPaQu1y2aqXqXDWYCgrp5UG", 1, 500_000_000),
+            (SENDER, "SysvarRent111111111111111111111111111111111", 1, 0),
+        ]);
+        // The lookup table only lists the addresses, and its readonly entry is only read
+        assert!(!graph[SENDER].contains_key("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"));
+        assert_eq!(graph[SENDER]["SysvarRent111111111111111111111111111111111"].readonly, 1);
+        assert_eq!(graph[SENDER]["3h1zGmCwsRJnVk5BuRNMLsPaQu1y2aqXqXDWYCgrp5UG"].readonly, 0);
+    }
+
+    #[test]
+    fn parsed_instructions_attribute_each_transfer_to_its_sender() {
+        let transaction = fixture(include_str!("../tests/fixtures/transactions/parsed_transfers.json"));
+        let (first, second) = ("HN7cABqLq46Es1jh92dQQisAq662SmxELLLsHHe4YWrH", "2ojv9BAiHUrvsm9gxDe7fJSzbNZSJcxZvf8dqmWGHG8S");
+        let co_signer = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+        let token_receiver = "3h1zGmCwsRJnVk5BuRNMLsPaQu1y2aqXqXDWYCgrp5UG";
+        let usdc = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+        let graph = build_transaction_graph(std::slice::from_ref(&transaction), &directed());
+        assert_eq!(graph[SENDER][first].lamports, 1_000_000_000);
+        assert_eq!(graph[SENDER][second].lamports, 0);
+        assert_eq!(graph[co_signer][second].lamports, 3_000_000_000);
+        assert!(!graph[co_signer].contains_key(first));
+        assert_eq!(graph[SENDER][token_receiver].tokens, BTreeMap::from([(usdc.to_string(), 2.5)]));
+
+        // An instruction the node couldn't decode sends the whole transaction back to the balance
+        // changes, which split each receiver's gain across both senders
+        let mut unparsed = transaction.clone();
+        unparsed["transaction"]["message"]["instructions"][1] = serde_json::json!({
+            "programId": "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "accounts": [co_signer, second], "data": "3Bxs4h24hBtQy9rw"
+        });
+        let graph = build_transaction_graph(&[unparsed], &directed());
+        assert_eq!(graph[SENDER][first].lamports, 250_000_000);
+        assert_eq!(graph[co_signer][first].lamports, 750_000_000);
+        assert_eq!(graph[SENDER][token_receiver].tokens[usdc], 2.5);
+    }
+
+    #[test]
+    fn failed_transaction_only_links_when_included() {
+        let transactions = [fixture(include_str!("../tests/fixtures/transactions/failed.json"))];
+
+        assert!(edge_list(&build_transaction_graph(&transactions, &directed())).is_empty());
+
+        // Only the fee left the payer, and it isn't counted as a transfer
+        let options = GraphOptions { include_failed: true, ..directed() };
+        assert_eq!(edge_list(&build_transaction_graph(&transactions, &options)), vec![
+            (SENDER, SYSTEM_PROGRAM, 1, 0),
+            (SENDER, "HN7cABqLq46Es1jh92dQQisAq662SmxELLLsHHe4YWrH", 1, 0),
+        ]);
+    }
+
+    #[test]
+    fn directed_graph_only_follows_sender_to_receiver() {
+        let transactions = vec![
+            serde_json::json!({ "transaction": { "message": { "accountKeys": ["A", "B"] } } }),
+            serde_json::json!({ "transaction": { "message": { "accountKeys": ["C", "B"] } } }),
+        ];
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions { directed: true, ..GraphOptions::default() });
+        assert_eq!(graph.len(), 3);
+        assert!(find_paths(&graph, "A", "C", 50).is_empty());
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+        assert_eq!(find_paths(&graph, "A", "C", 50), vec![path(&["A", "B", "C"])]);
+    }
+
+    #[test]
+    fn incremental_building_merges_token_accounts_seen_before_their_owner() {
+        // The first transaction closes the token account "Ata" without recording its balance,
+        // so its owner is only learned from the second one
+        let transactions = vec![
+            serde_json::json!({ "transaction": { "message": { "accountKeys": ["Wallet", "Ata"] } } }),
+            serde_json::json!({
+                "transaction": { "message": { "accountKeys": ["Other", "Ata"] } },
+                "meta": { "postTokenBalances": [{ "accountIndex": 1, "owner": "Wallet", "mint": "Mint", "uiTokenAmount": { "amount": "0", "decimals": 0 } }] }
+            }),
+        ];
+
+        let options = GraphOptions::default();
+        let mut builder = GraphBuilder::new(&options);
+        for transaction in &transactions {
+            builder.add(transaction);
+        }
+        let graph = builder.finish();
+
+        assert!(!graph.contains_key("Ata"));
+        assert_eq!(graph["Other"].keys().collect::<Vec<_>>(), ["Wallet"]);
+        assert!(!graph["Wallet"].contains_key("Wallet"));
+        assert_eq!(graph, build_transaction_graph(&transactions, &options));
+    }
+
+    #[test]
+    fn parallel_batches_build_the_same_graph_as_serial_adds() {
+        let fixtures = [
+            fixture(include_str!("../tests/fixtures/transactions/sol_transfer.json")),
+            fixture(include_str!("../tests/fixtures/transactions/multi_account.json")),
+            fixture(include_str!("../tests/fixtures/transactions/parsed_transfers.json")),
+        ];
+        // Each copy gets its own signature, so the order of edge signatures is checked too
+        let transactions: Vec<Value> = (0..300).map(|i| {
+            let mut transaction = fixtures[i % fixtures.len()].clone();
+            transaction["transaction"]["signatures"] = serde_json::json!([format!("sig{}", i)]);
+            transaction
+        }).collect();
+
+        // Several threads even on a single core, so that partial graphs are merged
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        for options in [directed(), GraphOptions::default()] {
+            let mut serial = GraphBuilder::new(&options);
+            for transaction in &transactions {
+                serial.add(transaction);
+            }
+            let mut parallel = GraphBuilder::new(&options);
+            pool.install(|| {
+                parallel.add_all(&transactions[..100]);
+                parallel.add_all(&transactions[100..]);
+            });
+            assert_eq!(parallel.finish_with_kinds(), serial.finish_with_kinds());
+        }
+    }
+
+    #[test]
+    fn edges_keep_a_bounded_sample_of_their_signatures() {
+        let transactions: Vec<Value> = (0..MAX_EDGE_SIGNATURES + 5)
+            .map(|i| serde_json::json!({ "transaction": { "signatures": [format!("sig{}", i)], "message": { "accountKeys": ["A", "B"] } } }))
+            .collect();
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+
+        let edge = &graph["A"]["B"];
+        assert_eq!(edge.count as usize, transactions.len());
+        assert_eq!(edge.signatures.len(), MAX_EDGE_SIGNATURES);
+        assert_eq!(edge.signatures[..2], ["sig0".to_string(), "sig1".to_string()]);
+        assert_eq!(graph["B"]["A"].signatures, edge.signatures);
+    }
+
+    #[test]
+    fn builder_learns_node_kinds_from_account_roles() {
+        let receiver = "HN7cABqLq46Es1jh92dQQisAq662SmxELLLsHHe4YWrH";
+        let token_transfer = serde_json::json!({
+            "transaction": { "message": {
+                "header": { "numRequiredSignatures": 1 },
+                "accountKeys": ["Wallet", "SourceAta", "DestAta", "Mint", "TokenProgram"],
+                "instructions": [{ "programIdIndex": 4, "accounts": [1, 3, 2, 0] }]
+            } },
+            "meta": {
+                "preTokenBalances": [{ "accountIndex": 1, "mint": "Mint", "owner": "Wallet", "uiTokenAmount": { "uiAmount": 5.0 } }],
+                "postTokenBalances": [{ "accountIndex": 2, "mint": "Mint", "owner": "Other", "uiTokenAmount": { "uiAmount": 5.0 } }]
+            }
+        });
+        let options = GraphOptions { keep_token_accounts: true, ..GraphOptions::default() };
+        let mut builder = GraphBuilder::new(&options);
+        builder.add(&fixture(include_str!("../tests/fixtures/transactions/sol_transfer.json")));
+        builder.add(&token_transfer);
+        let (_, kinds) = builder.finish_with_kinds();
+
+        assert_eq!(kinds[SENDER], NodeKind::Wallet);
+        assert_eq!(kinds[SYSTEM_PROGRAM], NodeKind::Program);
+        assert!(!kinds.contains_key(receiver));
+        assert_eq!(kinds["Wallet"], NodeKind::Wallet);
+        assert_eq!(kinds["Other"], NodeKind::Wallet);
+        assert_eq!(kinds["SourceAta"], NodeKind::TokenAccount);
+        assert_eq!(kinds["Mint"], NodeKind::Mint);
+        assert_eq!(kinds["TokenProgram"], NodeKind::Program);
+    }
+
+    #[test]
+    fn edges_to_readonly_accounts_are_counted_and_left_out_when_writable_only() {
+        let transaction = serde_json::json!({
+            "transaction": { "message": {
+                "header": { "numRequiredSignatures": 2, "numReadonlySignedAccounts": 1, "numReadonlyUnsignedAccounts": 2 },
+                "accountKeys": ["A", "Cosigner", "C", "Program", "Sysvar"]
+            } },
+            "meta": { "loadedAddresses": { "writable": ["Loaded"], "readonly": ["Table"] } }
+        });
+
+        let graph = build_transaction_graph(std::slice::from_ref(&transaction), &directed());
+        let readonly: Vec<(&str, u32)> = ["Cosigner", "C", "Program", "Sysvar", "Loaded", "Table"].iter()
+            .map(|account| (*account, graph["A"][*account].readonly))
+            .collect();
+        assert_eq!(readonly, vec![("Cosigner", 1), ("C", 0), ("Program", 1), ("Sysvar", 1), ("Loaded", 0), ("Table", 1)]);
+
+        let graph = build_transaction_graph(&[transaction], &GraphOptions { writable_only: true, ..directed() });
+        assert_eq!(edge_list(&graph), vec![("A", "C", 1, 0), ("A", "Loaded", 1, 0)]);
+
+        let parsed = serde_json::json!({ "transaction": { "message": { "accountKeys": [
+            { "pubkey": "A", "signer": true, "writable": true },
+            { "pubkey": "Program", "signer": false, "writable": false }
+        ] } } });
+        let accounts = account_keys(&parsed).unwrap();
+        assert_eq!(account_roles(&parsed, &accounts), vec![
+            AccountRole { signer: true, writable: true },
+            AccountRole { signer: false, writable: false },
+        ]);
+    }
+
+    #[test]
+    fn edges_record_the_distinct_block_times_of_their_transactions() {
+        let transactions: Vec<Value> = [Some(300), Some(100), None, Some(300)].iter()
+            .map(|time| serde_json::json!({ "blockTime": time, "transaction": { "message": { "accountKeys": ["A", "B"] } } }))
+            .collect();
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+
+        assert_eq!(graph["A"]["B"].count, 4);
+        assert_eq!(graph["A"]["B"].times, [100, 300]);
+        assert_eq!(graph["A"]["B"].active_window(), Some((100, 300)));
+        assert_eq!(graph["A"]["B"].first_time_from(101), Some(300));
+        assert_eq!(graph["A"]["B"].first_time_from(301), None);
+
+        // Merging whole edges, as when combining graphs, keeps the times sorted and distinct
+        let mut edge = Edge { times: vec![100, 300], ..Edge::default() };
+        edge.merge(&Edge { times: vec![50, 100, 200, 400], ..Edge::default() });
+        assert_eq!(edge.times, [50, 100, 200, 300, 400]);
+    }
+
+    #[test]
+    fn edges_count_repeated_transactions() {
+        let transfer = serde_json::json!({ "transaction": { "message": { "accountKeys": ["A", "B"] } } });
+        let transactions = vec![transfer.clone(), transfer, serde_json::json!({ "transaction": { "message": { "accountKeys": ["A", "C"] } } })];
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+        assert_eq!(graph["A"]["B"].count, 2);
+        assert_eq!(graph["B"]["A"].count, 2);
+        assert_eq!(graph["A"]["C"].count, 1);
+    }
+
+    #[test]
+    fn sol_transfers_are_attributed_from_balance_changes() {
+        let transaction = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["A", "B", "C"] } },
+            "meta": { "fee": 5000, "preBalances": [10_000_000, 0, 0], "postBalances": [6_995_000, 2_000_000, 1_000_000] }
+        });
+        let options = GraphOptions { directed: true, ..GraphOptions::default() };
+
+        let graph = build_transaction_graph(std::slice::from_ref(&transaction), &options);
+        assert_eq!(graph["A"]["B"].lamports, 2_000_000);
+        assert_eq!(graph["A"]["C"].lamports, 1_000_000);
+
+        let graph = build_transaction_graph(&[transaction], &GraphOptions { min_lamports: 1_500_000, ..options });
+        assert_eq!(graph["A"].len(), 1);
+        assert!(graph["A"].contains_key("B"));
+    }
+
+    #[test]
+    fn token_transfers_link_owners_of_the_same_mint() {
+        let balance = |index: u32, owner: &str, mint: &str, amount: &str| serde_json::json!({
+            "accountIndex": index, "owner": owner, "mint": mint,
+            "uiTokenAmount": { "amount": amount, "decimals": 6 }
+        });
+        let transaction = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["A", "ataA", "ataB", "ataC"] } },
+            "meta": {
+                "preTokenBalances": [balance(1, "A", "USDC", "5000000"), balance(2, "B", "USDC", "0"), balance(3, "C", "BONK", "7")],
+                "postTokenBalances": [balance(1, "A", "USDC", "3000000"), balance(2, "B", "USDC", "2000000"), balance(3, "C", "BONK", "7")]
+            }
+        });
+
+        let options = GraphOptions { directed: true, mint: Some("USDC".to_string()), ..GraphOptions::default() };
+        let graph = build_transaction_graph(std::slice::from_ref(&transaction), &options);
+
+        assert_eq!(graph["A"].len(), 1);
+        assert_eq!(graph["A"]["B"].tokens["USDC"], 2.0);
+
+        // A token minimum drops transfers below it, without touching edges that moved SOL
+        let options = GraphOptions { min_token_amount: 2.5, ..options };
+        let mut builder = GraphBuilder::new(&options);
+        builder.add(&transaction);
+        assert_eq!(builder.dropped_edges(), 1);
+        assert!(builder.finish().get("A").is_none_or(|neighbors| neighbors.is_empty()));
+        let graph = build_transaction_graph(&[transaction], &GraphOptions { directed: true, min_token_amount: 1.5, ..GraphOptions::default() });
+        assert_eq!(graph["A"]["B"].tokens["USDC"], 2.0);
+    }
+
+    #[test]
+    fn unsplittable_tokens_moved_one_at_a_time_are_nfts() {
+        let balance = |index: u32, owner: &str, mint: &str, amount: &str, decimals: u32| serde_json::json!({
+            "accountIndex": index, "owner": owner, "mint": mint,
+            "uiTokenAmount": { "amount": amount, "decimals": decimals }
+        });
+        let transaction = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["A", "ataA", "ataB", "ataA2", "ataC"] } },
+            "meta": {
+                "preTokenBalances": [balance(1, "A", "Nft", "1", 0), balance(2, "B", "Nft", "0", 0), balance(3, "A", "USDC", "5000000", 6), balance(4, "C", "USDC", "0", 6)],
+                "postTokenBalances": [balance(1, "A", "Nft", "0", 0), balance(2, "B", "Nft", "1", 0), balance(3, "A", "USDC", "4000000", 6), balance(4, "C", "USDC", "1000000", 6)]
+            }
+        });
+
+        let graph = build_transaction_graph(std::slice::from_ref(&transaction), &directed());
+        assert_eq!(graph["A"]["B"].nfts, BTreeSet::from(["Nft".to_string()]));
+        assert!(graph["A"]["C"].nfts.is_empty());
+
+        let graph = build_transaction_graph(&[transaction], &GraphOptions { nfts_only: true, ..directed() });
+        assert_eq!(edge_list(&graph), vec![("A", "B", 1, 0)]);
+        assert_eq!(graph["A"]["B"].tokens["Nft"], 1.0);
+    }
+
+    #[test]
+    fn wrapped_sol_counts_as_sol_unless_kept_apart() {
+        let balance = |index: u32, owner: &str, amount: &str| serde_json::json!({
+            "accountIndex": index, "owner": owner, "mint": WSOL_MINT,
+            "uiTokenAmount": { "amount": amount, "decimals": 9 }
+        });
+        // A moves 2 wSOL from its token account to B's, whose lamports move along
+        let transaction = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["A", "wsolA", "wsolB"] } },
+            "meta": {
+                "fee": 5000,
+                "preBalances": [10_000_000, 3_002_039_280u64, 2_039_280],
+                "postBalances": [9_995_000, 1_002_039_280, 2_002_039_280u64],
+                "preTokenBalances": [balance(1, "A", "3000000000"), balance(2, "B", "0")],
+                "postTokenBalances": [balance(1, "A", "1000000000"), balance(2, "B", "2000000000")]
+            }
+        });
+
+        let graph = build_transaction_graph(std::slice::from_ref(&transaction), &directed());
+        assert_eq!(graph["A"]["B"].lamports, 2_000_000_000);
+        assert!(graph["A"]["B"].tokens.is_empty());
+
+        let graph = build_transaction_graph(&[transaction], &GraphOptions { keep_wsol: true, ..directed() });
+        assert_eq!(graph["A"]["B"].lamports, 0);
+        assert_eq!(graph["A"]["B"].tokens[WSOL_MINT], 2.0);
+
+        let transfers = [Transfer {
+            signature: "tx".to_string(), block_time: None, from: "A".to_string(), to: "B".to_string(),
+            kind: TransferKind::Token { mint: WSOL_MINT.to_string(), amount: 1.5 },
+        }];
+        let graph = build_transfer_graph(&transfers, &directed());
+        assert_eq!(graph["A"]["B"].lamports, 1_500_000_000);
+        assert!(graph["A"]["B"].tokens.is_empty());
+        let graph = build_transfer_graph(&transfers, &GraphOptions { keep_wsol: true, ..directed() });
+        assert_eq!(graph["A"]["B"].tokens[WSOL_MINT], 1.5);
+    }
+
+    #[test]
+    fn token_accounts_are_merged_into_their_owners() {
+        let balance = |index: u32, owner: &str, amount: &str| serde_json::json!({
+            "accountIndex": index, "owner": owner, "mint": "USDC",
+            "uiTokenAmount": { "amount": amount, "decimals": 6 }
+        });
+        let transfer = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["A", "ataA", "ataB"] } },
+            "meta": {
+                "preTokenBalances": [balance(1, "A", "5000000"), balance(2, "B", "0")],
+                "postTokenBalances": [balance(1, "A", "3000000"), balance(2, "B", "2000000")]
+            }
+        });
+        // Closing ataB refunds its rent to B and records no token balances
+        let close = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["B", "ataB"] } },
+            "meta": { "fee": 0, "preBalances": [0, 2_039_280], "postBalances": [2_039_280, 0] }
+        });
+        let transactions = vec![transfer, close];
+        let options = GraphOptions { directed: true, ..GraphOptions::default() };
+
+        let graph = build_transaction_graph(&transactions, &options);
+        assert!(!graph.contains_key("ataA") && !graph.contains_key("ataB"));
+        assert_eq!(graph["A"]["B"].tokens["USDC"], 2.0);
+        assert!(graph["B"].is_empty());
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions { keep_token_accounts: true, ..options });
+        assert!(graph["A"].contains_key("ataB"));
+        assert!(graph["B"].contains_key("ataB"));
+    }
+
+    #[test]
+    fn program_filter_skips_transactions_not_invoking_it() {
+        let transactions = vec![
+            serde_json::json!({ "transaction": { "message": {
+                "accountKeys": ["A", "B", "Dex"], "instructions": [{ "programIdIndex": 2, "accounts": [0, 1] }]
+            } } }),
+            serde_json::json!({ "transaction": { "message": {
+                "accountKeys": ["A", "C", "Other"], "instructions": [{ "programIdIndex": 2, "accounts": [0, 1] }]
+            } } }),
+        ];
+        let options = GraphOptions { directed: true, program: Some("Dex".to_string()), ..GraphOptions::default() };
+
+        let graph = build_transaction_graph(&transactions, &options);
+        assert!(graph["A"].contains_key("B"));
+        assert!(!graph["A"].contains_key("C"));
+    }
+
+    #[test]
+    fn edges_of_transactions_invoking_a_dex_are_tagged_as_swaps() {
+        let transactions = vec![
+            serde_json::json!({ "transaction": { "message": {
+                "accountKeys": ["A", "B", "Router"], "instructions": [{ "programIdIndex": 2, "accounts": [0, 1] }]
+            } } }),
+            serde_json::json!({
+                "transaction": { "message": { "accountKeys": ["A", "C", "Other", "Dex"], "instructions": [{ "programIdIndex": 2, "accounts": [0, 1] }] } },
+                "meta": { "innerInstructions": [{ "index": 0, "instructions": [{ "programIdIndex": 3, "accounts": [0, 1] }] }] }
+            }),
+        ];
+        let options = GraphOptions { directed: true, swap_programs: HashSet::from(["Dex".to_string()]), ..GraphOptions::default() };
+
+        let graph = build_transaction_graph(&transactions, &options);
+        assert!(graph["A"]["B"].swaps.is_empty());
+        assert_eq!(graph["A"]["C"].swaps, BTreeSet::from(["Dex".to_string()]));
+        assert_eq!(graph["A"]["Dex"].swaps, BTreeSet::from(["Dex".to_string()]));
+    }
+
+    #[test]
+    fn time_range_filters_on_block_time() {
+        let options = GraphOptions { from: Some(100), to: Some(200), ..GraphOptions::default() };
+        assert!(options.in_time_range(Some(100)));
+        assert!(options.in_time_range(Some(200)));
+        assert!(!options.in_time_range(Some(201)));
+        assert!(!options.in_time_range(None));
+        assert!(GraphOptions { include_unknown_time: true, ..options }.in_time_range(None));
+        assert!(GraphOptions::default().in_time_range(None));
+    }
+
+    #[test]
+    fn slot_range_filters_on_the_transaction_slot() {
+        // The fixture is at slot 250000010, as a cached transaction read with --offline would be
+        let transactions = [fixture(include_str!("../tests/fixtures/transactions/sol_transfer.json"))];
+        let exact = GraphOptions { min_slot: Some(250_000_010), max_slot: Some(250_000_010), ..directed() };
+        assert!(!build_transaction_graph(&transactions, &exact).is_empty());
+        assert!(build_transaction_graph(&transactions, &GraphOptions { min_slot: Some(250_000_011), ..directed() }).is_empty());
+        assert!(build_transaction_graph(&transactions, &GraphOptions { max_slot: Some(250_000_009), ..directed() }).is_empty());
+
+        let options = GraphOptions { min_slot: Some(100), ..GraphOptions::default() };
+        assert!(!options.in_slot_range(None));
+        assert!(GraphOptions::default().in_slot_range(None));
+    }
+
+    #[test]
+    fn transfer_graph_counts_each_transaction_once_per_pair() {
+        let sol = |signature: &str, from: &str, to: &str, lamports: u64| Transfer {
+            signature: signature.to_string(), block_time: Some(10), from: from.to_string(), to: to.to_string(),
+            kind: TransferKind::Sol { lamports },
+        };
+        let transfers = vec![
+            sol("tx1", "A", "B", 100),
+            sol("tx1", "A", "B", 50),
+            sol("tx2", "A", "B", 25),
+            Transfer { kind: TransferKind::Token { mint: "USDC".to_string(), amount: 3.5 }, ..sol("tx3", "B", "C", 0) },
+        ];
+
+        let graph = build_transfer_graph(&transfers, &GraphOptions { directed: true, ..GraphOptions::default() });
+        assert_eq!(graph["A"]["B"].count, 2);
+        assert_eq!(graph["A"]["B"].lamports, 175);
+        assert_eq!(graph["B"]["C"].tokens["USDC"], 3.5);
+
+        let graph = build_transfer_graph(&transfers, &GraphOptions { mint: Some("USDC".to_string()), ..GraphOptions::default() });
+        assert!(!graph.contains_key("A"));
+        assert!(graph["C"].contains_key("B"));
+    }
+
+    #[test]
+    fn common_counterparties_are_shared_neighbors_by_weight() {
+        let transactions: Vec<Value> = [["A", "X"], ["A", "X"], ["B", "X"], ["A", "Y"], ["Y", "B"], ["A", "Z"], ["A", "B"]].iter()
+            .map(|keys| serde_json::json!({ "transaction": { "message": { "accountKeys": keys } } }))
+            .collect();
+
+        let graph = build_transaction_graph(&transactions, &GraphOptions::default());
+        let common = common_counterparties(&graph, &["A", "B"], false);
+        assert_eq!(common, vec![
+            Counterparty { address: "X".to_string(), count: 3 },
+            Counterparty { address: "Y".to_string(), count: 2 },
+        ]);
+
+        // Inbound edges count too when the graph is directed
+        let graph = build_transaction_graph(&transactions, &GraphOptions { directed: true, ..GraphOptions::default() });
+        let common: Vec<String> = common_counterparties(&graph, &["A", "B"], true).into_iter().map(|c| c.address).collect();
+        assert_eq!(common, vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn neighbors_merge_both_directions_of_a_directed_graph() {
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "A", "B", &Edge { count: 2, lamports: 100, ..Edge::default() }, true);
+        add_edge(&mut graph, "B", "A", &Edge { count: 1, lamports: 50, ..Edge::default() }, true);
+        add_edge(&mut graph, "C", "A", &Edge { count: 1, ..Edge::default() }, true);
+
+        let neighbors = neighbors(&graph, "A", true);
+        assert_eq!(neighbors, vec![
+            Neighbor { address: "B".to_string(), edge: Edge { count: 3, lamports: 150, ..Edge::default() } },
+            Neighbor { address: "C".to_string(), edge: Edge { count: 1, ..Edge::default() } },
+        ]);
+    }
+}
@@ -0,0 +1,235 @@
+//! [`TransferProvider`] backed by the Helius enhanced-transactions API, which returns
+//! native and token transfers already decoded from each transaction.
+
+use std::time::Duration;
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use crate::error::SolConnectError;
+use crate::is_valid_pubkey;
+use crate::metrics::METRICS;
+use crate::provider::{Transfer, TransferKind, TransferProvider};
+use crate::rpc::{backoff_delay, http_client, Proxy, DEFAULT_TIMEOUT};
+
+const HELIUS_API_URL: &str = "https://api.helius.xyz";
+// The API returns at most 100 transactions per page
+const PAGE_LIMIT: usize = 100;
+
+pub struct HeliusProvider {
+    http: reqwest::Client,
+    timeout: Duration,
+    proxy: Proxy,
+    base_url: String,
+    api_key: String,
+    max_retries: u32,
+    max_transactions: usize,
+    until: Option<String>,
+    min_slot: Option<u64>,
+    max_slot: Option<u64>,
+}
+
+impl HeliusProvider {
+    pub fn new(api_key: String, max_retries: u32) -> Result<Self, SolConnectError> {
+        let proxy = Proxy::default();
+        let http = http_client(DEFAULT_TIMEOUT, &proxy)?;
+        Ok(HeliusProvider {
+            http,
+            timeout: DEFAULT_TIMEOUT,
+            proxy,
+            base_url: HELIUS_API_URL.to_string(),
+            api_key,
+            max_retries,
+            max_transactions: 10_000,
+            until: None,
+            min_slot: None,
+            max_slot: None,
+        })
+    }
+
+    /// Stops paginating an address after this many transactions (default 10,000); 0 fetches its whole history.
+    pub fn with_max_transactions(mut self, max_transactions: usize) -> Self {
+        self.max_transactions = max_transactions;
+        self
+    }
+
+    /// Gives up on a request after `timeout` instead of the default 30 seconds, then retries it.
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self, SolConnectError> {
+        self.http = http_client(timeout, &self.proxy)?;
+        self.timeout = timeout;
+        Ok(self)
+    }
+
+    /// Sends requests through `proxy` instead of the one the environment names.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Result<Self, SolConnectError> {
+        self.http = http_client(self.timeout, &proxy)?;
+        self.proxy = proxy;
+        Ok(self)
+    }
+
+    /// Only fetches transactions newer than `signature`.
+    pub fn with_until(mut self, signature: Option<String>) -> Self {
+        self.until = signature;
+        self
+    }
+
+    /// Only keeps transactions within these inclusive slot bounds, and stops paginating at the
+    /// first one before `min_slot`.
+    pub fn with_slots(mut self, min_slot: Option<u64>, max_slot: Option<u64>) -> Self {
+        self.min_slot = min_slot;
+        self.max_slot = max_slot;
+        self
+    }
+
+    async fn fetch_page(&self, address: &str, before: Option<&str>) -> Result<Vec<Value>, SolConnectError> {
+        let url = format!("{}/v0/addresses/{}/transactions", self.base_url, address);
+        let mut query = vec![("api-key", self.api_key.clone()), ("limit", PAGE_LIMIT.to_string())];
+        if let Some(before) = before {
+            query.push(("before", before.to_string()));
+        }
+        if let Some(ref until) = self.until {
+            query.push(("until", until.clone()));
+        }
+
+        let mut attempt = 0;
+        loop {
+            debug!("GET {} (before: {:?})", url, before);
+            let error = match self.http.get(&url).query(&query).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    let bytes = response.bytes().await;
+                    METRICS.record_request(0, bytes.as_ref().map_or(0, |bytes| bytes.len()));
+                    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        METRICS.record_rate_limited();
+                    }
+                    match bytes {
+                        _ if !status.is_success() => SolConnectError::Http(status),
+                        Ok(bytes) => return Ok(serde_json::from_slice::<Vec<Value>>(&bytes)?),
+                        Err(e) => e.into(),
+                    }
+                }
+                Err(e) => {
+                    METRICS.record_request(0, 0);
+                    e.into()
+                }
+            };
+
+            if !error.is_retryable() || attempt >= self.max_retries {
+                return Err(error);
+            }
+            let delay = backoff_delay(attempt);
+            warn!("Helius request for {} failed ({}), retrying in {:.1}s", address, error, delay.as_secs_f64());
+            METRICS.record_retry();
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Decodes the `nativeTransfers` and `tokenTransfers` of one enhanced transaction. Tokens of a
+/// non-fungible `tokenStandard` are NFTs.
+fn parse_transfers(transaction: &Value) -> Vec<Transfer> {
+    let signature = transaction.get("signature").and_then(|s| s.as_str()).unwrap_or_default();
+    let block_time = transaction.get("timestamp").and_then(|t| t.as_i64());
+    let mut transfers = Vec::new();
+
+    let parties = |transfer: &Value| {
+        let from = transfer.get("fromUserAccount").and_then(|a| a.as_str()).filter(|a| !a.is_empty())?;
+        let to = transfer.get("toUserAccount").and_then(|a| a.as_str()).filter(|a| !a.is_empty())?;
+        Some((from.to_string(), to.to_string()))
+    };
+
+    for native in transaction.get("nativeTransfers").and_then(|t| t.as_array()).into_iter().flatten() {
+        let lamports = native.get("amount").and_then(|a| a.as_u64()).unwrap_or_default();
+        if let Some((from, to)) = parties(native) {
+            transfers.push(Transfer { signature: signature.to_string(), block_time, from, to, kind: TransferKind::Sol { lamports } });
+        }
+    }
+
+    for token in transaction.get("tokenTransfers").and_then(|t| t.as_array()).into_iter().flatten() {
+        let mint = token.get("mint").and_then(|m| m.as_str());
+        let amount = token.get("tokenAmount").and_then(|a| a.as_f64()).unwrap_or_default();
+        let standard = token.get("tokenStandard").and_then(|s| s.as_str()).unwrap_or_default();
+        if let (Some((from, to)), Some(mint)) = (parties(token), mint) {
+            let kind = match standard {
+                "NonFungible" | "NonFungibleEdition" | "ProgrammableNonFungible" => TransferKind::Nft { mint: mint.to_string() },
+                _ => TransferKind::Token { mint: mint.to_string(), amount },
+            };
+            transfers.push(Transfer { signature: signature.to_string(), block_time, from, to, kind });
+        }
+    }
+
+    transfers
+}
+
+#[async_trait(?Send)]
+impl TransferProvider for HeliusProvider {
+    async fn fetch_transfers(&self, address: &str) -> Result<Vec<Transfer>, SolConnectError> {
+        if !is_valid_pubkey(address) {
+            return Err(SolConnectError::InvalidAddress(address.to_string()));
+        }
+        let mut transfers = Vec::new();
+        let mut before: Option<String> = None;
+
+        let mut transactions = 0;
+
+        loop {
+            let page = self.fetch_page(address, before.as_deref()).await?;
+            let mut before_min_slot = false;
+            for transaction in &page {
+                let slot = transaction.get("slot").and_then(|s| s.as_u64());
+                if slot.is_some_and(|slot| self.min_slot.is_some_and(|min| slot < min)) {
+                    before_min_slot = true;
+                    break;
+                }
+                if slot.is_some_and(|slot| self.max_slot.is_some_and(|max| slot > max)) {
+                    continue;
+                }
+                transfers.extend(parse_transfers(transaction));
+                transactions += 1;
+            }
+
+            before = page.last().and_then(|tx| tx.get("signature")).and_then(|s| s.as_str()).map(String::from);
+            if page.len() < PAGE_LIMIT || before.is_none() || before_min_slot {
+                break;
+            }
+            if self.max_transactions != 0 && transactions >= self.max_transactions {
+                warn!(
+                    "Stopped after {} transactions for {}; older transactions were not fetched and may hide connections",
+                    transactions, address
+                );
+                break;
+            }
+        }
+
+        info!("Fetched {} transfers for address {}", transfers.len(), address);
+        Ok(transfers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_native_and_token_transfers() {
+        let transaction = serde_json::json!({
+            "signature": "sig",
+            "timestamp": 1700000000,
+            "nativeTransfers": [{ "fromUserAccount": "A", "toUserAccount": "B", "amount": 1500 }],
+            "tokenTransfers": [
+                { "fromUserAccount": "B", "toUserAccount": "C", "tokenAmount": 2.5, "mint": "USDC" },
+                { "fromUserAccount": "", "toUserAccount": "C", "tokenAmount": 1.0, "mint": "USDC" },
+                { "fromUserAccount": "C", "toUserAccount": "A", "tokenAmount": 1, "mint": "Nft", "tokenStandard": "ProgrammableNonFungible" }
+            ]
+        });
+
+        let transfers = parse_transfers(&transaction);
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers[0].kind, TransferKind::Sol { lamports: 1500 });
+        assert_eq!(transfers[0].block_time, Some(1700000000));
+        assert_eq!((transfers[1].from.as_str(), transfers[1].to.as_str()), ("B", "C"));
+        assert_eq!(transfers[1].kind, TransferKind::Token { mint: "USDC".to_string(), amount: 2.5 });
+        assert_eq!(transfers[2].kind, TransferKind::Nft { mint: "Nft".to_string() });
+    }
+}
@@ -0,0 +1,163 @@
+//! Human-readable names for well-known addresses, used to annotate path and DOT output.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+
+use crate::error::SolConnectError;
+
+/// Programs, mints and exchange hot wallets that show up in most transaction graphs.
+const BUILTIN_LABELS: &[(&str, &str)] = &[
+    ("11111111111111111111111111111111", "System Program"),
+    ("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", "Token Program"),
+    ("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb", "Token-2022 Program"),
+    ("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL", "Associated Token Account Program"),
+    ("ComputeBudget111111111111111111111111111111", "Compute Budget Program"),
+    ("Vote111111111111111111111111111111111111111", "Vote Program"),
+    ("Stake11111111111111111111111111111111111111", "Stake Program"),
+    ("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr", "Memo Program"),
+    ("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo", "Memo Program v1"),
+    ("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s", "Metaplex Token Metadata"),
+    ("SysvarRent111111111111111111111111111111111", "Rent Sysvar"),
+    ("SysvarC1ock11111111111111111111111111111111", "Clock Sysvar"),
+    ("So11111111111111111111111111111111111111112", "Wrapped SOL"),
+    ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"),
+    ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "USDT"),
+    ("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM", "Binance"),
+    ("5tzFkiKscXHK5ZXCGbXZxdw7gTjjD1mBwuoFbhUvuAi9", "Binance"),
+    ("H8sMJSCQxfKiFTCfDR3DUMLPwcRbM61LGFJ8N4dK3WjS", "Coinbase"),
+    ("GJRs4FwHtemZ5ZE9x3FNvJ8TMwitKTh21yxdRPqn7npE", "Coinbase"),
+    ("FWznbcNXWQuHTawe9RxvQ2LdCENssh12dsznf4RiouN5", "Kraken"),
+];
+
+/// DEX and aggregator programs. A transaction invoking one of them is most likely a swap, so the
+/// edges it creates are tagged with the program.
+const BUILTIN_DEXES: &[(&str, &str)] = &[
+    ("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "Jupiter Aggregator v6"),
+    ("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB", "Jupiter Aggregator v4"),
+    ("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8", "Raydium AMM v4"),
+    ("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK", "Raydium CLMM"),
+    ("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C", "Raydium CPMM"),
+    ("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc", "Orca Whirlpools"),
+    ("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP", "Orca Token Swap v2"),
+    ("srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX", "OpenBook"),
+    ("opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb", "OpenBook v2"),
+    ("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo", "Meteora DLMM"),
+    ("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY", "Phoenix"),
+];
+
+/// A labels file value: a name, or a name and whether the address is a DEX program. A plain
+/// name leaves the DEX table as it is.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LabelEntry {
+    Name(String),
+    Program { name: String, dex: Option<bool> },
+}
+
+/// Maps addresses to display names. Unknown addresses have no label and print as-is. Also knows
+/// which addresses are DEX programs.
+#[derive(Clone, Debug, Default)]
+pub struct Labels {
+    names: HashMap<String, String>,
+    dexes: HashSet<String>,
+}
+
+impl Labels {
+    /// The built-in labels for well-known programs, mints and exchanges.
+    pub fn builtin() -> Self {
+        let names = BUILTIN_LABELS.iter().chain(BUILTIN_DEXES).map(|(address, name)| (address.to_string(), name.to_string())).collect();
+        let dexes = BUILTIN_DEXES.iter().map(|(address, _)| address.to_string()).collect();
+        Labels { names, dexes }
+    }
+
+    /// The built-in labels, extended and overridden by a JSON object mapping addresses to names.
+    /// A value can also be an object such as `{ "name": "Lifinity", "dex": true }`, to add
+    /// a DEX program or, with `"dex": false`, take a built-in one out of the DEX table.
+    pub fn with_file(file: &Path) -> Result<Self, SolConnectError> {
+        let contents = fs::read_to_string(file)?;
+        let entries: HashMap<String, LabelEntry> = serde_json::from_str(&contents).map_err(|e| {
+            SolConnectError::Parse(format!("labels file {} must be a JSON object of address to name: {}", file.display(), e))
+        })?;
+        let mut labels = Labels::builtin();
+        for (address, entry) in entries {
+            let name = match entry {
+                LabelEntry::Name(name) => name,
+                LabelEntry::Program { name, dex } => {
+                    match dex {
+                        Some(true) => labels.dexes.insert(address.clone()),
+                        Some(false) => labels.dexes.remove(&address),
+                        None => false,
+                    };
+                    name
+                }
+            };
+            labels.names.insert(address, name);
+        }
+        Ok(labels)
+    }
+
+    /// The addresses of the DEX programs, whose transactions are tagged as swaps.
+    pub fn dex_programs(&self) -> &HashSet<String> {
+        &self.dexes
+    }
+
+    /// Adds names, such as resolved `.sol` domains, for addresses that don't have a label yet.
+    pub fn with_domains(mut self, domains: impl IntoIterator<Item = (String, String)>) -> Self {
+        for (address, domain) in domains {
+            self.names.entry(address).or_insert(domain);
+        }
+        self
+    }
+
+    pub fn get(&self, address: &str) -> Option<&str> {
+        self.names.get(address).map(String::as_str)
+    }
+
+    /// `address`, followed by its label in parentheses when it has one.
+    pub fn annotate(&self, address: &str) -> String {
+        match self.get(address) {
+            Some(name) => format!("{} ({})", address, name),
+            None => address.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_labels_are_valid_pubkeys() {
+        for (address, _) in BUILTIN_LABELS.iter().chain(BUILTIN_DEXES) {
+            assert!(crate::is_valid_pubkey(address), "{} is not a valid pubkey", address);
+        }
+    }
+
+    #[test]
+    fn labels_file_overrides_builtin_names() {
+        let file = std::env::temp_dir().join(format!("solconnect-labels-{}.json", std::process::id()));
+        fs::write(&file, r#"{
+            "11111111111111111111111111111111": "System",
+            "Wallet": "Alice",
+            "2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c": { "name": "Lifinity", "dex": true },
+            "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX": { "name": "Serum", "dex": false },
+            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4": "Jupiter"
+        }"#).unwrap();
+        let labels = Labels::with_file(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(labels.annotate("11111111111111111111111111111111"), "11111111111111111111111111111111 (System)");
+        assert_eq!(labels.get("Wallet"), Some("Alice"));
+        assert_eq!(labels.get("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"), Some("Token Program"));
+        assert_eq!(labels.annotate("Unknown"), "Unknown");
+
+        assert_eq!(labels.get("2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c"), Some("Lifinity"));
+        let dexes = labels.dex_programs();
+        assert!(dexes.contains("2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c"));
+        assert!(!dexes.contains("srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX"));
+        assert!(dexes.contains("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"));
+        assert_eq!(labels.get("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"), Some("Jupiter"));
+    }
+}
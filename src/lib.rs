@@ -0,0 +1,54 @@
+//! Trace connections between Solana addresses: fetch their transaction history over
+//! JSON-RPC, build a graph of the accounts that transacted together, and search it for paths.
+
+pub mod cache;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod flow;
+pub mod graph;
+pub mod helius;
+pub mod labels;
+pub mod metrics;
+pub mod paths;
+pub mod provider;
+pub mod rpc;
+pub mod sns;
+pub mod stats;
+pub mod subscribe;
+#[cfg(feature = "sqlite")]
+pub mod store;
+
+use solana_sdk::pubkey::Pubkey;
+
+pub use cache::{Compression, TransactionCache};
+pub use error::SolConnectError;
+pub use flow::{pair_flows, Flow};
+pub use graph::{
+    build_transaction_graph, build_transfer_graph, common_counterparties, merge_graphs, neighbors, Counterparty, Edge, Graph,
+    GraphBuilder, GraphOptions, Neighbor, NodeKind, NodeKinds, DEFAULT_EXCLUDES, MAX_EDGE_SIGNATURES, WSOL_MINT,
+};
+pub use helius::HeliusProvider;
+pub use labels::Labels;
+pub use metrics::{Metrics, MetricsSnapshot, METRICS};
+pub use paths::{
+    astar_path, bidirectional_shortest_path, cheapest_path, find_cycles, find_paths, find_paths_dfs, find_paths_dfs_each, find_paths_each, find_temporal_paths,
+    hop_times, k_shortest_paths, k_shortest_paths_each, neighbor_rings, path_weight, rank_paths, shortest_path, Landmarks, Weight,
+};
+pub use provider::{TransactionSource, Transfer, TransferKind, TransferProvider};
+pub use rpc::{
+    get_health, get_node_version, get_transaction_details, get_transaction_details_batch, get_transaction_history,
+    get_transaction_history_with_progress, Commitment, Encoding, HistoryOptions, HttpTransport, Proxy, RpcClient, RpcTransport,
+};
+pub use sns::{is_sol_domain, primary_domain, resolve_domain};
+pub use stats::{
+    connected_components, degree_distribution, edge_count, top_degrees, without_hubs, without_leaves, DegreeBucket, DegreeDistribution, NodeDegree,
+};
+pub use subscribe::{websocket_url, LogNotification, LogSubscription};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+
+/// Whether `address` is a valid base58-encoded Solana public key.
+pub fn is_valid_pubkey(address: &str) -> bool {
+    address.parse::<Pubkey>().is_ok()
+}
@@ -1,226 +1,2738 @@
-use std::collections::{HashMap, VecDeque, HashSet};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt::Write as _;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use futures::stream::{self, StreamExt};
+use futures::future::try_join_all;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use structopt::StructOpt;
-use reqwest;
-use serde_json::Value;
-use solana_sdk::pubkey::Pubkey;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use tokio::net::TcpListener;
+use tokio::signal;
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn, Level};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+use solconnect::cache::{clear_directory, default_cache_dir, directory_usage, CacheUsage, CACHE_CLUSTERS};
+use solconnect::config::Config;
+use solconnect::metrics::{format_bytes, serve_prometheus, METRICS};
+use solconnect::export::{
+    graph_document, graph_to_csv, graph_to_dot, graph_to_gexf, graph_to_graphml, lamports_to_sol, load_graph, hop_fees, hop_windows, path_edges,
+    path_labels, path_subgraph, paths_to_csv, save_graph, sankey_document, short_address, write_dot, write_matrix, AddressComponent, AnalysisResult,
+    CommonResult, ComponentPair, ComponentsResult, CycleLine, CycleResult, FlowResult, MatrixFormat, NeighborsResult, PairFlows, PairResult, PathLine,
+    Ring, WatchAlert,
+};
+use solconnect::{
+    astar_path, bidirectional_shortest_path, build_transaction_graph, build_transfer_graph, cheapest_path, common_counterparties,
+    connected_components, degree_distribution, edge_count, find_cycles, find_paths_dfs_each, find_paths_each, find_temporal_paths, get_health,
+    get_node_version, get_transaction_details, hop_times, is_sol_domain, is_valid_pubkey, k_shortest_paths_each, merge_graphs, neighbor_rings,
+    neighbors, pair_flows, primary_domain, rank_paths, resolve_domain, top_degrees, websocket_url, without_hubs, without_leaves, Commitment,
+    Compression, DegreeDistribution, Edge, Encoding, Graph, GraphBuilder, GraphOptions, HeliusProvider, HistoryOptions, HttpTransport, Labels,
+    Landmarks, LogSubscription, NodeDegree, NodeKinds, Proxy, RpcClient, SolConnectError, TransactionCache, TransactionSource, TransferProvider,
+    Weight, DEFAULT_EXCLUDES,
+};
 
 #[derive(StructOpt)]
+#[structopt(name = "solconnect", about = "Trace connections between Solana addresses")]
 struct Cli {
-    address1: String,
-    address2: String,
+    /// Solana RPC endpoint; overrides the SOLANA_RPC_ENDPOINT environment variable. Repeat the flag or
+    /// separate endpoints with commas to fail over to the next one when an endpoint keeps failing
+    #[structopt(long, global = true, use_delimiter = true, number_of_values = 1)]
+    rpc_url: Vec<String>,
+
+    /// Public RPC endpoint of this cluster to use when neither --rpc-url nor SOLANA_RPC_ENDPOINT is set,
+    /// in which case its cache is used too; otherwise the cache's cluster is guessed from the endpoint
+    #[structopt(long, global = true, possible_values = &["mainnet", "mainnet-beta", "devnet", "testnet"])]
+    cluster: Option<Cluster>,
+
+    /// Commitment level for signature and transaction requests
+    #[structopt(long, global = true, default_value = "finalized", possible_values = &["processed", "confirmed", "finalized"])]
+    commitment: Commitment,
+
+    /// Encoding of getTransaction results: json, or json-parsed to read transfers from decoded instructions
+    #[structopt(long, global = true, default_value = "json", possible_values = &["json", "json-parsed"])]
+    encoding: Encoding,
+
+    /// Where transaction data comes from: raw JSON-RPC or the Helius enhanced-transactions API
+    #[structopt(long, global = true, default_value = "rpc", possible_values = &["rpc", "helius"])]
+    provider: Provider,
+
+    /// API key for the selected provider; prefer the environment variable to keep it out of shell history
+    #[structopt(long, global = true, env = "SOLCONNECT_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Request header carrying --api-key for --provider rpc (default: Authorization, as a bearer token)
+    #[structopt(long, global = true)]
+    auth_header: Option<String>,
+
+    /// Maximum number of signatures fetched per address; 0 fetches the whole history
+    #[structopt(long, global = true, default_value = "10000")]
+    max_signatures: usize,
+
+    /// Only fetch transactions newer than this signature, e.g. the newest one seen by a previous run
+    #[structopt(long, global = true)]
+    until: Option<String>,
+
+    /// Skip transactions in slots before this one; their details are never fetched, and cached ones
+    /// are left out of the graph
+    #[structopt(long, global = true)]
+    min_slot: Option<u64>,
+
+    /// Skip transactions in slots after this one, fetched or cached, and require the node to have reached it
+    #[structopt(long, global = true)]
+    max_slot: Option<u64>,
+
+    /// Maximum number of RPC requests in flight at once, across history and detail fetching
+    #[structopt(long, global = true, default_value = "10", parse(try_from_str = parse_concurrency))]
+    concurrency: usize,
+
+    /// Number of getTransaction calls sent together in one JSON-RPC batch request
+    #[structopt(long, global = true, default_value = "1", parse(try_from_str = parse_batch_size))]
+    batch_size: usize,
+
+    /// Number of times a transient RPC failure (network error, 429, 5xx, node behind) is retried
+    #[structopt(long, global = true, default_value = "5")]
+    max_retries: u32,
+
+    /// Seconds to wait for a single request before retrying it
+    #[structopt(long, global = true, default_value = "30", parse(try_from_str = parse_timeout))]
+    timeout: Duration,
+
+    /// Send RPC and provider requests through this HTTP or HTTPS proxy instead of the one named by
+    /// HTTP_PROXY/HTTPS_PROXY
+    #[structopt(long, global = true)]
+    proxy: Option<String>,
+
+    /// Connect directly, ignoring HTTP_PROXY/HTTPS_PROXY
+    #[structopt(long, global = true, conflicts_with = "proxy")]
+    no_proxy: bool,
+
+    /// Directory for cached signature histories and transaction details (default: $XDG_CACHE_HOME/solconnect)
+    #[structopt(long, global = true, parse(from_os_str))]
+    cache_dir: Option<PathBuf>,
+
+    /// Always fetch transaction details from RPC, neither reading nor writing the cache
+    #[structopt(long, global = true)]
+    no_cache: bool,
+
+    /// How new entries of the cache directory are written; entries written either way are read
+    #[structopt(long, global = true, default_value = "gzip", possible_values = &["none", "gzip"])]
+    cache_compression: Compression,
+
+    /// Keep the cache, and the graph built from it, in this SQLite database instead of the cache directory
+    #[cfg(feature = "sqlite")]
+    #[structopt(long, global = true, parse(from_os_str), conflicts_with_all = &["cache-dir", "no-cache"])]
+    db: Option<PathBuf>,
+
+    /// Only fetch the signature lists and report how much detail fetching the command would do
+    #[structopt(long, global = true)]
+    dry_run: bool,
+
+    /// Don't check that the RPC endpoint answers before starting
+    #[structopt(long, global = true)]
+    skip_health_check: bool,
+
+    /// JSON file mapping addresses to names, added to and overriding the built-in labels
+    #[structopt(long, global = true, parse(from_os_str))]
+    labels: Option<PathBuf>,
+
+    /// Show the .sol domain of addresses in the results, looked up in the Solana Name Service
+    #[structopt(long, global = true)]
+    sns: bool,
+
+    /// Print addresses as links to this explorer: solscan, solana-explorer, or a URL template containing {address}
+    #[structopt(long, global = true)]
+    links: Option<Explorer>,
+
+    /// Read option defaults from this TOML file instead of the first solconnect.toml found in the
+    /// working directory or $XDG_CONFIG_HOME/solconnect
+    #[structopt(long, global = true, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Serve the run's request, cache and timing counters in the Prometheus text format on this
+    /// port of 127.0.0.1 while the command runs
+    #[structopt(long, global = true)]
+    metrics_port: Option<u16>,
+
+    /// Hide progress bars and spinners
+    #[structopt(short, long, global = true)]
+    quiet: bool,
+
+    /// Log more detail to stderr: -v for every RPC request, -vv for request and response bodies
+    #[structopt(short, long, global = true, parse(from_occurrences))]
+    verbose: u8,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+// Parsed once per run, so the size of the paths options is no concern
+#[allow(clippy::large_enum_variant)]
+#[derive(StructOpt)]
+enum Command {
+    /// Fetch the signature history and transaction details of addresses into the cache
+    Fetch {
+        /// Addresses whose transactions are fetched
+        #[structopt(required = true, min_values = 1)]
+        addresses: Vec<String>,
+    },
+    /// Find paths between every pair of addresses
+    Paths(PathsArgs),
+    /// Write the graph of previously fetched addresses as DOT, JSON, CSV, GEXF or GraphML
+    Export(ExportArgs),
+    /// List the addresses directly connected to one address
+    Neighbors(NeighborsArgs),
+    /// Keep running and report whenever new transactions connect a pair of addresses
+    Watch(WatchArgs),
+    /// Load a graph once and explore it interactively with path, neighbors, degree and common queries
+    Repl(ReplArgs),
+    /// Report or delete what the cache directory holds
+    Cache(CacheCommand),
+}
+
+#[derive(StructOpt)]
+enum CacheCommand {
+    /// Count the cached entries of each cluster and the disk space they take
+    Info,
+    /// Delete cached entries: those of --cluster only when it is given, otherwise those of every cluster
+    Clear {
+        /// Only delete entries written longer ago than this, such as 30d, 12h, 2w or 3mo
+        #[structopt(long, parse(try_from_str = parse_age))]
+        older_than: Option<Duration>,
+    },
+}
+
+#[derive(StructOpt)]
+struct PathsArgs {
+    /// Addresses to check for connections; every pair of them is analyzed
+    #[structopt(required_unless = "pairs-file", min_values = 1)]
+    addresses: Vec<String>,
+
+    /// Analyze only the pairs listed in this file, one `address1,address2` per line, or read them
+    /// from stdin with `-`. The graph is built once from the histories of every listed address
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["addresses", "quick", "common", "cycles"])]
+    pairs_file: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    graph: GraphArgs,
+
+    /// Build the graph only from data cached by `fetch`, without any RPC calls
+    #[structopt(long)]
+    offline: bool,
+
+    /// Read the graph from a file written by --save-graph instead of building it; graph options are ignored
+    #[structopt(long, parse(from_os_str), conflicts_with = "offline")]
+    load_graph: Option<PathBuf>,
+
+    /// Write the built graph to this file, to be reused with --load-graph
+    #[structopt(long, parse(from_os_str))]
+    save_graph: Option<PathBuf>,
+
+    /// Fetch the first address's history alone and stop there if the second address appears in it
+    #[structopt(long, conflicts_with_all = &["offline", "load-graph", "save-graph", "common"])]
+    quick: bool,
+
+    /// Maximum number of addresses in a reported path (must be at least 1)
+    #[structopt(long, default_value = "50", parse(try_from_str = parse_max_depth))]
+    max_depth: usize,
+
+    /// Don't route paths through nodes linked to more than this many addresses; they can still be endpoints
+    #[structopt(long)]
+    max_node_degree: Option<usize>,
+
+    /// Before searching, repeatedly remove addresses linked to only one other address, which no
+    /// path between the inputs can pass through
+    #[structopt(long, conflicts_with = "cycles")]
+    prune_leaves: bool,
+
+    /// Output format for the discovered paths: text, one JSON document, or ndjson with one JSON
+    /// object per path, printed as the search finds it, in search order rather than ranked
+    #[structopt(long, default_value = "text", possible_values = &["text", "json", "ndjson"])]
+    format: OutputFormat,
+
+    /// Write the transaction graph to this file in Graphviz DOT format
+    #[structopt(long, parse(from_os_str))]
+    dot: Option<PathBuf>,
+
+    /// Write the transaction graph to this file in GEXF format, for Gephi
+    #[structopt(long, parse(from_os_str))]
+    gexf: Option<PathBuf>,
+
+    /// Write the transaction graph to this file in GraphML format
+    #[structopt(long, parse(from_os_str))]
+    graphml: Option<PathBuf>,
+
+    /// Write the value moved along the discovered paths to this file as a d3-sankey nodes/links
+    /// JSON document, in SOL or, with --mint, in that token
+    #[structopt(long, parse(from_os_str), requires = "directed", conflicts_with_all = &["common", "cycles", "components", "flow"])]
+    sankey: Option<PathBuf>,
+
+    /// Write the graph's adjacency matrix to this file as CSV, with the address of every row and
+    /// column in <file stem>.nodes.csv next to it
+    #[structopt(long, parse(from_os_str))]
+    matrix: Option<PathBuf>,
+
+    /// Layout of --matrix: dense rows of counts, or sparse row,col,weight,lamports lines
+    /// (default: dense up to 2000 nodes, sparse above)
+    #[structopt(long, requires = "matrix", possible_values = &["dense", "sparse"])]
+    matrix_format: Option<MatrixFormat>,
+
+    /// Write the graph's edges to this file as CSV rows of source, target and transaction count
+    #[structopt(long, parse(from_os_str))]
+    csv_edges: Option<PathBuf>,
+
+    /// Write the discovered paths to this file as CSV, one row per path with a column per hop
+    #[structopt(long, parse(from_os_str), conflicts_with = "common")]
+    csv_paths: Option<PathBuf>,
+
+    /// Write only the addresses and edges along the discovered paths to this file, as JSON if its
+    /// name ends in .json and as Graphviz DOT otherwise
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["common", "flow"])]
+    paths_subgraph: Option<PathBuf>,
+
+    /// Only report a single minimum-length path
+    #[structopt(long)]
+    shortest: bool,
+
+    /// Report a single minimum-length path found by A* search, which heads for the target using
+    /// hop distances to a few landmark nodes and explores far less of a large graph
+    #[structopt(long, conflicts_with_all = &["shortest", "k", "temporal", "common", "flow", "cycles"])]
+    astar: bool,
+
+    /// Only report paths that move forward in time, each hop happening no earlier than the one before it
+    #[structopt(long, conflicts_with_all = &["shortest", "k", "quick"])]
+    temporal: bool,
+
+    /// Under each path, list the transactions linking every pair of consecutive addresses
+    #[structopt(long)]
+    why: bool,
+
+    /// Under each path, show when every pair of consecutive addresses first and last interacted
+    #[structopt(long)]
+    timeline: bool,
+
+    /// Under each path, list the fees paid by the transactions linking every pair of consecutive
+    /// addresses, read from the cached transaction details
+    #[structopt(long)]
+    fees: bool,
+
+    /// Report a single path that costs the least under this weight instead of one with the fewest
+    /// hops (Dijkstra's algorithm): `value` follows the hops that moved the most SOL, `count` those
+    /// backed by the most transactions, and `hops` finds a shortest path
+    #[structopt(long, possible_values = &["hops", "value", "count"], conflicts_with_all = &["shortest", "astar", "k", "temporal", "common", "flow", "components", "cycles"])]
+    weight: Option<Weight>,
+
+    /// Enumerate the paths depth-first, stopping once --max-paths of them have been found, so a
+    /// dense graph can't blow up the search
+    #[structopt(long, conflicts_with_all = &["shortest", "astar", "k", "temporal", "weight", "common", "flow", "components", "cycles"])]
+    dfs: bool,
+
+    /// Report the k shortest loopless paths, ordered by length (Yen's algorithm)
+    #[structopt(long, conflicts_with = "shortest")]
+    k: Option<usize>,
+
+    /// Report at most this many paths per pair, after ordering them by hop count and then by the
+    /// number of transactions along them. With --dfs or ndjson, the search itself stops at this many
+    #[structopt(long)]
+    max_paths: Option<usize>,
+
+    /// Instead of searching for paths, list the addresses that all inputs transacted with directly
+    #[structopt(long, conflicts_with_all = &["shortest", "k"])]
+    common: bool,
+
+    /// Instead of searching for paths, report how much SOL and each token moved between every pair,
+    /// directly and through intermediaries
+    #[structopt(long, requires = "directed", conflicts_with_all = &["shortest", "k", "common", "temporal"])]
+    flow: bool,
+
+    /// Instead of searching for paths, split the graph into its connected components and report
+    /// which one each address lands in, and whether each pair shares one
+    #[structopt(long, conflicts_with_all = &["shortest", "astar", "k", "common", "temporal", "flow", "cycles"])]
+    components: bool,
+
+    /// Instead of searching for paths, list the cycles of up to --max-depth addresses that pass
+    /// through an input address, such as funds that came back to where they left
+    #[structopt(long, requires = "directed", conflicts_with_all = &["shortest", "k", "common", "temporal", "flow", "quick"])]
+    cycles: bool,
+
+    /// Also print the highest-degree nodes of the graph, which are usually exchanges or programs
+    #[structopt(long)]
+    stats: bool,
+
+    /// Number of hub nodes listed by --stats
+    #[structopt(long, default_value = "10")]
+    top: usize,
+}
+
+#[derive(StructOpt)]
+struct ExportArgs {
+    /// Addresses whose cached transactions make up the graph
+    #[structopt(required = true, min_values = 1)]
+    addresses: Vec<String>,
+
+    #[structopt(flatten)]
+    graph: GraphArgs,
+
+    /// Output format for the graph
+    #[structopt(long, default_value = "dot", possible_values = &["dot", "json", "csv", "gexf", "graphml"])]
+    format: ExportFormat,
+
+    /// File to write the graph to (default: stdout)
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+#[derive(StructOpt)]
+struct NeighborsArgs {
+    /// Address whose direct connections are listed
+    address: String,
+
+    #[structopt(flatten)]
+    graph: GraphArgs,
+
+    /// Build the graph only from data cached by `fetch`, without any RPC calls
+    #[structopt(long)]
+    offline: bool,
+
+    /// Also list the addresses up to this many hops away, grouped by distance
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_degree))]
+    degree: usize,
+
+    /// Output format for results: text, one JSON document, or ndjson with one JSON object per neighbor
+    #[structopt(long, default_value = "text", possible_values = &["text", "json", "ndjson"])]
+    format: OutputFormat,
+}
+
+#[derive(StructOpt)]
+struct ReplArgs {
+    /// Addresses whose cached transactions make up the graph
+    #[structopt(required_unless = "load-graph", min_values = 1)]
+    addresses: Vec<String>,
+
+    #[structopt(flatten)]
+    graph: GraphArgs,
+
+    /// Read the graph from a file written by --save-graph instead of building it from the cache
+    #[structopt(long, parse(from_os_str), conflicts_with = "addresses")]
+    load_graph: Option<PathBuf>,
+
+    /// Maximum number of addresses in a path found by `path` (must be at least 1)
+    #[structopt(long, default_value = "50", parse(try_from_str = parse_max_depth))]
+    max_depth: usize,
+}
+
+#[derive(StructOpt)]
+struct WatchArgs {
+    /// Addresses to watch; every pair of them is checked after each new transaction
+    #[structopt(required = true, min_values = 2)]
+    addresses: Vec<String>,
+
+    #[structopt(flatten)]
+    graph: GraphArgs,
+
+    /// Build the starting graph only from data cached by `fetch` instead of fetching the histories
+    #[structopt(long)]
+    offline: bool,
+
+    /// Maximum number of addresses in a reported path (must be at least 1)
+    #[structopt(long, default_value = "50", parse(try_from_str = parse_max_depth))]
+    max_depth: usize,
+
+    /// WebSocket endpoint to subscribe on (default: the first RPC endpoint with a ws:// or wss:// scheme)
+    #[structopt(long)]
+    ws_url: Option<String>,
+
+    /// Output format for alerts: text, or one JSON object per line (json and ndjson are the same here)
+    #[structopt(long, default_value = "text", possible_values = &["text", "json", "ndjson"])]
+    format: OutputFormat,
+}
+
+/// Options controlling which transactions and edges make up the graph.
+#[derive(StructOpt)]
+struct GraphArgs {
+    /// Only follow edges from a transaction's sender (first account) to its other accounts
+    #[structopt(long)]
+    directed: bool,
+
+    /// Skip edges that moved less than this much SOL within a transaction, unless they moved enough of a token
+    #[structopt(long)]
+    min_sol: Option<f64>,
+
+    /// Skip edges that moved less than this amount of every token within a transaction, unless they
+    /// moved enough SOL
+    #[structopt(long)]
+    min_token: Option<f64>,
+
+    /// Only build edges from transfers of this SPL token mint
+    #[structopt(long)]
+    mint: Option<String>,
+
+    /// Only build edges from NFT transfers: tokens without decimals moved one at a time
+    #[structopt(long)]
+    nfts_only: bool,
+
+    /// Only build edges from transactions with an instruction invoking this program id
+    #[structopt(long)]
+    program: Option<String>,
+
+    /// Ignore transactions before this time (unix seconds or RFC3339)
+    #[structopt(long, parse(try_from_str = parse_timestamp))]
+    from: Option<i64>,
+
+    /// Ignore transactions after this time (unix seconds or RFC3339)
+    #[structopt(long, parse(try_from_str = parse_timestamp))]
+    to: Option<i64>,
+
+    /// Ignore transactions older than this, such as 24h, 7d or 3mo; a shortcut for --from
+    #[structopt(long, parse(try_from_str = parse_age), conflicts_with = "from")]
+    since: Option<Duration>,
+
+    /// Keep transactions without a blockTime when --from/--to/--since is given
+    #[structopt(long)]
+    include_unknown_time: bool,
+
+    /// Also build edges from transactions that failed, whose effects were reverted
+    #[structopt(long)]
+    include_failed: bool,
+
+    /// Keep token accounts as separate nodes instead of merging them into their owner wallets
+    #[structopt(long)]
+    keep_token_accounts: bool,
+
+    /// Keep wrapped SOL a token of its own instead of counting its transfers as SOL
+    #[structopt(long)]
+    no_wsol_merge: bool,
+
+    /// Only link a transaction's first account to the accounts it could write, not to those it
+    /// merely read, such as programs and config accounts
+    #[structopt(long)]
+    writable_only: bool,
+
+    /// Leave this address out of the graph, like the programs and sysvars excluded by default; can be repeated
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<String>,
+
+    /// Keep the System Program, token programs, sysvars and other accounts nearly every transaction references
+    #[structopt(long)]
+    no_default_excludes: bool,
+}
+
+impl GraphArgs {
+    // Transactions invoking the DEX programs of `labels` are tagged as swaps. The slot bounds are
+    // global, and also keep transactions read from the cache within --min-slot and --max-slot.
+    fn options(&self, args: &Cli, labels: &Labels) -> GraphOptions {
+        GraphOptions {
+            directed: self.directed,
+            min_lamports: self.min_sol.map(|sol| (sol * LAMPORTS_PER_SOL as f64) as u64).unwrap_or(0),
+            min_token_amount: self.min_token.unwrap_or(0.0),
+            mint: self.mint.clone(),
+            nfts_only: self.nfts_only,
+            program: self.program.clone(),
+            // parse_age keeps an age within i64 seconds; one reaching past the earliest timestamp bounds nothing
+            from: self.since
+                .map(|age| i64::try_from(age.as_secs()).ok().and_then(|age| chrono::Utc::now().timestamp().checked_sub(age)).unwrap_or(i64::MIN))
+                .or(self.from),
+            to: self.to,
+            min_slot: args.min_slot,
+            max_slot: args.max_slot,
+            include_unknown_time: self.include_unknown_time,
+            include_failed: self.include_failed,
+            keep_token_accounts: self.keep_token_accounts,
+            keep_wsol: self.no_wsol_merge,
+            writable_only: self.writable_only,
+            exclude: DEFAULT_EXCLUDES.iter()
+                .filter(|_| !self.no_default_excludes)
+                .map(|address| address.to_string())
+                .chain(self.exclude.iter().cloned())
+                .collect(),
+            swap_programs: labels.dex_programs().clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// One JSON object per line, each printed as soon as it is known
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            other => Err(format!("unknown output format: {}", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Dot,
+    Json,
+    Csv,
+    Gexf,
+    Graphml,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "dot" => Ok(ExportFormat::Dot),
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "gexf" => Ok(ExportFormat::Gexf),
+            "graphml" => Ok(ExportFormat::Graphml),
+            other => Err(format!("unknown export format: {}", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Provider {
+    Rpc,
+    Helius,
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "rpc" => Ok(Provider::Rpc),
+            "helius" => Ok(Provider::Helius),
+            other => Err(format!("unknown provider: {}", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+}
+
+impl Cluster {
+    fn name(self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "mainnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+        }
+    }
+
+    fn url(self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Testnet => "https://api.testnet.solana.com",
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mainnet" | "mainnet-beta" => Ok(Cluster::Mainnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            other => Err(format!("unknown cluster: {}", other)),
+        }
+    }
+}
+
+/// A block explorer that text output can link addresses to.
+#[derive(Clone, PartialEq)]
+enum Explorer {
+    Solscan,
+    /// explorer.solana.com
+    Official,
+    /// A URL template in which `{address}` is replaced by the address, or `{signature}` by a
+    /// transaction signature; the placeholder not being filled in is left empty
+    Custom(String),
+}
+
+// Both public explorers select a cluster other than mainnet with the same query parameter.
+fn cluster_query(cluster: Option<Cluster>) -> &'static str {
+    match cluster {
+        Some(Cluster::Devnet) => "?cluster=devnet",
+        Some(Cluster::Testnet) => "?cluster=testnet",
+        Some(Cluster::Mainnet) | None => "",
+    }
+}
+
+impl Explorer {
+    fn account_url(&self, address: &str, cluster: Option<Cluster>) -> String {
+        let query = cluster_query(cluster);
+        match self {
+            Explorer::Solscan => format!("https://solscan.io/account/{}{}", address, query),
+            Explorer::Official => format!("https://explorer.solana.com/address/{}{}", address, query),
+            Explorer::Custom(template) => template.replace("{address}", address).replace("{signature}", ""),
+        }
+    }
+
+    // A custom template without a `{signature}` placeholder only links addresses, so the
+    // signature is shown as it is.
+    fn transaction_url(&self, signature: &str, cluster: Option<Cluster>) -> String {
+        let query = cluster_query(cluster);
+        match self {
+            Explorer::Solscan => format!("https://solscan.io/tx/{}{}", signature, query),
+            Explorer::Official => format!("https://explorer.solana.com/tx/{}{}", signature, query),
+            Explorer::Custom(template) if template.contains("{signature}") => template.replace("{signature}", signature).replace("{address}", ""),
+            Explorer::Custom(_) => signature.to_string(),
+        }
+    }
+}
+
+impl FromStr for Explorer {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "solscan" => Ok(Explorer::Solscan),
+            "solana-explorer" => Ok(Explorer::Official),
+            template if template.contains("{address}") => Ok(Explorer::Custom(template.to_string())),
+            other => Err(format!("unknown explorer {}; use solscan, solana-explorer, or a URL template containing {{address}}", other)),
+        }
+    }
+}
+
+fn parse_max_depth(value: &str) -> Result<usize, String> {
+    let depth: usize = value.parse().map_err(|e| format!("invalid depth: {}", e))?;
+    if depth < 1 {
+        return Err("max depth must be at least 1".to_string());
+    }
+    Ok(depth)
+}
+
+fn parse_degree(value: &str) -> Result<usize, String> {
+    let degree: usize = value.parse().map_err(|e| format!("invalid degree: {}", e))?;
+    if degree < 1 {
+        return Err("degree must be at least 1".to_string());
+    }
+    Ok(degree)
+}
+
+fn parse_timestamp(value: &str) -> Result<i64, String> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|time| time.timestamp())
+        .map_err(|e| format!("expected unix seconds or an RFC3339 timestamp: {}", e))
+}
+
+fn parse_batch_size(value: &str) -> Result<usize, String> {
+    let size: usize = value.parse().map_err(|e| format!("invalid batch size: {}", e))?;
+    if size < 1 {
+        return Err("batch size must be at least 1".to_string());
+    }
+    Ok(size)
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("invalid timeout: {}", e))?;
+    if !(seconds > 0.0 && seconds.is_finite()) {
+        return Err("timeout must be a positive number of seconds".to_string());
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+// A month is 30 days and a year 365, which is close enough for scoping a search. An age too long to
+// subtract from a unix timestamp is rejected, like a count that overflows.
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid age {}: expected a number followed by s, m, h, d, w, mo or y", value);
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (count, unit) = value.split_at(split);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        "mo" => 30 * 86_400,
+        "y" => 365 * 86_400,
+        _ => return Err(invalid()),
+    };
+    count.checked_mul(unit_seconds)
+        .filter(|seconds| i64::try_from(*seconds).is_ok())
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age {} is out of range", value))
+}
+
+fn parse_concurrency(value: &str) -> Result<usize, String> {
+    let concurrency: usize = value.parse().map_err(|e| format!("invalid concurrency: {}", e))?;
+    if concurrency < 1 {
+        return Err("concurrency must be at least 1".to_string());
+    }
+    Ok(concurrency)
+}
+
+// Resolves the endpoints in order of precedence: --rpc-url, SOLANA_RPC_ENDPOINT, the --cluster public endpoint.
+// The first endpoint is the primary one and the rest are fallbacks.
+fn get_rpc_endpoints(rpc_urls: &[String], cluster: Option<Cluster>) -> Vec<String> {
+    if !rpc_urls.is_empty() {
+        return rpc_urls.to_vec();
+    }
+    match env::var("SOLANA_RPC_ENDPOINT") {
+        Ok(urls) => urls.split(',').map(str::trim).filter(|url| !url.is_empty()).map(String::from).collect(),
+        Err(_) => {
+            if cluster.is_none() {
+                warn!("SOLANA_RPC_ENDPOINT environment variable not set. Using default endpoint.");
+            }
+            vec![cluster.unwrap_or(Cluster::Mainnet).url().to_string()]
+        }
+    }
+}
+
+fn progress_bar(len: u64, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(len);
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} [{elapsed_precise}] {wide_bar} {pos}/{len} ({per_sec}, ETA {eta})")
+            .expect("valid progress template"),
+    );
+    bar
+}
+
+// Turns true on the first Ctrl+C. A crawl that sees it stops sending requests and carries on
+// with what it has fetched, so the cache is kept and paths are still searched.
+static INTERRUPT: OnceLock<watch::Receiver<bool>> = OnceLock::new();
+
+// Installs the Ctrl+C handler: the first press interrupts the crawl, a second one exits at once.
+fn handle_interrupts() {
+    let (sender, receiver) = watch::channel(false);
+    if INTERRUPT.set(receiver).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_err() {
+            return;
+        }
+        warn!("Interrupted; finishing with the data fetched so far (press Ctrl+C again to quit)");
+        let _ = sender.send(true);
+        if signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
+
+// Resolves once Ctrl+C has been pressed, and never if no handler is installed.
+async fn interrupted() {
+    if let Some(receiver) = INTERRUPT.get() {
+        if receiver.clone().wait_for(|&interrupted| interrupted).await.is_ok() {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+fn is_interrupted() -> bool {
+    INTERRUPT.get().is_some_and(|receiver| *receiver.borrow())
+}
+
+// Paginates through an address's signatures behind a spinner showing the running count.
+// Several addresses are fetched at once, so each spinner gets its own line in `progress`.
+// Returns `None` if the crawl is interrupted before the history is complete.
+async fn fetch_history(source: &dyn TransactionSource, address: &str, options: &HistoryOptions, progress: &MultiProgress) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+    let spinner = progress.add(ProgressBar::new_spinner());
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner.set_message(format!("Fetching signatures for {}", address));
+
+    let on_page = |count: usize| spinner.set_message(format!("Fetching signatures for {}: {} so far", address, count));
+    let signatures = tokio::select! {
+        signatures = source.signatures_for_address(address, options, &on_page) => Some(signatures),
+        _ = interrupted() => None,
+    };
+    spinner.finish_and_clear();
+
+    let Some(signatures) = signatures.transpose()? else {
+        progress.suspend(|| warn!("Leaving out {}, whose history was still being fetched", address));
+        return Ok(None);
+    };
+    progress.suspend(|| info!("Fetched {} transactions for address {}", signatures.len(), address));
+    Ok(Some(signatures))
+}
+
+fn proxy(args: &Cli) -> Proxy {
+    match args.proxy {
+        Some(ref url) => Proxy::Url(url.clone()),
+        None if args.no_proxy => Proxy::Direct,
+        None => Proxy::Environment,
+    }
+}
+
+fn rpc_client(args: &Cli) -> Result<RpcClient, Box<dyn Error>> {
+    if args.api_key.is_none() && args.auth_header.is_some() {
+        warn!("--auth-header has no effect without --api-key");
+    }
+    let transport = |endpoint: String| -> Result<HttpTransport, Box<dyn Error>> {
+        let transport = HttpTransport::new(endpoint)?.with_timeout(args.timeout)?.with_proxy(proxy(args))?;
+        Ok(match args.api_key {
+            Some(ref api_key) => transport.with_auth_header(args.auth_header.as_deref().unwrap_or("Authorization"), api_key)?,
+            None => transport,
+        })
+    };
+
+    let mut endpoints = get_rpc_endpoints(&args.rpc_url, args.cluster).into_iter();
+    let primary = endpoints.next().ok_or("SOLANA_RPC_ENDPOINT lists no endpoint")?;
+    let mut rpc = RpcClient::with_transport(transport(primary)?, args.max_retries);
+    for fallback in endpoints {
+        rpc = rpc.with_fallback(transport(fallback)?);
+    }
+    Ok(rpc.with_commitment(args.commitment).with_encoding(args.encoding).with_max_in_flight(args.concurrency))
+}
+
+// The cluster whose cache this run reads and fills: --cluster, or else a guess from the first
+// endpoint's URL, since providers name their devnet and testnet endpoints after the cluster.
+fn cache_cluster(args: &Cli) -> &'static str {
+    // The cluster is that of the endpoint in use, which is --cluster's own only when neither
+    // --rpc-url nor SOLANA_RPC_ENDPOINT picks another
+    let endpoint = match args.rpc_url.first().cloned().or_else(|| env::var("SOLANA_RPC_ENDPOINT").ok()) {
+        Some(endpoint) => endpoint.to_ascii_lowercase(),
+        None => return args.cluster.map_or("mainnet", Cluster::name),
+    };
+    if endpoint.contains("devnet") {
+        "devnet"
+    } else if endpoint.contains("testnet") {
+        "testnet"
+    } else if endpoint.contains("localhost") || endpoint.contains("127.0.0.1") {
+        "localnet"
+    } else {
+        "mainnet"
+    }
+}
+
+fn open_cache(args: &Cli) -> Result<Option<TransactionCache>, Box<dyn Error>> {
+    if args.no_cache {
+        return Ok(None);
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(ref file) = args.db {
+        return Ok(Some(TransactionCache::open_database(file)?));
+    }
+    let root = args.cache_dir.clone().unwrap_or_else(default_cache_dir);
+    // Histories cached before each cluster got a directory of its own are at the top
+    if fs::read_dir(root.join("history")).is_ok_and(|mut histories| histories.next().is_some()) {
+        warn!(
+            "{} holds entries cached before each cluster got its own cache; move them into the directory of their cluster, \
+             such as {}, to reuse them, or delete them with `solconnect cache clear`",
+            root.display(), root.join("mainnet").display()
+        );
+    }
+    let cache = TransactionCache::open(root.join(cache_cluster(args)))?;
+    Ok(Some(cache.with_compression(args.cache_compression)))
+}
+
+// Fetches every address's signature history at once and returns the distinct signatures.
+// Each history is also merged into the cache so that `paths --offline` and `export` can use it later,
+// unless --min-slot or --max-slot cut it to a window, which a later run would take for all of it.
+async fn fetch_signatures(args: &Cli, source: &dyn TransactionSource, cache: Option<&TransactionCache>, addresses: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let started = Instant::now();
+    let history = HistoryOptions {
+        max_signatures: args.max_signatures,
+        until: args.until.clone(),
+        min_slot: args.min_slot,
+        max_slot: args.max_slot,
+        ..HistoryOptions::default()
+    };
+    let progress = if args.quiet { MultiProgress::with_draw_target(ProgressDrawTarget::hidden()) } else { MultiProgress::new() };
+    let histories = try_join_all(addresses.iter().map(|address| fetch_history(source, address, &history, &progress))).await?;
+    METRICS.record_phase("history", started.elapsed());
+
+    let windowed = args.min_slot.is_some() || args.max_slot.is_some();
+    if let Some(cache) = cache.filter(|_| !windowed) {
+        for (address, signatures) in addresses.iter().zip(&histories) {
+            let Some(signatures) = signatures else {
+                continue;
+            };
+            if let Err(e) = cache.merge_history(address, signatures) {
+                warn!("Failed to cache history of {}: {}", address, e);
+            }
+        }
+    }
+
+    let mut all_signatures: Vec<String> = histories.into_iter().flatten().flatten().collect();
+    all_signatures.sort();
+    all_signatures.dedup();
+    Ok(all_signatures)
+}
+
+// Fetches transaction details, from the cache where possible, skipping transactions that can't be fetched.
+// Each transaction is handed to `on_transaction` as soon as it arrives instead of being collected,
+// and the number of transactions fetched is returned. An interrupt drops the requests still in flight.
+// The time spent in `on_transaction` is left out of the detail phase; the caller accounts for it.
+async fn fetch_details(
+    args: &Cli,
+    source: &dyn TransactionSource,
+    cache: Option<&TransactionCache>,
+    signatures: &[String],
+    on_transaction: &mut dyn FnMut(serde_json::Value),
+) -> usize {
+    info!("Fetching details for {} unique transactions", signatures.len());
+
+    let started = Instant::now();
+    let mut handling = Duration::ZERO;
+    let bar = progress_bar(signatures.len() as u64, args.quiet);
+    let mut fetched = 0;
+    let mut skipped = 0;
+    let mut unexpected: Option<SolConnectError> = None;
+    let mut details = stream::iter(signatures.chunks(args.batch_size))
+        .map(|chunk| source.transactions(cache, chunk))
+        .buffer_unordered(args.concurrency);
+    loop {
+        let results = tokio::select! {
+            results = details.next() => match results {
+                Some(results) => results,
+                None => break,
+            },
+            _ = interrupted() => {
+                bar.suspend(|| warn!("Stopped after {} of {} transactions", bar.position(), signatures.len()));
+                break;
+            }
+        };
+        bar.inc(results.len() as u64);
+        for result in results {
+            match result {
+                Ok(transaction) => {
+                    let handled = Instant::now();
+                    on_transaction(transaction);
+                    handling += handled.elapsed();
+                    fetched += 1;
+                }
+                Err(e) => {
+                    debug!("Skipping transaction: {}", e);
+                    skipped += 1;
+                    if unexpected.is_none() && !matches!(e, SolConnectError::TransactionNotFound(_)) {
+                        unexpected = Some(e);
+                    }
+                }
+            }
+        }
+    }
+    bar.finish_and_clear();
+    METRICS.record_phase("details", started.elapsed().saturating_sub(handling));
+    // Pruned transactions are expected on most nodes; anything else is worth seeing without --verbose
+    if let Some(error) = unexpected {
+        warn!("Skipped {} transaction(s) that couldn't be fetched, for example: {}", skipped, error);
+    } else if skipped > 0 {
+        info!("Skipped {} transaction(s) the node no longer has", skipped);
+    }
+    fetched
+}
+
+// Fetches every address's history and the transaction details over JSON-RPC and builds the graph,
+// along with the kinds of its accounts.
+async fn crawl_rpc(args: &Cli, addresses: &[String], options: &GraphOptions) -> Result<(Graph, NodeKinds), Box<dyn Error>> {
+    let rpc = rpc_client(args)?;
+    let cache = open_cache(args)?;
+    let signatures = fetch_signatures(args, &rpc, cache.as_ref(), addresses).await?;
+
+    info!("Building transaction graph");
+    let mut builder = GraphBuilder::new(options);
+    let mut building = Duration::ZERO;
+    fetch_details(args, &rpc, cache.as_ref(), &signatures, &mut |transaction| {
+        let started = Instant::now();
+        builder.add(&transaction);
+        building += started.elapsed();
+    }).await;
+    report_dropped_edges(&builder);
+    let started = Instant::now();
+    let (graph, kinds) = builder.finish_with_kinds();
+    METRICS.record_phase("graph", building + started.elapsed());
+    if let Some(ref cache) = cache {
+        if let Err(e) = cache.record_graph(&graph, options.directed) {
+            warn!("Failed to store the graph in {}: {}", cache.location().display(), e);
+        }
+    }
+    Ok((graph, kinds))
+}
+
+fn report_dropped_edges(builder: &GraphBuilder) {
+    if builder.dropped_edges() > 0 {
+        info!("Dropped {} transaction edge(s) that moved less than --min-sol or --min-token", builder.dropped_edges());
+    }
+}
+
+// How many landmarks guide --astar. Each one costs up to two breadth-first searches of the whole
+// graph, paid once for all of the pairs.
+const ASTAR_LANDMARKS: usize = 4;
+
+// How many cached transactions are held in memory at once while their graph is built.
+const CACHED_GRAPH_BATCH: usize = 4096;
+
+// Builds the graph from the histories and transaction details cached by earlier runs, without any RPC calls.
+fn load_cached(args: &Cli, addresses: &[String], options: &GraphOptions) -> Result<(Graph, NodeKinds), Box<dyn Error>> {
+    if args.provider != Provider::Rpc {
+        return Err("cached graphs are built from raw transactions and require --provider rpc".into());
+    }
+    let cache = open_cache(args)?.ok_or("--no-cache can't be combined with reading cached data")?;
+
+    let mut signatures = Vec::new();
+    for address in addresses {
+        let history = cache.get_history(address)
+            .ok_or_else(|| format!("no cached history for {}; run `solconnect fetch {}` first", address, address))?;
+        info!("Loaded {} cached signatures for address {}", history.len(), address);
+        signatures.extend(history);
+    }
+    signatures.sort();
+    signatures.dedup();
+
+    info!("Building transaction graph from {} cached transactions", signatures.len());
+    let started = Instant::now();
+    let mut builder = GraphBuilder::new(options);
+    let mut missing = 0;
+    // Transactions are read in batches that are built in parallel, so memory stays bounded
+    for chunk in signatures.chunks(CACHED_GRAPH_BATCH) {
+        let transactions: Vec<serde_json::Value> = chunk.iter().filter_map(|signature| cache.get(signature)).collect();
+        missing += chunk.len() - transactions.len();
+        builder.add_all(&transactions);
+    }
+    if missing > 0 {
+        warn!("{} of {} cached signatures have no cached details", missing, signatures.len());
+    }
+    report_dropped_edges(&builder);
+    let graph = builder.finish_with_kinds();
+    METRICS.record_phase("graph", started.elapsed());
+    Ok(graph)
+}
+
+// The --quick fast path: builds the graph from the first address's history alone, and returns it
+// if the second address is a direct counterparty. Otherwise the full crawl runs as usual, and
+// finds the first history's transaction details in the cache. An interrupted crawl returns the
+// partial graph too, linked or not; `direct_path` tells which.
+async fn quick_link(args: &Cli, addresses: &[String], options: &GraphOptions) -> Result<Option<(Graph, NodeKinds)>, Box<dyn Error>> {
+    if addresses.len() != 2 {
+        warn!("--quick only checks a pair of addresses; running the full analysis");
+        return Ok(None);
+    }
+
+    info!("Checking for a direct link between {} and {}", addresses[0], addresses[1]);
+    let graph = match args.provider {
+        Provider::Rpc => crawl_rpc(args, &addresses[..1], options).await?,
+        Provider::Helius => crawl_helius(args, &addresses[..1], options).await?,
+    };
+    if is_interrupted() {
+        return Ok(Some(graph));
+    }
+    match direct_path(&graph.0, &addresses[0], &addresses[1]) {
+        Some(path) => {
+            let count = graph.0[&path[0]][&path[1]].count;
+            info!("{} and {} transacted directly in {} transaction(s)", addresses[0], addresses[1], count);
+            Ok(Some(graph))
+        }
+        None => {
+            info!("No direct link found; fetching the history of {} too", addresses[1]);
+            Ok(None)
+        }
+    }
+}
+
+// The one-hop path between two addresses, if they are linked directly. A directed graph stores a
+// transfer from the second address to the first only that way round, and the path follows it.
+fn direct_path(graph: &Graph, first: &str, second: &str) -> Option<Vec<String>> {
+    let linked = |from: &str, to: &str| graph.get(from).is_some_and(|neighbors| neighbors.contains_key(to));
+    if linked(first, second) {
+        Some(vec![first.to_string(), second.to_string()])
+    } else if linked(second, first) {
+        Some(vec![second.to_string(), first.to_string()])
+    } else {
+        None
+    }
+}
+
+// Builds the graph from transfers decoded by the Helius enhanced-transactions API. Transfers don't
+// say what kind of account each side is, so no kinds are returned.
+async fn crawl_helius(args: &Cli, addresses: &[String], options: &GraphOptions) -> Result<(Graph, NodeKinds), Box<dyn Error>> {
+    let api_key = args.api_key.clone().ok_or("--provider helius requires --api-key")?;
+    if options.program.is_some() {
+        warn!("--program is not supported with --provider helius and will be ignored");
+    }
+    if args.auth_header.is_some() {
+        warn!("--auth-header is ignored with --provider helius, which takes the key as a query parameter");
+    }
+    let helius = HeliusProvider::new(api_key, args.max_retries)?
+        .with_timeout(args.timeout)?
+        .with_proxy(proxy(args))?
+        .with_max_transactions(args.max_signatures)
+        .with_until(args.until.clone())
+        .with_slots(args.min_slot, args.max_slot);
+
+    let started = Instant::now();
+    let helius = &helius;
+    let histories = try_join_all(addresses.iter().map(|address| async move {
+        tokio::select! {
+            transfers = helius.fetch_transfers(address) => transfers.map(Some),
+            _ = interrupted() => {
+                warn!("Leaving out {}, whose history was still being fetched", address);
+                Ok(None)
+            }
+        }
+    })).await?;
+    METRICS.record_phase("history", started.elapsed());
+    // A transaction between two of the addresses shows up in both histories; keep it once
+    let mut transfers = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    for fetched in histories.into_iter().flatten() {
+        let new_signatures: HashSet<String> = fetched.iter().map(|t| t.signature.clone()).collect();
+        transfers.extend(fetched.into_iter().filter(|t| !seen.contains(&t.signature)));
+        seen.extend(new_signatures);
+    }
+
+    info!("Building transaction graph from {} transfers", transfers.len());
+    let started = Instant::now();
+    let graph = build_transfer_graph(&transfers, options);
+    METRICS.record_phase("graph", started.elapsed());
+    Ok((graph, NodeKinds::new()))
+}
+
+// The index of the component every address is in, for components listed by `connected_components`.
+fn component_index(components: &[Vec<String>]) -> HashMap<&str, usize> {
+    components.iter().enumerate()
+        .flat_map(|(index, component)| component.iter().map(move |address| (address.as_str(), index)))
+        .collect()
+}
+
+// Whether the pairs that have at least one path link all of the addresses into one group.
+fn all_connected(addresses: &[String], pairs: &[(usize, usize, Vec<Vec<String>>)]) -> bool {
+    let mut group: Vec<usize> = (0..addresses.len()).collect();
+    fn root(group: &mut [usize], mut i: usize) -> usize {
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    for (i, j, paths) in pairs {
+        if !paths.is_empty() {
+            let (a, b) = (root(&mut group, *i), root(&mut group, *j));
+            group[a] = b;
+        }
+    }
+    let first = root(&mut group, 0);
+    (0..addresses.len()).all(|i| root(&mut group, i) == first)
+}
+
+// An address as printed in text output: its explorer link when --links is given, then its label if it has one.
+fn display_address(args: &Cli, labels: &Labels, address: &str) -> String {
+    match args.links {
+        Some(ref explorer) => {
+            let url = explorer.account_url(address, args.cluster);
+            match labels.get(address) {
+                Some(name) => format!("{} ({})", url, name),
+                None => url,
+            }
+        }
+        None => labels.annotate(address),
+    }
+}
+
+// The addresses of `path` joined by arrows. A hop whose transactions invoked a DEX names it on
+// the arrow, as in `A -[swap@Jupiter]-> B`, since such a link is a trade rather than a transfer,
+// and a hop that moved NFTs names their mints, as in `A -[nft:<mint>]-> B`.
+fn format_path(args: &Cli, labels: &Labels, graph: &Graph, path: &[String]) -> String {
+    let mut shown = path.first().map(|address| display_address(args, labels, address)).unwrap_or_default();
+    for hop in path.windows(2) {
+        let mut tags = Vec::new();
+        if let Some(edge) = graph.get(&hop[0]).and_then(|neighbors| neighbors.get(&hop[1])) {
+            if !edge.swaps.is_empty() {
+                let dexes: Vec<String> = edge.swaps.iter()
+                    .map(|program| labels.get(program).map(str::to_string).unwrap_or_else(|| short_address(program)))
+                    .collect();
+                tags.push(format!("swap@{}", dexes.join(",")));
+            }
+            // The full mint, to look the NFT and its collection up
+            if !edge.nfts.is_empty() {
+                let mints: Vec<&str> = edge.nfts.iter().map(|mint| labels.get(mint).unwrap_or(mint)).collect();
+                tags.push(format!("nft:{}", mints.join(",")));
+            }
+        }
+        if tags.is_empty() {
+            shown.push_str(" -> ");
+        } else {
+            let _ = write!(shown, " -[{}]-> ", tags.join(" "));
+        }
+        shown.push_str(&display_address(args, labels, &hop[1]));
+    }
+    shown
+}
+
+// The fee of a transaction, from its cached details; `None` if it isn't cached.
+fn cached_fee(cache: &TransactionCache, signature: &str) -> Option<u64> {
+    cache.get(signature)?.get("meta")?.get("fee")?.as_u64()
+}
+
+// With --fees, prints the fee of every transaction recorded on each hop of `path` and what the
+// path cost in all. A transaction behind several hops counts once toward the total.
+fn print_hop_fees(graph: &Graph, path: &[String], cache: &TransactionCache) {
+    let mut total = HashMap::new();
+    for hop in hop_fees(graph, path, |signature| cached_fee(cache, signature)) {
+        let recorded = hop.transactions.len();
+        let shown = if recorded < hop.count as usize { format!("{} of {}", recorded, hop.count) } else { recorded.to_string() };
+        println!(
+            "  {} -> {}: {} SOL in fees over {} transaction(s)",
+            short_address(hop.source), short_address(hop.target), lamports_to_sol(hop.lamports), shown
+        );
+        for transaction in &hop.transactions {
+            match transaction.lamports {
+                Some(lamports) => println!("    {}: {} SOL", transaction.signature, lamports_to_sol(lamports)),
+                None => println!("    {}: not in the cache", transaction.signature),
+            }
+            total.insert(transaction.signature, transaction.lamports);
+        }
+    }
+    let known = total.values().flatten().count();
+    let missing = if known < total.len() { format!(" ({} not in the cache)", total.len() - known) } else { String::new() };
+    println!("  Total: {} SOL over {} transaction(s){}", lamports_to_sol(total.values().flatten().sum()), known, missing);
+}
+
+// The evidence behind each hop of a path: the signatures recorded on the edge it follows.
+fn print_hop_signatures(args: &Cli, graph: &Graph, path: &[String]) {
+    for hop in path.windows(2) {
+        let Some(edge) = graph.get(&hop[0]).and_then(|neighbors| neighbors.get(&hop[1])) else {
+            continue;
+        };
+        let shown = if edge.signatures.len() < edge.count as usize { " (sample)" } else { "" };
+        println!("  {} -> {}: {} transaction(s){}", short_address(&hop[0]), short_address(&hop[1]), edge.count, shown);
+        for signature in &edge.signatures {
+            match args.links {
+                Some(ref explorer) => println!("    {}", explorer.transaction_url(signature, args.cluster)),
+                None => println!("    {}", signature),
+            }
+        }
+    }
+}
+
+fn format_time(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0).map_or_else(|| time.to_string(), |t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+}
+
+fn print_hop_times(graph: &Graph, path: &[String]) {
+    for (hop, time) in path.windows(2).zip(hop_times(graph, path).unwrap_or_default()) {
+        println!("  {} -> {} at {}", short_address(&hop[0]), short_address(&hop[1]), format_time(time));
+    }
+}
+
+// With --timeline, prints the span of time each hop's transactions cover, which tells a one-off
+// interaction from a long-standing relationship.
+fn print_hop_windows(graph: &Graph, path: &[String]) {
+    for window in hop_windows(graph, path) {
+        let hop = format!("{} -> {}", short_address(window.source), short_address(window.target));
+        match (window.first_time, window.last_time) {
+            (Some(first), Some(last)) if first == last => {
+                println!("  {}: {} transaction(s), all at {}", hop, window.count, format_time(first));
+            }
+            (Some(first), Some(last)) => {
+                let days = (last - first) / 86_400;
+                let span = if days > 0 { format!("{} day(s)", days) } else { format!("{} hour(s)", (last - first) / 3600) };
+                println!("  {}: {} transaction(s) from {} to {}, over {}", hop, window.count, format_time(first), format_time(last), span);
+            }
+            _ => println!("  {}: {} transaction(s), none with a block time", hop, window.count),
+        }
+    }
+}
+
+// How many '#' the largest bucket of the --stats degree histogram gets.
+const HISTOGRAM_WIDTH: usize = 40;
+
+fn print_stats(args: &Cli, hubs: Option<&[NodeDegree]>, distribution: Option<&DegreeDistribution>, labels: &Labels) {
+    if let Some(hubs) = hubs {
+        println!("Top {} nodes by degree:", hubs.len());
+        for hub in hubs {
+            println!("{} (degree {})", display_address(args, labels, &hub.address), hub.degree);
+        }
+    }
+    if let Some(distribution) = distribution {
+        let largest = distribution.buckets.iter().map(|bucket| bucket.count).max().unwrap_or_default().max(1);
+        println!("Degree distribution:");
+        for bucket in &distribution.buckets {
+            // Any node at all gets a mark, so a handful of hubs doesn't vanish next to the leaves
+            let bar = (bucket.count * HISTOGRAM_WIDTH).div_ceil(largest);
+            println!("{}", format!("{:>8} {:>8} {}", bucket.range(), bucket.count, "#".repeat(bar)).trim_end());
+        }
+        println!("Mean degree {:.2}, median {}, max {}", distribution.mean, distribution.median, distribution.max);
+    }
+}
+
+// Resolves .sol domains to their owners, and drops invalid and repeated addresses with a warning so
+// one typo doesn't abort the whole run. A domain that can't be resolved is an error, since it was
+// clearly meant as an input.
+async fn input_addresses(args: &Cli, raw: &[String]) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut addresses = Vec::new();
+    let mut rpc = None;
+    for input in raw {
+        let Some(address) = resolve_input(args, &mut rpc, input).await? else {
+            continue;
+        };
+        if addresses.contains(&address) {
+            warn!("Skipping duplicate address: {}", input);
+        } else {
+            info!("Address {}: {}", addresses.len() + 1, address);
+            addresses.push(address);
+        }
+    }
+    Ok(addresses)
+}
+
+// One input as an address: itself if it's valid, or the owner of a .sol domain, looked up with
+// `rpc`, which is created on first use. An invalid input is `None` after a warning.
+async fn resolve_input(args: &Cli, rpc: &mut Option<RpcClient>, input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    if is_valid_pubkey(input) {
+        return Ok(Some(input.to_string()));
+    }
+    if !is_sol_domain(input) {
+        warn!("Skipping invalid address: {}", input);
+        return Ok(None);
+    }
+    let rpc = match rpc {
+        Some(rpc) => rpc,
+        None => rpc.insert(rpc_client(args)?),
+    };
+    let owner = resolve_domain(rpc, input).await.map_err(|e| format!("couldn't resolve {}: {}", input, e))?;
+    info!("Resolved {} to {}", input, owner);
+    Ok(Some(owner))
+}
+
+// A line of --pairs-file: its number and the two inputs on it.
+type PairLine = (usize, String, String);
+
+// Reads the pairs of --pairs-file, or of stdin for `-`, with the line each is on. Blank lines and
+// lines starting with `#` are skipped; any other line must be two addresses separated by a comma.
+fn read_pairs_file(file: &Path) -> Result<Vec<PairLine>, Box<dyn Error>> {
+    let (name, text) = if file.as_os_str() == "-" {
+        ("stdin".to_string(), std::io::read_to_string(std::io::stdin())?)
+    } else {
+        let text = fs::read_to_string(file).map_err(|e| format!("couldn't read {}: {}", file.display(), e))?;
+        (file.display().to_string(), text)
+    };
+    let mut pairs = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split(',').map(str::trim).collect::<Vec<_>>()[..] {
+            [first, second] => pairs.push((n + 1, first.to_string(), second.to_string())),
+            _ => return Err(format!("{} line {}: expected `address1,address2`, found `{}`", name, n + 1, line).into()),
+        }
+    }
+    Ok(pairs)
+}
+
+// Resolves the inputs of --pairs-file like `input_addresses`, each distinct input once. Returns the
+// distinct addresses and every pair as indices into them, in the order of the file. A pair with an
+// invalid address, with the same address twice, or listed before is skipped with a warning.
+async fn input_pairs(args: &Cli, file: &Path) -> Result<(Vec<String>, Vec<(usize, usize)>), Box<dyn Error>> {
+    let mut addresses = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    let mut resolved: HashMap<String, Option<usize>> = HashMap::new();
+    let mut pairs = Vec::new();
+    let mut seen = HashSet::new();
+    let mut rpc = None;
+    for (line, first, second) in read_pairs_file(file)? {
+        let mut ends = [None; 2];
+        for (end, input) in ends.iter_mut().zip([first, second]) {
+            if !resolved.contains_key(&input) {
+                let index = resolve_input(args, &mut rpc, &input).await?.map(|address| {
+                    *indices.entry(address.clone()).or_insert_with(|| {
+                        addresses.push(address);
+                        addresses.len() - 1
+                    })
+                });
+                resolved.insert(input.clone(), index);
+            }
+            *end = resolved[&input];
+        }
+        match ends {
+            [Some(i), Some(j)] if i == j => warn!("Skipping the pair on line {}, whose addresses are the same", line),
+            [Some(i), Some(j)] if !seen.insert((i, j)) => warn!("Skipping the pair on line {}, which is listed earlier", line),
+            [Some(i), Some(j)] => pairs.push((i, j)),
+            _ => warn!("Skipping the pair on line {}, which has an invalid address", line),
+        }
+    }
+    Ok((addresses, pairs))
+}
+
+// Looks up the .sol domain of each address for --sns, from the cache where possible. A failed lookup
+// is only logged, so the address is shown without a domain instead of aborting the run.
+async fn resolve_domains(args: &Cli, addresses: &[&str]) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let rpc = rpc_client(args)?;
+    let cache = open_cache(args)?;
+    let mut unique = addresses.to_vec();
+    unique.sort_unstable();
+    unique.dedup();
+    info!("Resolving .sol domains of {} addresses", unique.len());
+
+    let lookups: Vec<(&str, Option<String>)> = stream::iter(unique)
+        .map(|address| {
+            let (rpc, cache) = (&rpc, cache.as_ref());
+            async move {
+                if let Some(domain) = cache.and_then(|c| c.get_domain(address)) {
+                    return (address, domain);
+                }
+                match primary_domain(rpc, address).await {
+                    Ok(domain) => {
+                        if let Err(e) = cache.map_or(Ok(()), |c| c.put_domain(address, domain.as_deref())) {
+                            warn!("Failed to cache the domain of {}: {}", address, e);
+                        }
+                        (address, domain)
+                    }
+                    Err(e) => {
+                        debug!("Couldn't look up the domain of {}: {}", address, e);
+                        (address, None)
+                    }
+                }
+            }
+        })
+        .buffer_unordered(args.concurrency)
+        .collect()
+        .await;
+    Ok(lookups.into_iter().filter_map(|(address, domain)| domain.map(|domain| (address.to_string(), domain))).collect())
 }
 
-// Add this function at the beginning of your file
-fn get_rpc_endpoint() -> String {
-    env::var("SOLANA_RPC_ENDPOINT").unwrap_or_else(|_| {
-        eprintln!("SOLANA_RPC_ENDPOINT environment variable not set. Using default endpoint.");
-        "https://api.mainnet-beta.solana.com".to_string()
+fn load_labels(args: &Cli) -> Result<Labels, Box<dyn Error>> {
+    Ok(match args.labels {
+        Some(ref file) => Labels::with_file(file)?,
+        None => Labels::builtin(),
     })
 }
 
-async fn get_transaction_history(address: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let solana_api_endpoint = get_rpc_endpoint();
-    let mut signatures = Vec::new();
-    let mut before: Option<String> = None;
-    let limit = 1000;
-    let max_iterations = 10; // Fetch up to 10,000 transactions
-    let mut iteration = 0;
+async fn run_fetch(args: &Cli, addresses: &[String]) -> Result<(), Box<dyn Error>> {
+    let addresses = input_addresses(args, addresses).await?;
+    if addresses.is_empty() {
+        error!("At least one valid address is required");
+        return Ok(());
+    }
+    if args.provider != Provider::Rpc {
+        return Err("fetch caches raw transactions and requires --provider rpc".into());
+    }
 
-    loop {
-        let mut params = serde_json::json!([address, { "limit": limit }]);
-        if let Some(ref before_signature) = before {
-            params[1]["before"] = serde_json::Value::String(before_signature.clone());
-        }
-
-        let body = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "getConfirmedSignaturesForAddress2",
-            "params": params
-        });
-
-        let client = reqwest::Client::new();
-        let response = client.post(solana_api_endpoint)
-            .json(&body)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
-
-        if let Some(result) = response.get("result").and_then(|r| r.as_array()) {
-            if result.is_empty() {
-                break;
-            }
+    let rpc = rpc_client(args)?;
+    let cache = open_cache(args)?.ok_or("fetch stores its results in the cache and can't be combined with --no-cache")?;
+    let signatures = fetch_signatures(args, &rpc, Some(&cache), &addresses).await?;
+    let fetched = fetch_details(args, &rpc, Some(&cache), &signatures, &mut |_| {}).await;
 
-            for tx in result {
-                if let Some(sig) = tx.get("signature").and_then(|s| s.as_str()) {
-                    signatures.push(sig.to_string());
-                }
+    println!("Cached {} of {} transactions for {} address(es) in {}", fetched, signatures.len(), addresses.len(), cache.location().display());
+    Ok(())
+}
+
+// --dry-run: fetches the signature histories a crawl would start with, then reports the size of the
+// detail phase instead of running it. Nothing is written to the cache.
+async fn run_dry_run(args: &Cli) -> Result<(), Box<dyn Error>> {
+    let raw = match args.command {
+        Command::Fetch { ref addresses } => addresses.clone(),
+        Command::Paths(ref paths_args) if !paths_args.offline && paths_args.load_graph.is_none() => match paths_args.pairs_file {
+            Some(ref file) => read_pairs_file(file)?.into_iter().flat_map(|(_, first, second)| [first, second]).collect(),
+            None => paths_args.addresses.clone(),
+        },
+        Command::Neighbors(ref neighbors_args) if !neighbors_args.offline => vec![neighbors_args.address.clone()],
+        Command::Watch(ref watch_args) if !watch_args.offline => watch_args.addresses.clone(),
+        _ => return Err("--dry-run only applies to commands that fetch transactions over RPC".into()),
+    };
+    if args.provider != Provider::Rpc {
+        return Err("--dry-run estimates JSON-RPC detail requests and requires --provider rpc".into());
+    }
+    let addresses = input_addresses(args, &raw).await?;
+    if addresses.is_empty() {
+        error!("At least one valid address is required");
+        return Ok(());
+    }
+
+    let rpc = rpc_client(args)?;
+    let cache = open_cache(args)?;
+    let signatures = fetch_signatures(args, &rpc, None, &addresses).await?;
+    let cached = cache.map_or(0, |cache| signatures.iter().filter(|signature| cache.get(signature).is_some()).count());
+    let to_fetch = signatures.len() - cached;
+    let requests = to_fetch.div_ceil(args.batch_size);
+
+    println!("Unique transactions: {}", signatures.len());
+    println!("Already cached: {}", cached);
+    println!("To fetch: {}", to_fetch);
+    if args.batch_size > 1 {
+        println!("Detail requests: {} batches of up to {} transactions, {} at a time", requests, args.batch_size, args.concurrency);
+    } else {
+        println!("Detail requests: {} getTransaction calls, {} at a time", requests, args.concurrency);
+    }
+    Ok(())
+}
+
+// Writes the graph files asked for with --dot, --gexf and --graphml, highlighting `paths`, the flows
+// along `paths` with --sankey, and `paths` themselves with --csv-paths.
+fn write_graph_files(
+    paths_args: &PathsArgs,
+    graph: &Graph,
+    directed: bool,
+    queries: &[&str],
+    paths: &[Vec<String>],
+    labels: &Labels,
+    kinds: &NodeKinds,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(ref file) = paths_args.dot {
+        write_dot(file, graph, directed, queries, paths, labels, kinds)?;
+        info!("Wrote graph to {}", file.display());
+    }
+    if let Some(ref file) = paths_args.gexf {
+        fs::write(file, graph_to_gexf(graph, directed, queries, paths, labels))?;
+        info!("Wrote graph to {}", file.display());
+    }
+    if let Some(ref file) = paths_args.graphml {
+        fs::write(file, graph_to_graphml(graph, directed, queries, paths, labels))?;
+        info!("Wrote graph to {}", file.display());
+    }
+    if let Some(ref file) = paths_args.sankey {
+        let sankey = sankey_document(graph, paths, labels, paths_args.graph.mint.as_deref());
+        if sankey.circular > 0 {
+            warn!("Left {} hop(s) that would close a cycle out of the Sankey diagram", sankey.circular);
+        }
+        fs::write(file, serde_json::to_string_pretty(&sankey)? + "\n")?;
+        info!("Wrote {} flow(s) between {} address(es) to {}", sankey.links.len(), sankey.nodes.len(), file.display());
+    }
+    if let Some(ref file) = paths_args.csv_paths {
+        fs::write(file, paths_to_csv(paths))?;
+        info!("Wrote {} path(s) to {}", paths.len(), file.display());
+    }
+    if let Some(ref file) = paths_args.paths_subgraph {
+        let subgraph = path_subgraph(graph, paths, directed);
+        let contents = if file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+            serde_json::to_string_pretty(&graph_document(&subgraph, directed))? + "\n"
+        } else {
+            graph_to_dot(&subgraph, directed, queries, paths, labels, kinds)
+        };
+        fs::write(file, contents)?;
+        info!("Wrote the {} address(es) and {} edge(s) along the paths to {}", subgraph.len(), edge_count(&subgraph, directed), file.display());
+    }
+    Ok(())
+}
+
+async fn run_paths(args: &Cli, paths_args: &PathsArgs) -> Result<(), Box<dyn Error>> {
+    // Opened up front, so --no-cache fails before the crawl rather than after it
+    let fee_cache = if paths_args.fees {
+        Some(open_cache(args)?.ok_or("--fees reads the fees from cached transactions and can't be combined with --no-cache")?)
+    } else {
+        None
+    };
+    let (addresses, pair_indices) = match paths_args.pairs_file {
+        Some(ref file) => {
+            let (addresses, pairs) = input_pairs(args, file).await?;
+            if pairs.is_empty() {
+                error!("At least one valid pair of addresses is required");
+                return Ok(());
             }
+            info!("Analyzing connections of {} pair(s) between {} addresses", pairs.len(), addresses.len());
+            (addresses, pairs)
+        }
+        None => {
+            info!("Analyzing connections between {} addresses:", paths_args.addresses.len());
+            let addresses = input_addresses(args, &paths_args.addresses).await?;
+            if addresses.is_empty() || (addresses.len() < 2 && !paths_args.cycles) {
+                error!("At least two valid addresses are required, or one with --cycles");
+                return Ok(());
+            }
+            let pairs = (0..addresses.len()).flat_map(|i| (i + 1..addresses.len()).map(move |j| (i, j))).collect::<Vec<_>>();
+            (addresses, pairs)
+        }
+    };
+
+    let labels = load_labels(args)?;
+    let options = paths_args.graph.options(args, &labels);
+    let quick_graph = if paths_args.quick { quick_link(args, &addresses, &options).await? } else { None };
+    let direct_link = quick_graph.as_ref().and_then(|(graph, _)| direct_path(graph, &addresses[0], &addresses[1]));
+
+    let ((graph, kinds), directed) = if let Some(graph) = quick_graph {
+        (graph, options.directed)
+    } else if let Some(ref file) = paths_args.load_graph {
+        let (graph, directed) = load_graph(file)?;
+        if options.directed && !directed {
+            warn!("{} holds an undirected graph; --directed is ignored", file.display());
+        }
+        ((graph, NodeKinds::new()), directed)
+    } else if paths_args.offline {
+        (load_cached(args, &addresses, &options)?, options.directed)
+    } else {
+        let graph = match args.provider {
+            Provider::Rpc => crawl_rpc(args, &addresses, &options).await?,
+            Provider::Helius => crawl_helius(args, &addresses, &options).await?,
+        };
+        (graph, options.directed)
+    };
+
+    info!("Number of nodes in graph: {}", graph.len());
 
-            before = result.last().and_then(|tx| tx.get("signature").and_then(|sig| sig.as_str()).map(String::from));
+    if let Some(ref file) = paths_args.csv_edges {
+        fs::write(file, graph_to_csv(&graph, directed))?;
+        info!("Wrote graph edges to {}", file.display());
+    }
+    if let Some(ref file) = paths_args.matrix {
+        let format = paths_args.matrix_format.unwrap_or_else(|| MatrixFormat::for_graph(&graph));
+        let index_file = write_matrix(file, &graph, format)?;
+        info!("Wrote {} adjacency matrix to {} and its index to {}", format.as_str(), file.display(), index_file.display());
+    }
+
+    if let Some(ref file) = paths_args.save_graph {
+        save_graph(file, &graph, directed)?;
+        info!("Saved graph to {}", file.display());
+    }
+
+    let hubs = paths_args.stats.then(|| top_degrees(&graph, directed, paths_args.top));
+    let distribution = paths_args.stats.then(|| degree_distribution(&graph, directed));
+
+    if paths_args.common {
+        let queries: Vec<&str> = addresses.iter().map(String::as_str).collect();
+        let started = Instant::now();
+        let counterparties = common_counterparties(&graph, &queries, directed);
+        METRICS.record_phase("paths", started.elapsed());
+        let labels = if args.sns {
+            let shown: Vec<&str> = queries.iter().copied().chain(counterparties.iter().map(|c| c.address.as_str())).collect();
+            labels.with_domains(resolve_domains(args, &shown).await?)
         } else {
-            break;
+            labels
+        };
+        write_graph_files(paths_args, &graph, directed, &queries, &[], &labels, &kinds)?;
+        match paths_args.format {
+            OutputFormat::Text => {
+                println!("Found {} common counterparties:", counterparties.len());
+                for counterparty in &counterparties {
+                    println!("{} ({} transactions)", display_address(args, &labels, &counterparty.address), counterparty.count);
+                }
+                print_stats(args, hubs.as_deref(), distribution.as_ref(), &labels);
+            }
+            OutputFormat::Json => {
+                let result = CommonResult {
+                    addresses: &addresses,
+                    directed,
+                    node_count: graph.len(),
+                    counterparties: &counterparties,
+                    hubs: hubs.as_deref(),
+                    degree_distribution: distribution.as_ref(),
+                };
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            OutputFormat::Ndjson => {
+                for counterparty in &counterparties {
+                    println!("{}", serde_json::to_string(counterparty)?);
+                }
+            }
         }
+        return Ok(());
+    }
 
-        iteration += 1;
-        if iteration >= max_iterations {
-            break;
+    if paths_args.flow {
+        if !directed {
+            return Err("--flow needs a directed graph, but the loaded graph is undirected".into());
+        }
+        let started = Instant::now();
+        let pairs: Vec<PairFlows> = pair_indices.iter()
+            .map(|&(i, j)| PairFlows { address1: &addresses[i], address2: &addresses[j], flows: pair_flows(&graph, &addresses[i], &addresses[j]) })
+            .collect();
+        METRICS.record_phase("paths", started.elapsed());
+        match paths_args.format {
+            OutputFormat::Text => {
+                for pair in &pairs {
+                    println!("Flow from {} to {}:", display_address(args, &labels, pair.address1), display_address(args, &labels, pair.address2));
+                    for flow in &pair.flows {
+                        let asset = match flow.mint {
+                            Some(ref mint) => labels.get(mint).map(str::to_string).unwrap_or_else(|| short_address(mint)),
+                            None => "SOL".to_string(),
+                        };
+                        println!(
+                            "  {}: sent {} and received {} directly (net {}); up to {} sent and {} received through any route (net {})",
+                            asset, flow.direct_out, flow.direct_in, flow.net_direct, flow.routed_out, flow.routed_in, flow.net_routed
+                        );
+                    }
+                }
+            }
+            OutputFormat::Json => {
+                let result = FlowResult { addresses: &addresses, node_count: graph.len(), pairs };
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            OutputFormat::Ndjson => {
+                for pair in &pairs {
+                    println!("{}", serde_json::to_string(pair)?);
+                }
+            }
         }
+        return Ok(());
     }
 
-    println!("Fetched {} transactions for address {}", signatures.len(), address);
-    Ok(signatures)
-}
+    let queries: Vec<&str> = addresses.iter().map(String::as_str).collect();
+    let (search_graph, excluded_hubs) = match paths_args.max_node_degree {
+        Some(max_degree) => {
+            let (pruned, hubs) = without_hubs(&graph, directed, max_degree, &queries);
+            info!("Excluded {} node(s) with more than {} neighbors from path searches", hubs.len(), max_degree);
+            (Some(pruned), hubs)
+        }
+        None => (None, Vec::new()),
+    };
+    let search_graph = if paths_args.prune_leaves {
+        let before = search_graph.as_ref().unwrap_or(&graph);
+        let (core, leaves) = without_leaves(before, &queries);
+        info!(
+            "Pruned {} dead-end node(s) and {} edge(s), leaving {} nodes for path searches",
+            leaves.len(), edge_count(before, directed) - edge_count(&core, directed), core.len()
+        );
+        Some(core)
+    } else {
+        search_graph
+    };
+    let search_graph = search_graph.as_ref().unwrap_or(&graph);
 
-async fn get_transaction_details(signature: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let solana_api_endpoint = get_rpc_endpoint();
-    
-    let body = serde_json::json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getConfirmedTransaction",
-        "params": [
-            signature,
-            "json"
-        ]
-    });
+    if paths_args.components {
+        let started = Instant::now();
+        let components = connected_components(search_graph);
+        let component_of = component_index(&components);
+        METRICS.record_phase("paths", started.elapsed());
+        let memberships: Vec<AddressComponent> = addresses.iter()
+            .map(|address| {
+                let component = component_of.get(address.as_str()).copied();
+                AddressComponent { address, component, size: component.map_or(0, |c| components[c].len()) }
+            })
+            .collect();
+        let pairs: Vec<ComponentPair> = pair_indices.iter()
+            .map(|&(i, j)| {
+                let (component1, component2) = (memberships[i].component, memberships[j].component);
+                ComponentPair {
+                    address1: &addresses[i],
+                    address2: &addresses[j],
+                    component1,
+                    component2,
+                    same_component: component1.is_some() && component1 == component2,
+                }
+            })
+            .collect();
+        match paths_args.format {
+            OutputFormat::Text => {
+                let sizes: Vec<String> = components.iter().take(10).map(|component| component.len().to_string()).collect();
+                println!(
+                    "The graph of {} addresses has {} connected component(s); the largest hold {} address(es)",
+                    search_graph.len(), components.len(), sizes.join(", ")
+                );
+                for membership in &memberships {
+                    match membership.component {
+                        Some(component) => println!(
+                            "{} is in component {} ({} addresses)", display_address(args, &labels, membership.address), component + 1, membership.size
+                        ),
+                        None => println!("{} is not in the graph", display_address(args, &labels, membership.address)),
+                    }
+                }
+                for pair in &pairs {
+                    let (first, second) = (display_address(args, &labels, pair.address1), display_address(args, &labels, pair.address2));
+                    if pair.same_component {
+                        println!("{} and {} are in the same component", first, second);
+                    } else {
+                        println!("{} and {} are in different components, so no path connects them", first, second);
+                    }
+                }
+            }
+            OutputFormat::Json => {
+                let result = ComponentsResult {
+                    addresses: &addresses,
+                    directed,
+                    node_count: search_graph.len(),
+                    component_sizes: components.iter().map(Vec::len).collect(),
+                    memberships,
+                    pairs,
+                };
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            OutputFormat::Ndjson => {
+                for pair in &pairs {
+                    println!("{}", serde_json::to_string(pair)?);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if paths_args.cycles {
+        if !directed {
+            return Err("--cycles needs a directed graph, but the loaded graph is undirected".into());
+        }
+        info!("Finding cycles through the addresses");
+        let started = Instant::now();
+        let cycles = find_cycles(search_graph, &queries, paths_args.max_depth);
+        METRICS.record_phase("paths", started.elapsed());
+        let labels = if args.sns {
+            let shown: Vec<&str> = queries.iter().copied().chain(cycles.iter().flatten().map(String::as_str)).collect();
+            labels.with_domains(resolve_domains(args, &shown).await?)
+        } else {
+            labels
+        };
+        write_graph_files(paths_args, &graph, directed, &queries, &cycles, &labels, &kinds)?;
+        match paths_args.format {
+            OutputFormat::Text => {
+                println!("Found {} cycle(s) through the addresses:", cycles.len());
+                for (n, cycle) in cycles.iter().enumerate() {
+                    println!("Cycle {}: {}", n + 1, format_path(args, &labels, &graph, cycle));
+                    if paths_args.timeline {
+                        print_hop_windows(&graph, cycle);
+                    }
+                    if let Some(ref cache) = fee_cache {
+                        print_hop_fees(&graph, cycle, cache);
+                    }
+                    if paths_args.why {
+                        print_hop_signatures(args, &graph, cycle);
+                    }
+                }
+                print_stats(args, hubs.as_deref(), distribution.as_ref(), &labels);
+            }
+            OutputFormat::Json => {
+                let result = CycleResult {
+                    addresses: &addresses,
+                    node_count: graph.len(),
+                    cycles: &cycles,
+                    cycle_edges: path_edges(&graph, &cycles),
+                    labels: path_labels(&cycles, &labels),
+                    excluded_hubs: &excluded_hubs,
+                };
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
+            OutputFormat::Ndjson => {
+                for cycle in &cycles {
+                    let line = CycleLine { cycle, cycle_edges: path_edges(&graph, std::slice::from_ref(cycle)) };
+                    println!("{}", serde_json::to_string(&line)?);
+                }
+            }
+        }
+        return Ok(());
+    }
 
-    let client = reqwest::Client::new();
-    let response = client.post(solana_api_endpoint)
-        .json(&body)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    // With ndjson, each path is printed the moment the search finds it; stdout is line buffered,
+    // so a consumer sees it without waiting for the rest of the search. Those paths come in search
+    // order rather than ranked, and --max-paths stops the search once that many are out.
+    let streaming = paths_args.format == OutputFormat::Ndjson;
+    let print_path = |i: usize, j: usize, path: Option<&Vec<String>>| -> Result<(), Box<dyn Error>> {
+        let line = PathLine {
+            address1: &addresses[i],
+            address2: &addresses[j],
+            path: path.map(Vec::as_slice),
+            hop_times: path.filter(|_| paths_args.temporal).map(|path| hop_times(&graph, path).unwrap_or_default()),
+            hop_windows: path.filter(|_| paths_args.timeline).map(|path| hop_windows(&graph, path)),
+            hop_fees: path.zip(fee_cache.as_ref()).map(|(path, cache)| hop_fees(&graph, path, |signature| cached_fee(cache, signature))),
+            path_edges: path.map(|path| path_edges(&graph, std::slice::from_ref(path))).unwrap_or_default(),
+        };
+        println!("{}", serde_json::to_string(&line)?);
+        Ok(())
+    };
+    // The search stops at --max-paths when paths are streamed or found depth first; otherwise all
+    // of them are ranked and the best are kept
+    let running_cap = (streaming || paths_args.dfs).then_some(paths_args.max_paths).flatten();
 
-    if let Some(result) = response.get("result") {
-        Ok(result.clone())
+    let mut pairs = Vec::new();
+    if let Some(path) = direct_link {
+        if streaming {
+            print_path(0, 1, Some(&path))?;
+        }
+        pairs.push((0, 1, vec![path]));
     } else {
-        Err("Failed to fetch transaction details".into())
+        info!("Finding paths between addresses");
+        let started = Instant::now();
+        let landmarks = paths_args.astar.then(|| Landmarks::new(search_graph, directed, ASTAR_LANDMARKS));
+        for &(i, j) in &pair_indices {
+            let (start, end) = (&addresses[i], &addresses[j]);
+            let mut paths = Vec::new();
+            let mut failure = None;
+            // Takes each path as the search finds it, and tells the search whether to go on
+            let mut found = |path: Vec<String>| -> bool {
+                if running_cap.is_some_and(|cap| paths.len() >= cap) {
+                    return false;
+                }
+                if streaming {
+                    if let Err(err) = print_path(i, j, Some(&path)) {
+                        failure = Some(err);
+                        return false;
+                    }
+                }
+                paths.push(path);
+                running_cap.is_none_or(|cap| paths.len() < cap)
+            };
+            if let Some(ref landmarks) = landmarks {
+                if let Some(path) = astar_path(search_graph, landmarks, start, end, paths_args.max_depth) {
+                    found(path);
+                }
+            } else if let Some(weight) = paths_args.weight {
+                if let Some(path) = cheapest_path(search_graph, start, end, paths_args.max_depth, |edge| weight.cost(edge)) {
+                    found(path);
+                }
+            } else if let Some(k) = paths_args.k {
+                k_shortest_paths_each(search_graph, start, end, k, paths_args.max_depth, |path| found(path.to_vec()));
+            } else if paths_args.temporal {
+                find_temporal_paths(search_graph, start, end, paths_args.max_depth).into_iter().all(&mut found);
+            } else if paths_args.dfs {
+                find_paths_dfs_each(search_graph, start, end, paths_args.max_depth, &mut found);
+            } else if paths_args.shortest {
+                if let Some(path) = bidirectional_shortest_path(search_graph, start, end, paths_args.max_depth, directed) {
+                    found(path);
+                }
+            } else {
+                find_paths_each(search_graph, start, end, paths_args.max_depth, &mut found);
+            }
+            if let Some(err) = failure {
+                return Err(err);
+            }
+            if !streaming {
+                paths = rank_paths(search_graph, paths);
+                if let Some(max_paths) = paths_args.max_paths {
+                    paths.truncate(max_paths);
+                }
+            } else if paths.is_empty() {
+                print_path(i, j, None)?;
+            }
+            pairs.push((i, j, paths));
+        }
+        METRICS.record_phase("paths", started.elapsed());
     }
-}
+    let all_paths: Vec<Vec<String>> = pairs.iter().flat_map(|(_, _, paths)| paths.iter().cloned()).collect();
+    let connected = all_connected(&addresses, &pairs);
+    let labels = if args.sns {
+        let shown: Vec<&str> = queries.iter().copied().chain(all_paths.iter().flatten().map(String::as_str)).collect();
+        labels.with_domains(resolve_domains(args, &shown).await?)
+    } else {
+        labels
+    };
 
-fn build_transaction_graph(transactions: &[Value]) -> HashMap<String, HashSet<String>> {
-    let mut graph = HashMap::new();
+    write_graph_files(paths_args, &graph, directed, &queries, &all_paths, &labels, &kinds)?;
 
-    for transaction in transactions {
-        if let Some(transaction_info) = transaction.get("transaction") {
-            if let Some(message) = transaction_info.get("message") {
-                if let Some(account_keys) = message.get("accountKeys").and_then(|ak| ak.as_array()) {
-                    let accounts: Vec<String> = account_keys.iter()
-                        .filter_map(|key| key.as_str().map(|s| s.to_string()))
-                        .collect();
+    // Only worked out when some pair has no path, to tell a pair that nothing links from one the search's limits ruled out
+    let components = if pairs.iter().any(|(_, _, paths)| paths.is_empty()) { connected_components(search_graph) } else { Vec::new() };
+    let component_of = component_index(&components);
 
-                    if let Some(sender) = accounts.first() {
-                        for receiver in accounts.iter().skip(1) {
-                            graph.entry(sender.clone()).or_insert_with(HashSet::new).insert(receiver.clone());
-                            graph.entry(receiver.clone()).or_insert_with(HashSet::new).insert(sender.clone());
-                            
-                            // Debug print
-                            // println!("Connection: {} <-> {}", sender, receiver);
+    match paths_args.format {
+        OutputFormat::Text => {
+            for (i, j, paths) in &pairs {
+                println!("Found {} path(s) between {} and {}:", paths.len(), addresses[*i], addresses[*j]);
+                if paths.is_empty() {
+                    match (component_of.get(addresses[*i].as_str()), component_of.get(addresses[*j].as_str())) {
+                        (Some(first), Some(second)) if first == second => {
+                            println!("They are in the same component, but no path within the search's limits links them");
                         }
+                        _ => println!("They are in different components of the graph, so no path can link them"),
+                    }
+                }
+                for (n, path) in paths.iter().enumerate() {
+                    println!("Path {}:", n + 1);
+                    println!("{}", format_path(args, &labels, &graph, path));
+                    if paths_args.temporal {
+                        print_hop_times(&graph, path);
+                    }
+                    if paths_args.timeline {
+                        print_hop_windows(&graph, path);
+                    }
+                    if let Some(ref cache) = fee_cache {
+                        print_hop_fees(&graph, path, cache);
+                    }
+                    if paths_args.why {
+                        print_hop_signatures(args, &graph, path);
                     }
                 }
             }
+            // With --pairs-file only the listed pairs matter, not whether all of the addresses are connected
+            if addresses.len() > 2 && paths_args.pairs_file.is_none() {
+                if connected {
+                    println!("All {} addresses are connected", addresses.len());
+                } else {
+                    println!("The addresses do not all belong to one connected group");
+                }
+            }
+            print_stats(args, hubs.as_deref(), distribution.as_ref(), &labels);
         }
+        OutputFormat::Json => {
+            let result = AnalysisResult {
+                addresses: &addresses,
+                directed,
+                node_count: graph.len(),
+                connected,
+                pairs: pairs.iter()
+                    .map(|(i, j, paths)| PairResult {
+                        address1: &addresses[*i],
+                        address2: &addresses[*j],
+                        paths,
+                        hop_times: paths_args.temporal
+                            .then(|| paths.iter().map(|path| hop_times(&graph, path).unwrap_or_default()).collect()),
+                        hop_windows: paths_args.timeline.then(|| paths.iter().map(|path| hop_windows(&graph, path)).collect()),
+                        hop_fees: fee_cache.as_ref()
+                            .map(|cache| paths.iter().map(|path| hop_fees(&graph, path, |signature| cached_fee(cache, signature))).collect()),
+                    })
+                    .collect(),
+                path_edges: path_edges(&graph, &all_paths),
+                hubs: hubs.as_deref(),
+                degree_distribution: distribution.as_ref(),
+                labels: path_labels(&all_paths, &labels),
+                excluded_hubs: &excluded_hubs,
+            };
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        // Already printed pair by pair
+        OutputFormat::Ndjson => {}
     }
 
-    graph
+    Ok(())
 }
 
-fn find_paths(graph: &HashMap<String, HashSet<String>>, start: &str, end: &str, max_depth: usize) -> Vec<Vec<String>> {
-    let mut queue = VecDeque::new();
-    queue.push_back((start.to_string(), vec![start.to_string()]));
-    let mut paths = Vec::new();
-    let mut visited = HashSet::new();
+async fn run_export(args: &Cli, export_args: &ExportArgs) -> Result<(), Box<dyn Error>> {
+    let addresses = input_addresses(args, &export_args.addresses).await?;
+    if addresses.is_empty() {
+        error!("At least one valid address is required");
+        return Ok(());
+    }
 
-    while let Some((node, path)) = queue.pop_front() {
-        if path.len() > max_depth {
-            continue;
+    let labels = load_labels(args)?;
+    let options = export_args.graph.options(args, &labels);
+    let (graph, kinds) = load_cached(args, &addresses, &options)?;
+    let queries: Vec<&str> = addresses.iter().map(String::as_str).collect();
+    let output = match export_args.format {
+        ExportFormat::Dot => graph_to_dot(&graph, options.directed, &queries, &[], &labels, &kinds),
+        ExportFormat::Json => serde_json::to_string_pretty(&graph_document(&graph, options.directed))? + "\n",
+        ExportFormat::Csv => graph_to_csv(&graph, options.directed),
+        ExportFormat::Gexf => graph_to_gexf(&graph, options.directed, &queries, &[], &labels),
+        ExportFormat::Graphml => graph_to_graphml(&graph, options.directed, &queries, &[], &labels),
+    };
+
+    match export_args.output {
+        Some(ref file) => {
+            fs::write(file, output)?;
+            info!("Wrote graph with {} nodes to {}", graph.len(), file.display());
         }
+        None => print!("{}", output),
+    }
+    Ok(())
+}
 
-        if node == end {
-            paths.push(path.clone());
-            continue;
+// What an edge carried: its transactions, and the SOL and tokens moved along it.
+fn describe_edge(labels: &Labels, edge: &Edge) -> String {
+    let mut moved = vec![format!("{} transactions", edge.count)];
+    if edge.readonly > 0 {
+        moved.push(format!("{} read-only", edge.readonly));
+    }
+    if edge.lamports > 0 {
+        moved.push(format!("{} SOL", lamports_to_sol(edge.lamports)));
+    }
+    for (mint, amount) in &edge.tokens {
+        let mint_name = labels.get(mint).map(str::to_string).unwrap_or_else(|| short_address(mint));
+        moved.push(format!("{} {}", amount, mint_name));
+    }
+    moved.join(", ")
+}
+
+async fn run_neighbors(args: &Cli, neighbors_args: &NeighborsArgs) -> Result<(), Box<dyn Error>> {
+    let addresses = input_addresses(args, std::slice::from_ref(&neighbors_args.address)).await?;
+    let Some(address) = addresses.first() else {
+        error!("A valid address is required");
+        return Ok(());
+    };
+
+    let mut labels = load_labels(args)?;
+    let options = neighbors_args.graph.options(args, &labels);
+    let (graph, _) = if neighbors_args.offline {
+        load_cached(args, &addresses, &options)?
+    } else {
+        match args.provider {
+            Provider::Rpc => crawl_rpc(args, &addresses, &options).await?,
+            Provider::Helius => crawl_helius(args, &addresses, &options).await?,
         }
+    };
+    let neighbors = neighbors(&graph, address, options.directed);
+    let rings = if neighbors_args.degree > 1 { neighbor_rings(&graph, address, neighbors_args.degree) } else { Vec::new() };
 
-        if let Some(next_nodes) = graph.get(&node) {
-            for next_node in next_nodes {
-                if !visited.contains(next_node) {
-                    let mut new_path = path.clone();
-                    new_path.push(next_node.to_string());
-                    queue.push_back((next_node.to_string(), new_path));
-                    visited.insert(next_node.clone());
+    match neighbors_args.format {
+        OutputFormat::Text => {
+            if args.sns {
+                let shown: Vec<&str> = std::iter::once(address.as_str()).chain(neighbors.iter().map(|n| n.address.as_str())).collect();
+                labels = labels.with_domains(resolve_domains(args, &shown).await?);
+            }
+            println!("Found {} direct connection(s) of {}:", neighbors.len(), display_address(args, &labels, address));
+            for neighbor in &neighbors {
+                println!("{}: {}", display_address(args, &labels, &neighbor.address), describe_edge(&labels, &neighbor.edge));
+            }
+            for (i, ring) in rings.iter().enumerate().skip(1) {
+                println!("{} address(es) {} hops away:", ring.len(), i + 1);
+                for neighbor in ring {
+                    println!("{}", display_address(args, &labels, neighbor));
                 }
             }
+            if !rings.is_empty() {
+                let counts: Vec<String> = rings.iter().enumerate().map(|(i, ring)| format!("{} at {}", ring.len(), i + 1)).collect();
+                println!("Addresses per hop distance: {}", counts.join(", "));
+            }
+        }
+        OutputFormat::Json => {
+            let rings = rings.iter().enumerate()
+                .map(|(i, ring)| Ring { degree: i + 1, count: ring.len(), addresses: ring })
+                .collect();
+            let result = NeighborsResult { address, directed: options.directed, neighbors: &neighbors, rings };
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Ndjson => {
+            for neighbor in &neighbors {
+                println!("{}", serde_json::to_string(neighbor)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+const REPL_HELP: &str = "\
+path <address> <address>         shortest path between two addresses
+neighbors <address>              direct connections of an address, busiest first
+degree <address>                 how many addresses an address is linked to
+common <address> <address>...    counterparties that all the addresses transacted with
+help                             this list
+quit                             leave the session (or press Ctrl+D)";
+
+// Loads the graph once, then answers the queries typed at the prompt until `quit` or Ctrl+D. A
+// failed query is reported and the session goes on. Lines are kept in a history file in the
+// cache directory, so earlier sessions' queries can be recalled.
+async fn run_repl(args: &Cli, repl_args: &ReplArgs) -> Result<(), Box<dyn Error>> {
+    let labels = load_labels(args)?;
+    let (graph, directed) = match repl_args.load_graph {
+        Some(ref file) => load_graph(file)?,
+        None => {
+            let addresses = input_addresses(args, &repl_args.addresses).await?;
+            if addresses.is_empty() {
+                error!("At least one valid address is required");
+                return Ok(());
+            }
+            let options = repl_args.graph.options(args, &labels);
+            (load_cached(args, &addresses, &options)?.0, options.directed)
+        }
+    };
+    info!("Loaded a graph of {} nodes and {} edges; type `help` for the commands", graph.len(), edge_count(&graph, directed));
+
+    let history = args.cache_dir.clone().unwrap_or_else(default_cache_dir).join("repl_history");
+    let mut editor = DefaultEditor::new()?;
+    // There is no history before the first session
+    let _ = editor.load_history(&history);
+    let mut rpc = None;
+    loop {
+        let line = match editor.readline("solconnect> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, inputs)) = words.split_first() else {
+            continue;
+        };
+        let _ = editor.add_history_entry(line.as_str());
+        if matches!(command, "quit" | "exit") {
+            break;
         }
+        if let Err(e) = repl_query(args, &mut rpc, &graph, directed, &labels, repl_args.max_depth, command, inputs).await {
+            error!("{}", e);
+        }
+    }
+
+    if let Some(dir) = history.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Err(e) = editor.save_history(&history) {
+        warn!("Failed to save the session history to {}: {}", history.display(), e);
+    }
+    Ok(())
+}
+
+// The labels to show `shown` with, including their .sol domains with --sns.
+async fn repl_labels(args: &Cli, labels: &Labels, shown: &[&str]) -> Result<Labels, Box<dyn Error>> {
+    if !args.sns {
+        return Ok(labels.clone());
     }
+    Ok(labels.clone().with_domains(resolve_domains(args, shown).await?))
+}
 
-    paths
+// Answers one REPL query, printing the result.
+#[allow(clippy::too_many_arguments)]
+async fn repl_query(
+    args: &Cli,
+    rpc: &mut Option<RpcClient>,
+    graph: &Graph,
+    directed: bool,
+    labels: &Labels,
+    max_depth: usize,
+    command: &str,
+    inputs: &[&str],
+) -> Result<(), Box<dyn Error>> {
+    let arity = match command {
+        "path" => 2..=2,
+        "neighbors" | "degree" => 1..=1,
+        "common" => 2..=usize::MAX,
+        "help" => 0..=0,
+        other => return Err(format!("unknown command `{}`; type `help` for the commands", other).into()),
+    };
+    if !arity.contains(&inputs.len()) {
+        return Err(format!("wrong number of addresses for `{}`; type `help` for the commands", command).into());
+    }
+    let mut addresses = Vec::new();
+    for input in inputs {
+        if !is_valid_pubkey(input) && !is_sol_domain(input) {
+            return Err(format!("{} is neither an address nor a .sol domain", input).into());
+        }
+        let address = resolve_input(args, rpc, input).await?.expect("addresses and domains always resolve");
+        if !graph.contains_key(&address) {
+            return Err(format!("{} is not in the graph", address).into());
+        }
+        addresses.push(address);
+    }
+    match command {
+        "path" => {
+            let (start, end) = (&addresses[0], &addresses[1]);
+            let Some(path) = bidirectional_shortest_path(graph, start, end, max_depth, directed) else {
+                println!("No path of at most {} addresses links {} and {}", max_depth, start, end);
+                return Ok(());
+            };
+            let labels = repl_labels(args, labels, &path.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+            println!("Found a path of {} hop(s):", path.len() - 1);
+            println!("{}", format_path(args, &labels, graph, &path));
+        }
+        "neighbors" => {
+            let address = &addresses[0];
+            let neighbors = neighbors(graph, address, directed);
+            let labels = repl_labels(args, labels, &std::iter::once(address.as_str()).chain(neighbors.iter().map(|n| n.address.as_str())).collect::<Vec<_>>()).await?;
+            println!("Found {} direct connection(s) of {}:", neighbors.len(), display_address(args, &labels, address));
+            for neighbor in &neighbors {
+                println!("{}: {}", display_address(args, &labels, &neighbor.address), describe_edge(&labels, &neighbor.edge));
+            }
+        }
+        "degree" => {
+            let address = &addresses[0];
+            let labels = repl_labels(args, labels, &[address.as_str()]).await?;
+            let degree = neighbors(graph, address, directed).len();
+            print!("{} is linked to {} address(es)", display_address(args, &labels, address), degree);
+            if directed {
+                let inbound = graph.values().filter(|edges| edges.contains_key(address)).count();
+                print!(", sending to {} and receiving from {}", graph[address].len(), inbound);
+            }
+            println!();
+        }
+        "common" => {
+            let queries: Vec<&str> = addresses.iter().map(String::as_str).collect();
+            let counterparties = common_counterparties(graph, &queries, directed);
+            let labels = repl_labels(args, labels, &queries.iter().copied().chain(counterparties.iter().map(|c| c.address.as_str())).collect::<Vec<_>>()).await?;
+            println!("Found {} common counterparties:", counterparties.len());
+            for counterparty in &counterparties {
+                println!("{} ({} transactions)", display_address(args, &labels, &counterparty.address), counterparty.count);
+            }
+        }
+        _ => println!("{}", REPL_HELP),
+    }
+    Ok(())
 }
 
-fn is_valid_pubkey(address: &str) -> bool {
-    address.parse::<Pubkey>().is_ok()
+// `cache info` and `cache clear`, over the cache of --cluster or else of every cluster. Entries
+// cached before each cluster got a directory of its own count as a cache of their own.
+fn run_cache(args: &Cli, command: &CacheCommand) -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    if args.db.is_some() {
+        return Err("`cache` manages the cache directory; a --db database is a single file, to query or delete as a whole".into());
+    }
+    let root = args.cache_dir.clone().unwrap_or_else(default_cache_dir);
+    let caches: Vec<(&str, PathBuf)> = match args.cluster {
+        Some(cluster) => vec![(cluster.name(), root.join(cluster.name()))],
+        None => CACHE_CLUSTERS.iter()
+            .map(|cluster| (*cluster, root.join(cluster)))
+            .chain([("from before per-cluster caches", root.clone())])
+            .collect(),
+    };
+
+    let mut total = CacheUsage::default();
+    match *command {
+        CacheCommand::Info => {
+            println!("Cache directory: {}", root.display());
+            for (name, dir) in &caches {
+                let usage = directory_usage(dir)?;
+                if usage.entries() > 0 {
+                    println!(
+                        "{}: {} transaction(s), {} address history file(s), {} domain(s), {}",
+                        name, usage.transactions, usage.histories, usage.domains, format_bytes(usage.bytes)
+                    );
+                }
+                total.add(&usage);
+            }
+            println!("Total: {} file(s), {}", total.entries(), format_bytes(total.bytes));
+        }
+        CacheCommand::Clear { older_than } => {
+            for (name, dir) in &caches {
+                let removed = clear_directory(dir, older_than)?;
+                if removed.entries() > 0 {
+                    println!("{}: deleted {} file(s), {}", name, removed.entries(), format_bytes(removed.bytes));
+                }
+                total.add(&removed);
+            }
+            println!("Deleted {} file(s) in total, freeing {}", total.entries(), format_bytes(total.bytes));
+        }
+    }
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Cli::from_args();
+// How often a transaction announced over the WebSocket is looked up, and how long to wait in between:
+// the notification can arrive before getTransaction serves the transaction at the client's commitment.
+const WATCH_FETCH_ATTEMPTS: u32 = 5;
+const WATCH_FETCH_DELAY: Duration = Duration::from_secs(2);
+
+async fn fetch_notified(rpc: &RpcClient, cache: Option<&TransactionCache>, signature: &str) -> Option<serde_json::Value> {
+    for attempt in 1..=WATCH_FETCH_ATTEMPTS {
+        match get_transaction_details(rpc, cache, signature).await {
+            Ok(transaction) => return Some(transaction),
+            Err(SolConnectError::TransactionNotFound(_)) if attempt < WATCH_FETCH_ATTEMPTS => tokio::time::sleep(WATCH_FETCH_DELAY).await,
+            Err(e) => {
+                warn!("Skipping transaction {}: {}", signature, e);
+                return None;
+            }
+        }
+    }
+    None
+}
 
-    println!("Analyzing connection between addresses:");
-    println!("Address 1: {}", args.address1);
-    println!("Address 2: {}", args.address2);
+// Builds the graph of the addresses' histories, then subscribes to their new transactions and
+// folds each one into the graph as it lands. Whenever a pair becomes connected, or connected by a
+// shorter path than before, an alert is printed. Runs until interrupted, reconnecting with backoff
+// when the WebSocket drops.
+async fn run_watch(args: &Cli, watch_args: &WatchArgs) -> Result<(), Box<dyn Error>> {
+    if args.provider != Provider::Rpc {
+        return Err("watch subscribes to the RPC node's WebSocket and requires --provider rpc".into());
+    }
+    let addresses = input_addresses(args, &watch_args.addresses).await?;
+    if addresses.len() < 2 {
+        error!("At least two valid addresses are required");
+        return Ok(());
+    }
 
-    if !is_valid_pubkey(&args.address1) || !is_valid_pubkey(&args.address2) {
-        println!("Invalid address provided");
+    let labels = load_labels(args)?;
+    let options = watch_args.graph.options(args, &labels);
+    let (mut graph, _) = if watch_args.offline {
+        load_cached(args, &addresses, &options)?
+    } else {
+        crawl_rpc(args, &addresses, &options).await?
+    };
+    if is_interrupted() {
         return Ok(());
     }
+    let url = match watch_args.ws_url {
+        Some(ref url) => url.clone(),
+        None => websocket_url(&get_rpc_endpoints(&args.rpc_url, args.cluster)[0])?,
+    };
 
-    let signatures1 = get_transaction_history(&args.address1).await?;
-    let signatures2 = get_transaction_history(&args.address2).await?;
-    
-    let mut all_signatures = signatures1;
-    all_signatures.extend(signatures2);
-    all_signatures.sort();
-    all_signatures.dedup();
+    // The shortest path known for each pair, so only new or shorter connections raise an alert
+    let mut pairs = Vec::new();
+    for i in 0..addresses.len() {
+        for j in i + 1..addresses.len() {
+            let path = bidirectional_shortest_path(&graph, &addresses[i], &addresses[j], watch_args.max_depth, options.directed);
+            match path {
+                Some(ref path) => info!("{} and {} are already connected in {} hop(s)", addresses[i], addresses[j], path.len() - 1),
+                None => info!("{} and {} are not connected yet", addresses[i], addresses[j]),
+            }
+            pairs.push((i, j, path));
+        }
+    }
+
+    let rpc = rpc_client(args)?;
+    let cache = open_cache(args)?;
+    let mut seen = HashSet::new();
+    let mut attempt = 0;
+    loop {
+        let connected = tokio::select! {
+            subscription = LogSubscription::connect(&url, &addresses, args.commitment) => subscription,
+            _ = interrupted() => return Ok(()),
+        };
+        let error = match connected {
+            Ok(mut subscription) => {
+                info!("Watching {} addresses for new transactions on {}", addresses.len(), url);
+                attempt = 0;
+                loop {
+                    let notification = tokio::select! {
+                        notification = subscription.next() => match notification {
+                            Ok(notification) => notification,
+                            Err(e) => break e,
+                        },
+                        _ = interrupted() => return Ok(()),
+                    };
+                    if !seen.insert(notification.signature.clone()) {
+                        continue;
+                    }
+                    if notification.failed && !options.include_failed {
+                        debug!("Skipping failed transaction {}", notification.signature);
+                        continue;
+                    }
+                    debug!("New transaction {} involving {}", notification.signature, notification.address);
+                    let Some(transaction) = fetch_notified(&rpc, cache.as_ref(), &notification.signature).await else {
+                        continue;
+                    };
+                    merge_graphs(&mut graph, build_transaction_graph(std::slice::from_ref(&transaction), &options));
 
-    println!("Fetching details for {} unique transactions", all_signatures.len());
+                    for (i, j, known) in &mut pairs {
+                        let Some(path) = bidirectional_shortest_path(&graph, &addresses[*i], &addresses[*j], watch_args.max_depth, options.directed) else {
+                            continue;
+                        };
+                        if known.as_ref().is_some_and(|known| known.len() <= path.len()) {
+                            continue;
+                        }
+                        match watch_args.format {
+                            OutputFormat::Text => {
+                                let change = if known.is_some() { "Shorter path" } else { "New path" };
+                                println!(
+                                    "{} between {} and {} ({} hop(s)), completed by transaction {}:",
+                                    change, display_address(args, &labels, &addresses[*i]), display_address(args, &labels, &addresses[*j]),
+                                    path.len() - 1, notification.signature
+                                );
+                                println!("{}", format_path(args, &labels, &graph, &path));
+                            }
+                            OutputFormat::Json | OutputFormat::Ndjson => {
+                                let alert = WatchAlert {
+                                    address1: &addresses[*i],
+                                    address2: &addresses[*j],
+                                    signature: &notification.signature,
+                                    path: &path,
+                                    path_edges: path_edges(&graph, std::slice::from_ref(&path)),
+                                };
+                                println!("{}", serde_json::to_string(&alert)?);
+                            }
+                        }
+                        *known = Some(path);
+                    }
+                }
+            }
+            Err(e) => e,
+        };
 
-    let mut all_transactions = Vec::new();
-    for (i, signature) in all_signatures.iter().enumerate() {
-        if i % 100 == 0 {
-            println!("Processed {} transactions", i);
+        if !error.is_retryable() {
+            return Err(error.into());
         }
-        if let Ok(transaction) = get_transaction_details(signature).await {
-            all_transactions.push(transaction);
+        let delay = Duration::from_secs(1 << attempt.min(5));
+        warn!("WebSocket subscription failed ({}), reconnecting in {}s", error, delay.as_secs());
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = interrupted() => return Ok(()),
         }
+        attempt += 1;
     }
+}
 
-    println!("Building transaction graph");
-    let graph = build_transaction_graph(&all_transactions);
+// Logs what the run cost: requests, retries, traffic, cache use and the time spent in each phase.
+// Runs that neither sent a request nor timed a phase have nothing to report.
+fn print_run_summary(total: Duration) {
+    let metrics = METRICS.snapshot();
+    if metrics.requests == 0 && metrics.phases.is_empty() {
+        return;
+    }
+    if metrics.requests > 0 {
+        info!(
+            "Sent {} request(s), {} of them retries; {} sent, {} received",
+            metrics.requests, metrics.retries, format_bytes(metrics.bytes_sent), format_bytes(metrics.bytes_received)
+        );
+        if metrics.rate_limited > 0 {
+            warn!(
+                "{} of the {} request(s) ({:.1}%) were rate-limited with HTTP 429",
+                metrics.rate_limited, metrics.requests, 100.0 * metrics.rate_limited as f64 / metrics.requests as f64
+            );
+        }
+    }
+    if metrics.cache_hits + metrics.cache_misses > 0 {
+        info!("Cache: {} hit(s), {} miss(es)", metrics.cache_hits, metrics.cache_misses);
+    }
+    let phases: Vec<String> = metrics.phases.iter()
+        .map(|(phase, elapsed)| {
+            let name = match *phase {
+                "history" => "signature history",
+                "details" => "transaction details",
+                "graph" => "graph build",
+                "paths" => "path search",
+                other => other,
+            };
+            format!("{} {:.1}s", name, elapsed.as_secs_f64())
+        })
+        .collect();
+    let phases = if phases.is_empty() { String::new() } else { format!("{}, ", phases.join(", ")) };
+    info!("Time: {}total {:.1}s", phases, total.as_secs_f64());
+}
 
-    // println!("Graph structure:");
-    // for (key, value) in &graph {
-    //     println!("{}: {:?}", key, value);
-    // }
-    println!("Number of nodes in graph: {}", graph.len());
+// Parses the command line, with the config file filling in every option it leaves out. A flag on
+// the command line wins over the file, and so does the environment variable of the API key or
+// endpoint. The file's values are appended as flags, so they are validated like typed ones.
+fn parse_args() -> Result<Cli, Box<dyn Error>> {
+    let mut argv: Vec<std::ffi::OsString> = env::args_os().collect();
+    let matches = Cli::clap().get_matches_from(&argv);
+    let subcommand = matches.subcommand().1;
+    let given = |name: &str| matches.occurrences_of(name) > 0 || subcommand.is_some_and(|m| m.occurrences_of(name) > 0);
 
-    println!("Finding paths between addresses");
-    let max_depth = 50; // Increased max depth
-    let paths = find_paths(&graph, &args.address1, &args.address2, max_depth);
+    let args = Cli::from_clap(&matches);
+    let config = match args.config {
+        Some(ref file) => Some(Config::load(file)?),
+        None => Config::find()?.map(|(_, config)| config),
+    };
+    let Some(config) = config else {
+        return Ok(args);
+    };
 
-    println!("Found {} path(s) between the addresses:", paths.len());
-    for (i, path) in paths.iter().enumerate() {
-        println!("Path {}:", i + 1);
-        for (j, address) in path.iter().enumerate() {
-            if j > 0 {
-                print!(" -> ");
-            }
-            print!("{}", address);
+    for (name, value) in config.options() {
+        let env_var = match name {
+            "api-key" => Some("SOLCONNECT_API_KEY"),
+            "rpc-url" => Some("SOLANA_RPC_ENDPOINT"),
+            _ => None,
+        };
+        let superseded = Config::superseded_by(name).iter().any(|flag| given(flag));
+        if !given(name) && !superseded && env_var.is_none_or(|var| env::var_os(var).is_none()) {
+            argv.push(format!("--{}={}", name, value).into());
+        }
+    }
+    Ok(Cli::from_iter(argv))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = parse_args()?;
+
+    let level = match args.verbose {
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt().with_writer(std::io::stderr).with_max_level(level).with_target(false).without_time().init();
+    handle_interrupts();
+
+    let started = Instant::now();
+    if let Some(port) = args.metrics_port {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        info!("Serving metrics on http://127.0.0.1:{}/metrics", port);
+        tokio::spawn(serve_prometheus(listener));
+    }
+
+    let result = run(&args).await;
+    print_run_summary(started.elapsed());
+    result
+}
+
+async fn run(args: &Cli) -> Result<(), Box<dyn Error>> {
+    if let (Some(min_slot), Some(max_slot)) = (args.min_slot, args.max_slot) {
+        if min_slot > max_slot {
+            return Err(format!("--min-slot {} is after --max-slot {}", min_slot, max_slot).into());
         }
-        println!();
     }
+    if !args.skip_health_check && uses_rpc(args) {
+        check_endpoint(args).await?;
+    }
+    if args.dry_run {
+        return run_dry_run(args).await;
+    }
+    match args.command {
+        Command::Fetch { ref addresses } => run_fetch(args, addresses).await,
+        Command::Paths(ref paths_args) => run_paths(args, paths_args).await,
+        Command::Export(ref export_args) => run_export(args, export_args).await,
+        Command::Neighbors(ref neighbors_args) => run_neighbors(args, neighbors_args).await,
+        Command::Watch(ref watch_args) => run_watch(args, watch_args).await,
+        Command::Repl(ref repl_args) => run_repl(args, repl_args).await,
+        Command::Cache(ref cache_command) => run_cache(args, cache_command),
+    }
+}
 
+// Whether the command sends JSON-RPC requests for its main work, which makes the endpoint worth
+// checking before it starts.
+fn uses_rpc(args: &Cli) -> bool {
+    match args.command {
+        _ if args.dry_run => true,
+        Command::Fetch { .. } | Command::Watch(_) => true,
+        Command::Paths(ref paths_args) => args.provider == Provider::Rpc && !paths_args.offline && paths_args.load_graph.is_none(),
+        Command::Neighbors(ref neighbors_args) => args.provider == Provider::Rpc && !neighbors_args.offline,
+        Command::Export(_) | Command::Repl(_) | Command::Cache(_) => false,
+    }
+}
+
+// Fails fast when the RPC endpoint doesn't answer, which is usually a mistyped URL or a missing
+// API key, instead of partway through a crawl. A node that answers but reports itself unhealthy
+// only gets a warning, since it may still serve the history.
+async fn check_endpoint(args: &Cli) -> Result<(), Box<dyn Error>> {
+    let rpc = rpc_client(args)?;
+    let version = get_node_version(&rpc).await.map_err(|e| {
+        format!(
+            "the RPC endpoint isn't answering ({}); check --rpc-url or SOLANA_RPC_ENDPOINT, and --api-key if the provider needs a key, \
+             or pass --skip-health-check to start anyway",
+            e
+        )
+    })?;
+    info!("RPC node runs solana-core {}", version);
+    match get_health(&rpc).await {
+        Ok(()) => {}
+        // Not every provider implements getHealth
+        Err(SolConnectError::Rpc { code: -32601, .. }) => debug!("The RPC node doesn't support getHealth"),
+        Err(e) => warn!("The RPC node reports that it is unhealthy ({}); recent transactions may be missing", e),
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direct_paths_need_an_edge_in_either_direction() {
+        let mut graph = Graph::new();
+        // What an interrupted --quick crawl may leave: the first address, linked to someone else
+        graph.entry("A".to_string()).or_default().insert("C".to_string(), Edge { count: 1, ..Edge::default() });
+        assert_eq!(direct_path(&graph, "A", "B"), None);
+        assert_eq!(direct_path(&Graph::new(), "A", "B"), None);
+
+        graph.entry("B".to_string()).or_default().insert("A".to_string(), Edge { count: 1, ..Edge::default() });
+        assert_eq!(direct_path(&graph, "A", "B"), Some(vec!["B".to_string(), "A".to_string()]));
+        graph.entry("A".to_string()).or_default().insert("B".to_string(), Edge { count: 1, ..Edge::default() });
+        assert_eq!(direct_path(&graph, "A", "B"), Some(vec!["A".to_string(), "B".to_string()]));
+    }
+
+    #[test]
+    fn ages_take_a_count_and_a_unit() {
+        let valid = [
+            ("45s", 45),
+            ("90m", 90 * 60),
+            ("12h", 12 * 3600),
+            ("30d", 30 * 86_400),
+            ("2w", 14 * 86_400),
+            ("3mo", 90 * 86_400),
+            ("1y", 365 * 86_400),
+            ("0d", 0),
+        ];
+        for (value, seconds) in valid {
+            assert_eq!(parse_age(value), Ok(Duration::from_secs(seconds)), "{}", value);
+        }
+
+        let invalid = ["", "30", "d", "mo", "1.5d", "-1d", "3 d", "3D", "2e", "1wk"];
+        for value in invalid {
+            assert!(parse_age(value).unwrap_err().starts_with("invalid age"), "{}", value);
+        }
+
+        // u64 seconds that overflow, or that would wrap when taken from a unix timestamp
+        let out_of_range = [format!("{}s", u64::MAX), format!("{}y", u64::MAX / 60), format!("{}s", i64::MAX as u64 + 1)];
+        for value in &out_of_range {
+            assert!(parse_age(value).unwrap_err().contains("out of range"), "{}", value);
+        }
+        assert_eq!(parse_age(&format!("{}s", i64::MAX)), Ok(Duration::from_secs(i64::MAX as u64)));
+        assert!(parse_age(&format!("{}0s", u64::MAX)).unwrap_err().starts_with("invalid age"));
+    }
+
+    #[test]
+    fn custom_explorers_link_signatures_only_with_a_placeholder() {
+        let signature = "5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXFSDwt8GFXM7W5Ncn16wmqokgpiKRLuS83KUxyZyv2sUYv";
+        let accounts: Explorer = "https://example.com/account/{address}".parse().unwrap();
+        assert_eq!(accounts.transaction_url(signature, None), signature);
+
+        let search: Explorer = "https://example.com/search?q={address}{signature}".parse().unwrap();
+        assert_eq!(search.transaction_url(signature, None), format!("https://example.com/search?q={}", signature));
+        assert_eq!(search.account_url("7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU", None), "https://example.com/search?q=7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU");
+        assert_eq!(Explorer::Solscan.transaction_url(signature, Some(Cluster::Devnet)), format!("https://solscan.io/tx/{}?cluster=devnet", signature));
+    }
+}
@@ -0,0 +1,215 @@
+//! Counters of what a run did over the network and with the cache, and how long each phase took.
+
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, warn};
+
+/// The counters the RPC client, the Helius provider and the transaction cache report to. They
+/// cover the whole process, however many clients it creates.
+pub static METRICS: Metrics = Metrics::new();
+
+/// Request, retry, cache and traffic counters, plus the wall-clock time spent in named phases.
+/// All of it can be updated from any thread.
+pub struct Metrics {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    rate_limited: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    /// In the order the phases first finished
+    phases: Mutex<Vec<(&'static str, Duration)>>,
+}
+
+/// The values of [`Metrics`] at one point in time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// HTTP requests sent, each retry counted again
+    pub requests: u64,
+    pub retries: u64,
+    /// Requests answered with HTTP 429
+    pub rate_limited: u64,
+    /// Transaction details served from the cache, and looked up there in vain
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            requests: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Counts one HTTP request and its traffic, in bytes of body.
+    pub fn record_request(&self, sent: usize, received: usize) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
+        self.bytes_received.fetch_add(received as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit { &self.cache_hits } else { &self.cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Adds `elapsed` to the time spent in `phase`.
+    pub fn record_phase(&self, phase: &'static str, elapsed: Duration) {
+        let mut phases = self.phases.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => phases.push((phase, elapsed)),
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            phases: self.phases.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone(),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+impl MetricsSnapshot {
+    /// The snapshot in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let counters = [
+            ("solconnect_requests_total", "HTTP requests sent to RPC and provider endpoints, retries included", self.requests),
+            ("solconnect_retries_total", "Requests retried after a transient failure", self.retries),
+            ("solconnect_rate_limited_total", "Requests answered with HTTP 429 Too Many Requests", self.rate_limited),
+            ("solconnect_cache_hits_total", "Transaction details served from the cache", self.cache_hits),
+            ("solconnect_cache_misses_total", "Transaction details that had to be fetched", self.cache_misses),
+            ("solconnect_sent_bytes_total", "Request body bytes sent", self.bytes_sent),
+            ("solconnect_received_bytes_total", "Response body bytes received", self.bytes_received),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+        }
+        text.push_str("# HELP solconnect_phase_seconds Wall-clock time spent in each phase of the run\n# TYPE solconnect_phase_seconds gauge\n");
+        for (phase, elapsed) in &self.phases {
+            let _ = writeln!(text, "solconnect_phase_seconds{{phase=\"{}\"}} {:.3}", phase, elapsed.as_secs_f64());
+        }
+        text
+    }
+}
+
+/// A byte count in B, KB, MB or GB, for humans.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// How long the metrics server waits after failing to accept a connection.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Answers every HTTP request on `listener` with the current [`METRICS`] in the Prometheus text
+/// format, whatever its path, until the task is dropped.
+pub async fn serve_prometheus(listener: TcpListener) {
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Errors such as running out of file descriptors persist for a while; don't spin on them
+                warn!("Failed to accept a metrics connection: {}", e);
+                tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            // The request itself doesn't matter; read what has arrived so the client sees a clean reply
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let body = METRICS.snapshot().to_prometheus();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body
+            );
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                debug!("Failed to send metrics to {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_and_phases_add_up() {
+        let metrics = Metrics::new();
+        metrics.record_request(100, 2048);
+        metrics.record_request(50, 0);
+        metrics.record_retry();
+        metrics.record_rate_limited();
+        metrics.record_cache_lookup(true);
+        metrics.record_cache_lookup(false);
+        metrics.record_cache_lookup(false);
+        metrics.record_phase("details", Duration::from_millis(1500));
+        metrics.record_phase("graph", Duration::from_millis(250));
+        metrics.record_phase("details", Duration::from_millis(500));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.requests, snapshot.retries, snapshot.bytes_sent, snapshot.bytes_received), (2, 1, 150, 2048));
+        assert_eq!((snapshot.rate_limited, snapshot.cache_hits, snapshot.cache_misses), (1, 1, 2));
+        assert_eq!(snapshot.phases, vec![("details", Duration::from_secs(2)), ("graph", Duration::from_millis(250))]);
+
+        let text = snapshot.to_prometheus();
+        assert!(text.contains("# TYPE solconnect_requests_total counter\nsolconnect_requests_total 2\n"));
+        assert!(text.contains("solconnect_received_bytes_total 2048\n"));
+        assert!(text.contains("solconnect_phase_seconds{phase=\"details\"} 2.000\n"));
+    }
+
+    #[test]
+    fn byte_counts_are_shown_in_the_largest_fitting_unit() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    }
+}
@@ -0,0 +1,1046 @@
+//! Path searches over the connection graph.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+use crate::graph::{Edge, Graph};
+
+/// Breadth-first enumeration of every simple path from `start` to `end` with at most `max_depth` addresses.
+/// Cycles are avoided per path rather than globally, so alternate routes through the same node are kept.
+pub fn find_paths(graph: &Graph, start: &str, end: &str, max_depth: usize) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    find_paths_each(graph, start, end, max_depth, |path| {
+        paths.push(path);
+        true
+    });
+    paths
+}
+
+/// Like `find_paths`, but hands each path to `on_path` as soon as it is found, fewest hops
+/// first, and stops once `on_path` returns `false`.
+pub fn find_paths_each(graph: &Graph, start: &str, end: &str, max_depth: usize, mut on_path: impl FnMut(Vec<String>) -> bool) {
+    let mut queue = VecDeque::new();
+    queue.push_back((start.to_string(), vec![start.to_string()]));
+
+    while let Some((node, path)) = queue.pop_front() {
+        if node == end {
+            if !on_path(path) {
+                return;
+            }
+            continue;
+        }
+
+        if path.len() >= max_depth {
+            continue;
+        }
+
+        if let Some(next_nodes) = graph.get(&node) {
+            for next_node in next_nodes.keys() {
+                if !path.contains(next_node) {
+                    let mut new_path = path.clone();
+                    new_path.push(next_node.to_string());
+                    queue.push_back((next_node.to_string(), new_path));
+                }
+            }
+        }
+    }
+}
+
+/// Depth-first enumeration of every simple path from `start` to `end` with at most `max_depth`
+/// addresses, stopping as soon as `limit` paths have been found. As in `find_paths`, only the
+/// addresses on the current path are off limits, but the search holds a single path at a time
+/// instead of a queue of them, and the limit cuts it short on dense graphs. Neighbours are tried
+/// in address order, so a limited search always returns the same paths.
+pub fn find_paths_dfs(graph: &Graph, start: &str, end: &str, max_depth: usize, limit: Option<usize>) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    if limit == Some(0) {
+        return paths;
+    }
+    find_paths_dfs_each(graph, start, end, max_depth, |path| {
+        paths.push(path);
+        limit.is_none_or(|limit| paths.len() < limit)
+    });
+    paths
+}
+
+/// Like `find_paths_dfs`, but hands each path to `on_path` as soon as it is found, and stops once
+/// `on_path` returns `false`.
+pub fn find_paths_dfs_each(graph: &Graph, start: &str, end: &str, max_depth: usize, mut on_path: impl FnMut(Vec<String>) -> bool) {
+    if max_depth == 0 {
+        return;
+    }
+    if start == end {
+        on_path(vec![start.to_string()]);
+        return;
+    }
+
+    let neighbors = |node: &str| {
+        let mut next_nodes: Vec<&str> = graph.get(node).into_iter().flat_map(|n| n.keys()).map(String::as_str).collect();
+        next_nodes.sort_unstable();
+        next_nodes
+    };
+    let mut path = vec![start];
+    let mut on_path_nodes = HashSet::from([start]);
+    // The neighbours of each address on the path, and how many of them have been tried
+    let mut stack = vec![(neighbors(start), 0)];
+
+    while let Some((next_nodes, tried)) = stack.last_mut() {
+        let Some(&next_node) = next_nodes.get(*tried) else {
+            stack.pop();
+            if let Some(node) = path.pop() {
+                on_path_nodes.remove(node);
+            }
+            continue;
+        };
+        *tried += 1;
+
+        if on_path_nodes.contains(next_node) {
+            continue;
+        }
+        if next_node == end {
+            if !on_path(path.iter().chain([&end]).map(|n| n.to_string()).collect()) {
+                return;
+            }
+        } else if path.len() + 1 < max_depth {
+            path.push(next_node);
+            on_path_nodes.insert(next_node);
+            stack.push((neighbors(next_node), 0));
+        }
+    }
+}
+
+/// Like `find_paths`, but only keeps paths that move forward in time: every hop must be backed by a
+/// transaction no earlier than the one used for the previous hop. Each hop takes the earliest
+/// such transaction, which leaves the most room for the rest of the path. Hops in the same second
+/// are allowed, since `blockTime` can't order transactions within one block. Edges without any
+/// block times can't be placed in time and are never traversed.
+pub fn find_temporal_paths(graph: &Graph, start: &str, end: &str, max_depth: usize) -> Vec<Vec<String>> {
+    let mut queue = VecDeque::new();
+    queue.push_back((start.to_string(), vec![start.to_string()], i64::MIN));
+    let mut paths = Vec::new();
+
+    while let Some((node, path, arrived)) = queue.pop_front() {
+        if node == end {
+            paths.push(path);
+            continue;
+        }
+
+        if path.len() >= max_depth {
+            continue;
+        }
+
+        for (next_node, edge) in graph.get(&node).into_iter().flatten() {
+            if path.contains(next_node) {
+                continue;
+            }
+            if let Some(time) = edge.first_time_from(arrived) {
+                let mut new_path = path.clone();
+                new_path.push(next_node.to_string());
+                queue.push_back((next_node.to_string(), new_path, time));
+            }
+        }
+    }
+
+    paths
+}
+
+/// The time of each hop along `path` when it is walked forward in time, taking the earliest
+/// transaction that isn't before the previous hop, as `find_temporal_paths` does. `None` if the
+/// path can't be walked that way or isn't in the graph.
+pub fn hop_times(graph: &Graph, path: &[String]) -> Option<Vec<i64>> {
+    let mut arrived = i64::MIN;
+    let mut times = Vec::new();
+    for hop in path.windows(2) {
+        arrived = graph.get(&hop[0])?.get(&hop[1])?.first_time_from(arrived)?;
+        times.push(arrived);
+    }
+    Some(times)
+}
+
+/// Plain BFS that stops as soon as `end` is reached. Each node is visited once,
+/// which is what makes this much cheaper than enumerating every path.
+pub fn shortest_path(graph: &Graph, start: &str, end: &str, max_depth: usize) -> Option<Vec<String>> {
+    restricted_shortest_path(graph, start, end, max_depth, &HashSet::new(), &HashSet::new())
+}
+
+/// Shortest path that may not pass through `excluded_nodes` or traverse `excluded_edges`.
+fn restricted_shortest_path(
+    graph: &Graph,
+    start: &str,
+    end: &str,
+    max_depth: usize,
+    excluded_nodes: &HashSet<&str>,
+    excluded_edges: &HashSet<(&str, &str)>,
+) -> Option<Vec<String>> {
+    let mut parents: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 1));
+    let mut visited = HashSet::from([start]);
+
+    while let Some((node, depth)) = queue.pop_front() {
+        if node == end {
+            let mut path = vec![end.to_string()];
+            let mut current = end;
+            while let Some(parent) = parents.get(current) {
+                path.push(parent.to_string());
+                current = parent;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        if depth >= max_depth {
+            continue;
+        }
+
+        for next_node in graph.get(node).into_iter().flat_map(|n| n.keys()) {
+            let next_node = next_node.as_str();
+            if excluded_nodes.contains(next_node) || excluded_edges.contains(&(node, next_node)) {
+                continue;
+            }
+            if visited.insert(next_node) {
+                parents.insert(next_node, node);
+                queue.push_back((next_node, depth + 1));
+            }
+        }
+    }
+
+    None
+}
+
+/// Follows parent links from `node` back to the root of a BFS tree, returning the nodes from `node` to the root.
+fn trace_parents<'a>(parents: &HashMap<&'a str, (Option<&'a str>, usize)>, node: &'a str) -> Vec<String> {
+    let mut chain = vec![node.to_string()];
+    let mut current = node;
+    while let Some((Some(parent), _)) = parents.get(current) {
+        chain.push(parent.to_string());
+        current = parent;
+    }
+    chain
+}
+
+/// Breadth-first search from both endpoints at once, always growing the smaller frontier by a full
+/// level. When the frontiers touch, the shortest connection found in that level is the overall
+/// shortest path, so this returns a path of the same length as `shortest_path` while exploring
+/// far fewer nodes on large graphs. In directed mode the backward search follows reversed edges.
+pub fn bidirectional_shortest_path(graph: &Graph, start: &str, end: &str, max_depth: usize, directed: bool) -> Option<Vec<String>> {
+    if start == end {
+        return Some(vec![start.to_string()]);
+    }
+
+    let reverse;
+    let backward_graph = if directed {
+        reverse = reversed(graph);
+        &reverse
+    } else {
+        graph
+    };
+
+    // node -> (parent, distance from the search root in edges)
+    let mut forward: HashMap<&str, (Option<&str>, usize)> = HashMap::from([(start, (None, 0))]);
+    let mut backward: HashMap<&str, (Option<&str>, usize)> = HashMap::from([(end, (None, 0))]);
+    let mut forward_frontier = vec![start];
+    let mut backward_frontier = vec![end];
+    let (mut forward_depth, mut backward_depth) = (0, 0);
+    let max_edges = max_depth.saturating_sub(1);
+
+    while !forward_frontier.is_empty() && !backward_frontier.is_empty() && forward_depth + backward_depth < max_edges {
+        let expand_forward = forward_frontier.len() <= backward_frontier.len();
+        let (frontier, this_side, other_side, adjacency) = if expand_forward {
+            (&mut forward_frontier, &mut forward, &backward, graph)
+        } else {
+            (&mut backward_frontier, &mut backward, &forward, backward_graph)
+        };
+
+        // (node on this side, node on the other side, total path length in edges)
+        let mut best: Option<(&str, &str, usize)> = None;
+        let mut next_frontier = Vec::new();
+        for node in frontier.iter().copied() {
+            let depth = this_side[node].1;
+            for next_node in adjacency.get(node).into_iter().flat_map(|n| n.keys()) {
+                let next_node = next_node.as_str();
+                if let Some((_, other_depth)) = other_side.get(next_node) {
+                    let total = depth + 1 + other_depth;
+                    if total <= max_edges && best.is_none_or(|(_, _, best_total)| total < best_total) {
+                        best = Some((node, next_node, total));
+                    }
+                }
+                if !this_side.contains_key(next_node) {
+                    this_side.insert(next_node, (Some(node), depth + 1));
+                    next_frontier.push(next_node);
+                }
+            }
+        }
+
+        if let Some((this_node, other_node, _)) = best {
+            let (forward_node, backward_node) = if expand_forward { (this_node, other_node) } else { (other_node, this_node) };
+            let mut path = trace_parents(&forward, forward_node);
+            path.reverse();
+            path.extend(trace_parents(&backward, backward_node));
+            return Some(path);
+        }
+
+        *frontier = next_frontier;
+        if expand_forward {
+            forward_depth += 1;
+        } else {
+            backward_depth += 1;
+        }
+    }
+
+    None
+}
+
+/// `graph` with every edge pointing the other way.
+fn reversed(graph: &Graph) -> Graph {
+    let mut reverse = Graph::new();
+    for (from, neighbors) in graph {
+        for (to, edge) in neighbors {
+            reverse.entry(to.clone()).or_default().insert(from.clone(), edge.clone());
+        }
+    }
+    reverse
+}
+
+/// The number of hops to each node from the root of a breadth-first search.
+type HopDistances = HashMap<String, usize>;
+
+/// Hop distances from every node in `graph` reachable from `start`.
+fn hop_distances(graph: &Graph, start: &str) -> HopDistances {
+    let mut distances = HashMap::from([(start.to_string(), 0)]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((node, distance)) = queue.pop_front() {
+        for next_node in graph.get(node).into_iter().flat_map(|n| n.keys()) {
+            if !distances.contains_key(next_node) {
+                distances.insert(next_node.clone(), distance + 1);
+                queue.push_back((next_node.as_str(), distance + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// The hop distances from and to a few landmark nodes of a graph, from which [`astar_path`]
+/// estimates how far any node is from its target. Computing them takes two breadth-first searches
+/// per landmark (one in an undirected graph), so they are built once and shared by every search
+/// over the same graph.
+pub struct Landmarks {
+    /// Per landmark: the distance from it to each node it reaches, and from each node that reaches
+    /// it to it. Undirected graphs only have the first.
+    distances: Vec<(HopDistances, Option<HopDistances>)>,
+}
+
+impl Landmarks {
+    /// Picks up to `count` landmarks spread over the graph: the node with the most neighbors first,
+    /// then each time the node farthest from the landmarks picked so far, among those the first
+    /// one reaches.
+    pub fn new(graph: &Graph, directed: bool, count: usize) -> Self {
+        let reverse = directed.then(|| reversed(graph));
+        let mut distances = Vec::new();
+        // Ties go to the smallest address, so the same graph always gets the same landmarks
+        let first = graph.iter().max_by(|(a, a_neighbors), (b, b_neighbors)| a_neighbors.len().cmp(&b_neighbors.len()).then_with(|| b.cmp(a)));
+        let Some((first, _)) = first else {
+            return Landmarks { distances };
+        };
+        // The smallest distance from any landmark to each node the first landmark reaches
+        let mut nearest = HopDistances::new();
+        let mut landmark = first.clone();
+        while distances.len() < count {
+            let from = hop_distances(graph, &landmark);
+            let to = reverse.as_ref().map(|reverse| hop_distances(reverse, &landmark));
+            if nearest.is_empty() {
+                nearest = from.clone();
+            } else {
+                for (node, distance) in &from {
+                    if let Some(nearest) = nearest.get_mut(node) {
+                        *nearest = (*nearest).min(*distance);
+                    }
+                }
+            }
+            distances.push((from, to));
+            let farthest = nearest.iter()
+                .filter(|(_, &distance)| distance > 0)
+                .max_by(|(a, a_distance), (b, b_distance)| a_distance.cmp(b_distance).then_with(|| b.cmp(a)));
+            match farthest {
+                Some((next, _)) => landmark = next.clone(),
+                None => break,
+            }
+        }
+        Landmarks { distances }
+    }
+
+    /// A lower bound on the number of hops from `node` to `end` by the triangle inequality, or
+    /// `None` if some landmark shows that `node` can't reach `end` at all.
+    fn estimate(&self, node: &str, end: &str) -> Option<usize> {
+        let mut estimate = 0;
+        for (from, to) in &self.distances {
+            let to = to.as_ref().unwrap_or(from);
+            // A landmark that reaches `node` but not `end` proves there is no way from one to the
+            // other, and so does one that `end` reaches but `node` doesn't
+            match (from.get(node), from.get(end)) {
+                (Some(node_distance), Some(end_distance)) => estimate = estimate.max(end_distance.saturating_sub(*node_distance)),
+                (Some(_), None) => return None,
+                _ => {}
+            }
+            match (to.get(node), to.get(end)) {
+                (Some(node_distance), Some(end_distance)) => estimate = estimate.max(node_distance.saturating_sub(*end_distance)),
+                (None, Some(_)) => return None,
+                _ => {}
+            }
+        }
+        Some(estimate)
+    }
+}
+
+/// A* search for a shortest path of at most `max_depth` addresses, guided by the distance estimates
+/// of `landmarks`, which must have been built from the same graph. It returns a path as short as
+/// [`shortest_path`] does, but heads for `end` instead of exploring every direction, so it visits
+/// far fewer nodes of a large, dense graph. Among nodes that look equally promising the one with
+/// fewer neighbors is expanded first, which steers the path around hubs when an equally short
+/// route through ordinary addresses exists.
+pub fn astar_path(graph: &Graph, landmarks: &Landmarks, start: &str, end: &str, max_depth: usize) -> Option<Vec<String>> {
+    let max_edges = max_depth.checked_sub(1)?;
+    let degree = |node: &str| graph.get(node).map_or(0, |neighbors| neighbors.len());
+    // node -> (parent, hops from `start`)
+    let mut reached: HashMap<&str, (Option<&str>, usize)> = HashMap::from([(start, (None, 0))]);
+    let mut closed: HashSet<&str> = HashSet::new();
+    // Lowest estimated total length first, then the node closest to `end`, then the fewest neighbors
+    let mut open = BinaryHeap::new();
+    let estimate = landmarks.estimate(start, end)?;
+    open.push(Reverse((estimate, estimate, degree(start), start)));
+
+    while let Some(Reverse((_, _, _, node))) = open.pop() {
+        if node == end {
+            let mut path = trace_parents(&reached, node);
+            path.reverse();
+            return Some(path);
+        }
+        if !closed.insert(node) {
+            continue;
+        }
+        let hops = reached[node].1 + 1;
+        for next_node in graph.get(node).into_iter().flat_map(|n| n.keys()) {
+            let next_node = next_node.as_str();
+            if closed.contains(next_node) || reached.get(next_node).is_some_and(|(_, known)| *known <= hops) {
+                continue;
+            }
+            let Some(remaining) = landmarks.estimate(next_node, end) else {
+                continue;
+            };
+            if hops + remaining > max_edges {
+                continue;
+            }
+            reached.insert(next_node, (Some(node), hops));
+            open.push(Reverse((hops + remaining, remaining, degree(next_node), next_node)));
+        }
+    }
+
+    None
+}
+
+/// What a hop costs in [`cheapest_path`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weight {
+    /// Every hop costs the same, so the cheapest path is a shortest one
+    Hops,
+    /// Hops that moved more SOL cost less, so the cheapest path follows the money. Token amounts
+    /// have no common unit and don't count.
+    Value,
+    /// Hops backed by more transactions cost less, so the cheapest path follows the strongest links
+    Count,
+}
+
+impl Weight {
+    /// The cost of following `edge`, always more than zero and at most one hop.
+    pub fn cost(self, edge: &Edge) -> f64 {
+        match self {
+            Weight::Hops => 1.0,
+            Weight::Value => 1.0 / (1.0 + edge.lamports as f64 / LAMPORTS_PER_SOL as f64),
+            Weight::Count => 1.0 / f64::from(edge.count.max(1)),
+        }
+    }
+}
+
+impl FromStr for Weight {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "hops" => Ok(Weight::Hops),
+            "value" => Ok(Weight::Value),
+            "count" => Ok(Weight::Count),
+            other => Err(format!("unknown path weight: {}", other)),
+        }
+    }
+}
+
+/// A path of [`cheapest_path`] waiting to be extended, ordered cheapest first and then by fewest hops.
+struct Candidate {
+    cost: f64,
+    hops: usize,
+    /// Index of the path's last address in the list of reached ones
+    reached: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed, so the cheapest candidate is at the top of the heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then(other.hops.cmp(&self.hops)).then(other.reached.cmp(&self.reached))
+    }
+}
+
+/// Dijkstra's algorithm: the path from `start` to `end` of at most `max_depth` addresses whose hops
+/// cost the least in total, each hop costing what `cost` says of its edge, which must not be
+/// negative. An address is settled again when reached in fewer hops, however much more it costs
+/// by then, so a cheap but long route doesn't hide a costlier one that fits in `max_depth`.
+pub fn cheapest_path(graph: &Graph, start: &str, end: &str, max_depth: usize, cost: impl Fn(&Edge) -> f64) -> Option<Vec<String>> {
+    let max_edges = max_depth.checked_sub(1)?;
+    // (address, index of the one before it)
+    let mut reached: Vec<(&str, Option<usize>)> = vec![(start, None)];
+    // address -> the fewest hops it has been settled at
+    let mut settled: HashMap<&str, usize> = HashMap::new();
+    let mut open = BinaryHeap::from([Candidate { cost: 0.0, hops: 0, reached: 0 }]);
+
+    while let Some(Candidate { cost: total, hops, reached: index }) = open.pop() {
+        let node = reached[index].0;
+        if node == end {
+            let mut path = Vec::with_capacity(hops + 1);
+            let mut at = Some(index);
+            while let Some(index) = at {
+                path.push(reached[index].0.to_string());
+                at = reached[index].1;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // Anything settled before was at most as cheap, so it's only beaten on hops
+        if settled.get(node).is_some_and(|fewest| *fewest <= hops) {
+            continue;
+        }
+        settled.insert(node, hops);
+        if hops == max_edges {
+            continue;
+        }
+        for (next_node, edge) in graph.get(node).into_iter().flatten() {
+            if settled.get(next_node.as_str()).is_some_and(|fewest| *fewest <= hops + 1) {
+                continue;
+            }
+            reached.push((next_node, Some(index)));
+            open.push(Candidate { cost: total + cost(edge), hops: hops + 1, reached: reached.len() - 1 });
+        }
+    }
+
+    None
+}
+
+/// Yen's algorithm: up to `k` distinct loopless paths in order of increasing length.
+/// Each new path deviates from a previous one at some "spur" node, with the edges already
+/// taken from that prefix removed so the spur search is forced onto a different route.
+pub fn k_shortest_paths(graph: &Graph, start: &str, end: &str, k: usize, max_depth: usize) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    k_shortest_paths_each(graph, start, end, k, max_depth, |path| {
+        paths.push(path.to_vec());
+        true
+    });
+    paths
+}
+
+/// Like `k_shortest_paths`, but hands each path to `on_path` as soon as it is settled, and stops
+/// once `on_path` returns `false`.
+pub fn k_shortest_paths_each(graph: &Graph, start: &str, end: &str, k: usize, max_depth: usize, mut on_path: impl FnMut(&[String]) -> bool) {
+    let mut found: Vec<Vec<String>> = Vec::new();
+    let mut candidates: Vec<Vec<String>> = Vec::new();
+
+    match shortest_path(graph, start, end, max_depth) {
+        Some(path) if k > 0 => found.push(path),
+        _ => return,
+    }
+    if !on_path(&found[0]) {
+        return;
+    }
+
+    while found.len() < k {
+        let previous = found[found.len() - 1].clone();
+        for i in 0..previous.len() - 1 {
+            let root = &previous[..=i];
+            let spur = previous[i].as_str();
+
+            let excluded_edges: HashSet<(&str, &str)> = found.iter()
+                .filter(|path| path.len() > i + 1 && path[..=i] == *root)
+                .map(|path| (path[i].as_str(), path[i + 1].as_str()))
+                .collect();
+            let excluded_nodes: HashSet<&str> = root[..i].iter().map(String::as_str).collect();
+
+            if let Some(spur_path) = restricted_shortest_path(graph, spur, end, max_depth - i, &excluded_nodes, &excluded_edges) {
+                let mut candidate = root[..i].to_vec();
+                candidate.extend(spur_path);
+                if !found.contains(&candidate) && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            break;
+        }
+        // Shortest candidate first; ties are broken by address order so results are stable
+        candidates.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        found.push(candidates.remove(0));
+        if !on_path(&found[found.len() - 1]) {
+            return;
+        }
+    }
+}
+
+/// The number of transactions behind the hops of `path`, added up. Hops missing from the graph count nothing.
+pub fn path_weight(graph: &Graph, path: &[String]) -> u64 {
+    path.windows(2)
+        .filter_map(|hop| graph.get(&hop[0]).and_then(|neighbors| neighbors.get(&hop[1])))
+        .map(|edge| u64::from(edge.count))
+        .sum()
+}
+
+/// Drops exact duplicates from `paths` and orders the rest fewest hops first, then by
+/// [`path_weight`] with the strongest first, then by address so the order is stable.
+pub fn rank_paths(graph: &Graph, mut paths: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    paths.sort();
+    paths.dedup();
+    let mut weighted: Vec<(u64, Vec<String>)> = paths.into_iter().map(|path| (path_weight(graph, &path), path)).collect();
+    weighted.sort_by(|(weight_a, a), (weight_b, b)| a.len().cmp(&b.len()).then(weight_b.cmp(weight_a)).then_with(|| a.cmp(b)));
+    weighted.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Every simple cycle through at least one of `starts`, up to `max_depth` distinct addresses long,
+/// found by a depth-first search from each start that tracks the addresses on the current path.
+/// Cycles follow edges in their stored direction, so they are only meaningful in a directed graph,
+/// where a cycle is value that came back to where it left. Each cycle is closed by repeating its
+/// first address (`[A, B, C, A]`) and reported once, starting at the first of `starts` on it, no
+/// matter from which of them it was found. The cycles of each start are ordered shortest first.
+pub fn find_cycles(graph: &Graph, starts: &[&str], max_depth: usize) -> Vec<Vec<String>> {
+    // Cycles seen so far, rotated to begin at their smallest address
+    let mut seen: HashSet<Vec<&str>> = HashSet::new();
+    let mut cycles = Vec::new();
+
+    for &start in starts {
+        let mut found = Vec::new();
+        let mut stack = vec![vec![start]];
+        while let Some(path) = stack.pop() {
+            let node = path[path.len() - 1];
+            for next_node in graph.get(node).into_iter().flat_map(|n| n.keys()).map(String::as_str) {
+                if next_node == start {
+                    let smallest = (0..path.len()).min_by_key(|&i| path[i]).unwrap_or_default();
+                    let rotated = [&path[smallest..], &path[..smallest]].concat();
+                    if path.len() >= 2 && seen.insert(rotated) {
+                        found.push(path.iter().chain([&start]).map(|n| n.to_string()).collect::<Vec<String>>());
+                    }
+                } else if path.len() < max_depth && !path.contains(&next_node) {
+                    let mut new_path = path.clone();
+                    new_path.push(next_node);
+                    stack.push(new_path);
+                }
+            }
+        }
+        found.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        cycles.extend(found);
+    }
+
+    cycles
+}
+
+/// Breadth-first rings around `start`: element `i` holds the addresses exactly `i + 1` hops away,
+/// sorted, for every distance up to `max_degree`. Edges are followed in their stored direction,
+/// so in a directed graph the rings are what `start` reaches rather than what reaches it.
+pub fn neighbor_rings(graph: &Graph, start: &str, max_degree: usize) -> Vec<Vec<String>> {
+    let mut visited = HashSet::from([start]);
+    let mut frontier = vec![start];
+    let mut rings = Vec::new();
+
+    while rings.len() < max_degree && !frontier.is_empty() {
+        let mut ring = Vec::new();
+        for node in frontier {
+            for next_node in graph.get(node).into_iter().flat_map(|n| n.keys()) {
+                if visited.insert(next_node.as_str()) {
+                    ring.push(next_node.as_str());
+                }
+            }
+        }
+        ring.sort_unstable();
+        rings.push(ring.iter().map(|n| n.to_string()).collect());
+        frontier = ring;
+    }
+
+    // The last ring is empty when the component ran out before `max_degree`
+    if rings.last().is_some_and(Vec::is_empty) {
+        rings.pop();
+    }
+    rings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{add_edge, build_transaction_graph, GraphOptions};
+
+    fn undirected(edges: &[(&str, &str)]) -> Graph {
+        let mut graph = Graph::new();
+        for (a, b) in edges {
+            add_edge(&mut graph, a, b, &Edge { count: 1, ..Edge::default() }, false);
+        }
+        graph
+    }
+
+    fn path(nodes: &[&str]) -> Vec<String> {
+        nodes.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn timed(edges: &[(&str, &str, &[i64])]) -> Graph {
+        let mut graph = Graph::new();
+        for (a, b, times) in edges {
+            add_edge(&mut graph, a, b, &Edge { count: times.len() as u32, times: times.to_vec(), ..Edge::default() }, false);
+        }
+        graph
+    }
+
+    fn directed(edges: &[(&str, &str)]) -> Graph {
+        let mut graph = Graph::new();
+        for (a, b) in edges {
+            add_edge(&mut graph, a, b, &Edge { count: 1, ..Edge::default() }, true);
+        }
+        graph
+    }
+
+    #[test]
+    fn ranked_paths_put_short_and_heavy_routes_first() {
+        let mut graph = undirected(&[("A", "B"), ("B", "D"), ("A", "X"), ("X", "Y"), ("Y", "D")]);
+        add_edge(&mut graph, "A", "C", &Edge { count: 5, ..Edge::default() }, false);
+        add_edge(&mut graph, "C", "D", &Edge { count: 5, ..Edge::default() }, false);
+        let found = vec![
+            path(&["A", "X", "Y", "D"]),
+            path(&["A", "B", "D"]),
+            path(&["A", "C", "D"]),
+            path(&["A", "B", "D"]),
+        ];
+        assert_eq!(path_weight(&graph, &found[2]), 10);
+        assert_eq!(rank_paths(&graph, found), vec![path(&["A", "C", "D"]), path(&["A", "B", "D"]), path(&["A", "X", "Y", "D"])]);
+    }
+
+    #[test]
+    fn cycles_through_the_queries_are_reported_once() {
+        // A -> B -> C -> A and A -> B -> A involve A; D -> E -> D doesn't involve any query
+        let graph = directed(&[("A", "B"), ("B", "C"), ("C", "A"), ("B", "A"), ("D", "E"), ("E", "D"), ("C", "D")]);
+        assert_eq!(find_cycles(&graph, &["A"], 5), vec![path(&["A", "B", "A"]), path(&["A", "B", "C", "A"])]);
+
+        // Found from both A and C, but listed once, from A
+        assert_eq!(find_cycles(&graph, &["A", "C"], 5).len(), 2);
+        assert_eq!(find_cycles(&graph, &["C", "A"], 5), vec![path(&["C", "A", "B", "C"]), path(&["A", "B", "A"])]);
+
+        // --max-depth counts the distinct addresses of a cycle
+        assert_eq!(find_cycles(&graph, &["A"], 2), vec![path(&["A", "B", "A"])]);
+        assert!(find_cycles(&graph, &["E", "Z"], 1).is_empty());
+    }
+
+    #[test]
+    fn temporal_paths_only_move_forward_in_time() {
+        // A -> B at 100, then B -> D at 50 is impossible, but B -> C -> D works
+        let graph = timed(&[("A", "B", &[100]), ("B", "D", &[50]), ("B", "C", &[90, 150]), ("C", "D", &[200])]);
+        assert_eq!(find_paths(&graph, "A", "D", 5).len(), 2);
+        assert_eq!(find_temporal_paths(&graph, "A", "D", 5), vec![path(&["A", "B", "C", "D"])]);
+        assert_eq!(hop_times(&graph, &path(&["A", "B", "C", "D"])), Some(vec![100, 150, 200]));
+        assert_eq!(hop_times(&graph, &path(&["A", "B", "D"])), None);
+    }
+
+    #[test]
+    fn temporal_paths_allow_hops_in_the_same_second_and_skip_untimed_edges() {
+        let graph = timed(&[("A", "B", &[100]), ("B", "C", &[100]), ("A", "D", &[]), ("D", "C", &[300])]);
+        assert_eq!(find_temporal_paths(&graph, "A", "C", 5), vec![path(&["A", "B", "C"])]);
+    }
+
+    #[test]
+    fn find_paths_returns_both_routes_through_a_diamond() {
+        let graph = undirected(&[("A", "B"), ("B", "D"), ("A", "C"), ("C", "D")]);
+
+        let mut paths = find_paths(&graph, "A", "D", 50);
+        paths.sort();
+
+        assert_eq!(paths, vec![path(&["A", "B", "D"]), path(&["A", "C", "D"])]);
+    }
+
+    #[test]
+    fn find_paths_follows_a_linear_chain() {
+        let graph = undirected(&[("A", "B"), ("B", "C"), ("C", "D")]);
+
+        assert_eq!(find_paths(&graph, "A", "D", 50), vec![path(&["A", "B", "C", "D"])]);
+        assert_eq!(find_paths(&graph, "D", "A", 50), vec![path(&["D", "C", "B", "A"])]);
+    }
+
+    #[test]
+    fn find_paths_keeps_routes_that_share_a_later_node() {
+        // Both routes pass through D; a search-wide visited set would only ever return one of them
+        let graph = undirected(&[("A", "B"), ("B", "D"), ("A", "C"), ("C", "D"), ("D", "E")]);
+
+        let mut paths = find_paths(&graph, "A", "E", 50);
+        paths.sort();
+
+        assert_eq!(paths, vec![path(&["A", "B", "D", "E"]), path(&["A", "C", "D", "E"])]);
+    }
+
+    #[test]
+    fn find_paths_terminates_on_a_cycle_without_revisiting_nodes() {
+        // A triangle A - B - C with a tail C - D
+        let graph = undirected(&[("A", "B"), ("B", "C"), ("C", "A"), ("C", "D")]);
+
+        let mut paths = find_paths(&graph, "A", "D", 50);
+        paths.sort();
+
+        assert_eq!(paths, vec![path(&["A", "B", "C", "D"]), path(&["A", "C", "D"])]);
+        assert!(paths.iter().all(|p| p.iter().collect::<HashSet<_>>().len() == p.len()));
+    }
+
+    #[test]
+    fn find_paths_returns_nothing_for_a_disconnected_pair() {
+        let graph = undirected(&[("A", "B"), ("C", "D")]);
+
+        assert!(find_paths(&graph, "A", "D", 50).is_empty());
+        assert!(find_paths(&graph, "A", "Unknown", 50).is_empty());
+    }
+
+    #[test]
+    fn find_paths_respects_max_depth() {
+        let graph = undirected(&[("A", "B"), ("B", "D"), ("A", "C"), ("C", "E"), ("E", "D")]);
+
+        assert_eq!(find_paths(&graph, "A", "D", 3), vec![path(&["A", "B", "D"])]);
+        assert_eq!(find_paths(&graph, "A", "D", 4).len(), 2);
+    }
+
+    #[test]
+    fn find_paths_dfs_returns_every_simple_path_exactly_once() {
+        // Every pair among five addresses is linked: 1 direct route from A to E, 3 through one
+        // other address, 6 through two and 6 through all three
+        let nodes = ["A", "B", "C", "D", "E"];
+        let links: Vec<(&str, &str)> = nodes.iter().enumerate().flat_map(|(i, &a)| nodes[i + 1..].iter().map(move |&b| (a, b))).collect();
+        let graph = undirected(&links);
+
+        let mut paths = find_paths_dfs(&graph, "A", "E", 50, None);
+        assert_eq!(paths.len(), 16);
+        assert!(paths.iter().all(|p| p.iter().collect::<HashSet<_>>().len() == p.len()));
+        assert!(paths.iter().all(|p| p[0] == "A" && p[p.len() - 1] == "E"));
+
+        let mut expected = find_paths(&graph, "A", "E", 50);
+        paths.sort();
+        expected.sort();
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn find_paths_dfs_respects_max_depth_and_the_limit() {
+        let graph = undirected(&[("A", "B"), ("B", "D"), ("A", "C"), ("C", "E"), ("E", "D"), ("B", "C")]);
+
+        assert_eq!(find_paths_dfs(&graph, "A", "D", 3, None), vec![path(&["A", "B", "D"])]);
+        let mut paths = find_paths_dfs(&graph, "A", "D", 4, None);
+        paths.sort();
+        assert_eq!(paths, vec![path(&["A", "B", "D"]), path(&["A", "C", "B", "D"]), path(&["A", "C", "E", "D"])]);
+
+        // Neighbours are tried in address order, so the first paths found are always the same
+        assert_eq!(find_paths_dfs(&graph, "A", "D", 50, Some(2)), vec![path(&["A", "B", "C", "E", "D"]), path(&["A", "B", "D"])]);
+        assert!(find_paths_dfs(&graph, "A", "D", 50, Some(0)).is_empty());
+        assert!(find_paths_dfs(&graph, "A", "Unknown", 50, None).is_empty());
+    }
+
+    #[test]
+    fn path_visitors_see_paths_in_search_order_and_stop_when_told() {
+        let graph = undirected(&[("A", "B"), ("B", "D"), ("A", "C"), ("C", "E"), ("E", "D"), ("B", "C")]);
+        let mut bfs = Vec::new();
+        find_paths_each(&graph, "A", "D", 50, |path| {
+            bfs.push(path);
+            bfs.len() < 2
+        });
+        assert_eq!(bfs, find_paths(&graph, "A", "D", 50)[..2].to_vec());
+
+        let mut dfs = Vec::new();
+        find_paths_dfs_each(&graph, "A", "D", 50, |path| {
+            dfs.push(path);
+            dfs.len() < 2
+        });
+        assert_eq!(dfs, find_paths_dfs(&graph, "A", "D", 50, Some(2)));
+
+        let mut yen = Vec::new();
+        k_shortest_paths_each(&graph, "A", "D", 10, 50, |path| {
+            yen.push(path.to_vec());
+            yen.len() < 2
+        });
+        assert_eq!(yen, k_shortest_paths(&graph, "A", "D", 2, 50));
+    }
+
+    #[test]
+    fn shortest_path_picks_the_fewest_hops() {
+        let graph = undirected(&[("A", "B"), ("B", "C"), ("C", "D"), ("A", "E"), ("E", "D")]);
+
+        assert_eq!(shortest_path(&graph, "A", "D", 50), Some(path(&["A", "E", "D"])));
+        assert_eq!(shortest_path(&graph, "A", "D", 2), None);
+        assert_eq!(shortest_path(&graph, "A", "Z", 50), None);
+    }
+
+    #[test]
+    fn k_shortest_paths_are_distinct_and_ordered_by_length() {
+        let graph = undirected(&[
+            ("A", "B"), ("B", "F"),
+            ("A", "C"), ("C", "D"), ("D", "F"),
+            ("A", "E"), ("E", "G"), ("G", "H"), ("H", "F"),
+            ("C", "B"),
+        ]);
+
+        let paths = k_shortest_paths(&graph, "A", "F", 10, 50);
+
+        assert_eq!(paths[0], path(&["A", "B", "F"]));
+        assert!(paths.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+        let unique: HashSet<&Vec<String>> = paths.iter().collect();
+        assert_eq!(unique.len(), paths.len());
+        for p in &paths {
+            let nodes: HashSet<&String> = p.iter().collect();
+            assert_eq!(nodes.len(), p.len(), "path {:?} revisits a node", p);
+        }
+        // Every simple A -> F path in this graph
+        let mut all = find_paths(&graph, "A", "F", 50);
+        all.sort();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(sorted, all);
+
+        assert_eq!(k_shortest_paths(&graph, "A", "F", 2, 50).len(), 2);
+        assert!(k_shortest_paths(&graph, "A", "Z", 3, 50).is_empty());
+    }
+
+    #[test]
+    fn bidirectional_search_matches_plain_bfs_length() {
+        // A 6x6 grid plus a shortcut, so many equal-length routes exist
+        let mut edges = Vec::new();
+        let names: Vec<Vec<String>> = (0..6).map(|r| (0..6).map(|c| format!("n{}{}", r, c)).collect()).collect();
+        for r in 0..6 {
+            for c in 0..6 {
+                if r + 1 < 6 { edges.push((names[r][c].as_str(), names[r + 1][c].as_str())); }
+                if c + 1 < 6 { edges.push((names[r][c].as_str(), names[r][c + 1].as_str())); }
+            }
+        }
+        edges.push(("n00", "n33"));
+        let graph = undirected(&edges);
+
+        for (start, end) in [("n00", "n55"), ("n05", "n50"), ("n11", "n44"), ("n02", "n02")] {
+            for max_depth in [1, 3, 5, 8, 50] {
+                let plain = shortest_path(&graph, start, end, max_depth);
+                let bidirectional = bidirectional_shortest_path(&graph, start, end, max_depth, false);
+                assert_eq!(plain.as_ref().map(Vec::len), bidirectional.as_ref().map(Vec::len), "{} -> {} depth {}", start, end, max_depth);
+                if let Some(found) = bidirectional {
+                    assert_eq!(found.first().map(String::as_str), Some(start));
+                    assert_eq!(found.last().map(String::as_str), Some(end));
+                    assert!(found.windows(2).all(|hop| graph[&hop[0]].contains_key(&hop[1])));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bidirectional_search_follows_edge_direction() {
+        let transactions = vec![
+            serde_json::json!({ "transaction": { "message": { "accountKeys": ["A", "B"] } } }),
+            serde_json::json!({ "transaction": { "message": { "accountKeys": ["B", "C"] } } }),
+        ];
+        let graph = build_transaction_graph(&transactions, &GraphOptions { directed: true, ..GraphOptions::default() });
+
+        assert_eq!(bidirectional_shortest_path(&graph, "A", "C", 50, true), Some(path(&["A", "B", "C"])));
+        assert_eq!(bidirectional_shortest_path(&graph, "C", "A", 50, true), None);
+    }
+
+    #[test]
+    fn astar_finds_paths_as_short_as_bfs() {
+        let mut edges = Vec::new();
+        let names: Vec<Vec<String>> = (0..6).map(|r| (0..6).map(|c| format!("n{}{}", r, c)).collect()).collect();
+        for r in 0..6 {
+            for c in 0..6 {
+                if r + 1 < 6 { edges.push((names[r][c].as_str(), names[r + 1][c].as_str())); }
+                if c + 1 < 6 { edges.push((names[r][c].as_str(), names[r][c + 1].as_str())); }
+            }
+        }
+        edges.push(("n00", "n33"));
+        edges.push(("x", "y"));
+
+        for graph_directed in [false, true] {
+            let graph = if graph_directed { directed(&edges) } else { undirected(&edges) };
+            for count in [0, 1, 4] {
+                let landmarks = Landmarks::new(&graph, graph_directed, count);
+                for (start, end) in [("n00", "n55"), ("n55", "n00"), ("n05", "n50"), ("n11", "n44"), ("n02", "n02"), ("n00", "x")] {
+                    for max_depth in [1, 3, 5, 8, 50] {
+                        let plain = shortest_path(&graph, start, end, max_depth);
+                        let found = astar_path(&graph, &landmarks, start, end, max_depth);
+                        assert_eq!(plain.as_ref().map(Vec::len), found.as_ref().map(Vec::len), "{} -> {} depth {}", start, end, max_depth);
+                        if let Some(found) = found {
+                            assert_eq!(found.first().map(String::as_str), Some(start));
+                            assert_eq!(found.last().map(String::as_str), Some(end));
+                            assert!(found.windows(2).all(|hop| graph[&hop[0]].contains_key(&hop[1])));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn astar_routes_around_hubs() {
+        let mut edges = vec![("A", "hub"), ("hub", "D"), ("A", "quiet"), ("quiet", "D")];
+        let spokes: Vec<String> = (0..10).map(|n| format!("spoke{}", n)).collect();
+        edges.extend(spokes.iter().map(|spoke| ("hub", spoke.as_str())));
+        let graph = undirected(&edges);
+
+        let landmarks = Landmarks::new(&graph, false, 3);
+        assert_eq!(astar_path(&graph, &landmarks, "A", "D", 50), Some(path(&["A", "quiet", "D"])));
+        assert_eq!(astar_path(&graph, &landmarks, "A", "spoke3", 50), Some(path(&["A", "hub", "spoke3"])));
+        assert_eq!(astar_path(&graph, &landmarks, "A", "spoke3", 2), None);
+    }
+
+    #[test]
+    fn cheapest_paths_follow_the_weight_instead_of_the_hop_count() {
+        // A - B - D is the shortest route; A - C - E - D moved more SOL over busier links
+        let mut graph = Graph::new();
+        let sol = LAMPORTS_PER_SOL;
+        for (a, b, count, lamports) in [("A", "B", 1, 0), ("B", "D", 1, 0), ("A", "C", 5, 40 * sol), ("C", "E", 9, 90 * sol), ("E", "D", 4, 60 * sol)] {
+            add_edge(&mut graph, a, b, &Edge { count, lamports, ..Edge::default() }, false);
+        }
+
+        let bfs = shortest_path(&graph, "A", "D", 50);
+        assert_eq!(cheapest_path(&graph, "A", "D", 50, |edge| Weight::Hops.cost(edge)), bfs);
+        assert_eq!(bfs, Some(path(&["A", "B", "D"])));
+        assert_eq!(cheapest_path(&graph, "A", "D", 50, |edge| Weight::Value.cost(edge)), Some(path(&["A", "C", "E", "D"])));
+        assert_eq!(cheapest_path(&graph, "A", "D", 50, |edge| Weight::Count.cost(edge)), Some(path(&["A", "C", "E", "D"])));
+
+        // The cheap route doesn't fit in three addresses, so the shortest one is taken instead
+        assert_eq!(cheapest_path(&graph, "A", "D", 3, |edge| Weight::Value.cost(edge)), bfs);
+        assert_eq!(cheapest_path(&graph, "A", "D", 2, |edge| Weight::Value.cost(edge)), None);
+        assert_eq!(cheapest_path(&graph, "A", "A", 1, |edge| Weight::Value.cost(edge)), Some(path(&["A"])));
+        assert_eq!("count".parse::<Weight>(), Ok(Weight::Count));
+    }
+
+    #[test]
+    fn neighbor_rings_group_nodes_by_shortest_hop_distance() {
+        // A - B - C - D, with a shortcut A - C and a separate E - F
+        let graph = undirected(&[("A", "B"), ("B", "C"), ("A", "C"), ("C", "D"), ("E", "F")]);
+
+        assert_eq!(neighbor_rings(&graph, "A", 5), vec![path(&["B", "C"]), path(&["D"])]);
+        assert_eq!(neighbor_rings(&graph, "A", 1), vec![path(&["B", "C"])]);
+        assert!(neighbor_rings(&graph, "Unknown", 3).is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+//! The backends solconnect reads history from: sources of raw transactions, such as a JSON-RPC
+//! node, and providers that return already-decoded transfers instead.
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::cache::TransactionCache;
+use crate::error::SolConnectError;
+use crate::rpc::HistoryOptions;
+
+/// A source of raw transactions in `getTransaction` JSON form, such as a JSON-RPC node.
+/// Everything that builds a graph from raw transactions goes through this trait, so another
+/// backend, or a canned one in tests, can stand in for [`RpcClient`](crate::RpcClient).
+#[async_trait(?Send)]
+pub trait TransactionSource {
+    /// Signatures of the most recent transactions involving `address`, newest first, calling
+    /// `on_page` with the running count whenever more have arrived.
+    async fn signatures_for_address(&self, address: &str, options: &HistoryOptions, on_page: &dyn Fn(usize)) -> Result<Vec<String>, SolConnectError>;
+
+    /// The transaction with `signature`, served from `cache` when the source uses one.
+    async fn transaction(&self, cache: Option<&TransactionCache>, signature: &str) -> Result<Value, SolConnectError>;
+
+    /// One result per signature, in the same order. Sources that can fetch several transactions
+    /// in one request should override this; by default they are fetched one at a time.
+    async fn transactions(&self, cache: Option<&TransactionCache>, signatures: &[String]) -> Vec<Result<Value, SolConnectError>> {
+        let mut results = Vec::with_capacity(signatures.len());
+        for signature in signatures {
+            results.push(self.transaction(cache, signature).await);
+        }
+        results
+    }
+}
+
+/// What moved in a [`Transfer`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransferKind {
+    Sol { lamports: u64 },
+    Token { mint: String, amount: f64 },
+    /// One token of a non-fungible mint
+    Nft { mint: String },
+}
+
+/// A single sender -> receiver movement of SOL or an SPL token within a transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub signature: String,
+    pub block_time: Option<i64>,
+    pub from: String,
+    pub to: String,
+    pub kind: TransferKind,
+}
+
+/// A source of decoded transfers for an address, such as the Helius enhanced-transactions API.
+#[async_trait(?Send)]
+pub trait TransferProvider {
+    /// Every transfer in the recent history of `address`, newest first.
+    async fn fetch_transfers(&self, address: &str) -> Result<Vec<Transfer>, SolConnectError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    /// Serves transactions from memory, keyed by signature.
+    struct MemorySource(HashMap<String, Value>);
+
+    #[async_trait(?Send)]
+    impl TransactionSource for MemorySource {
+        async fn signatures_for_address(&self, _: &str, _: &HistoryOptions, on_page: &dyn Fn(usize)) -> Result<Vec<String>, SolConnectError> {
+            let mut signatures: Vec<String> = self.0.keys().cloned().collect();
+            signatures.sort();
+            on_page(signatures.len());
+            Ok(signatures)
+        }
+
+        async fn transaction(&self, _: Option<&TransactionCache>, signature: &str) -> Result<Value, SolConnectError> {
+            self.0.get(signature).cloned().ok_or_else(|| SolConnectError::TransactionNotFound(signature.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn transactions_are_fetched_one_by_one_by_default() {
+        let source = MemorySource(HashMap::from([("sigA".to_string(), serde_json::json!({ "slot": 1 }))]));
+        let source: &dyn TransactionSource = &source;
+
+        let signatures = source.signatures_for_address("A", &HistoryOptions::default(), &|_| {}).await.unwrap();
+        assert_eq!(signatures, ["sigA"]);
+
+        let results = source.transactions(None, &["sigA".to_string(), "sigB".to_string()]).await;
+        assert_eq!(results[0].as_ref().unwrap()["slot"], 1);
+        assert!(matches!(results[1], Err(SolConnectError::TransactionNotFound(ref signature)) if signature == "sigB"));
+    }
+}
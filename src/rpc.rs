@@ -0,0 +1,1001 @@
+//! JSON-RPC access to a Solana node: a shared client with retry and backoff, and the
+//! signature-history and transaction-detail requests built on top of it.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use async_trait::async_trait;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde_json::Value;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, trace, warn};
+
+use crate::cache::TransactionCache;
+use crate::error::SolConnectError;
+use crate::is_valid_pubkey;
+use crate::metrics::METRICS;
+use crate::provider::TransactionSource;
+
+const INITIAL_BACKOFF_MS: u64 = 500;
+const MAX_BACKOFF_MS: u64 = 30_000;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_IN_FLIGHT: usize = 10;
+/// Successful responses in a row after which a rate-limited client sends one more request at once.
+const RATE_LIMIT_RECOVERY: usize = 20;
+/// HTTP 429 responses after which the user is told the endpoint is too limited for the crawl.
+const RATE_LIMIT_WARNING: usize = 5;
+
+/// How settled a block must be before the node includes it in a response.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    #[default]
+    Finalized,
+}
+
+impl Commitment {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+}
+
+impl FromStr for Commitment {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "processed" => Ok(Commitment::Processed),
+            "confirmed" => Ok(Commitment::Confirmed),
+            "finalized" => Ok(Commitment::Finalized),
+            other => Err(format!("unknown commitment: {}", other)),
+        }
+    }
+}
+
+/// How `getTransaction` results are encoded. `JsonParsed` has the node decode the instructions of
+/// the programs it knows, which lets the graph read transfers from them instead of from balances.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    JsonParsed,
+}
+
+impl Encoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            Encoding::JsonParsed => "jsonParsed",
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(Encoding::Json),
+            "json-parsed" | "jsonParsed" => Ok(Encoding::JsonParsed),
+            other => Err(format!("unknown encoding: {}", other)),
+        }
+    }
+}
+
+/// Carries a JSON-RPC request body to a node and returns the decoded response body.
+/// [`RpcClient`] adds retries, backoff and the in-flight limit on top, so an implementation
+/// only has to deliver a single attempt. Tests substitute one that replays recorded responses.
+#[async_trait]
+pub trait RpcTransport: Send + Sync {
+    async fn send(&self, body: &Value) -> Result<Value, SolConnectError>;
+}
+
+/// The proxy HTTP requests go through.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Proxy {
+    /// Whichever the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables name, if any
+    #[default]
+    Environment,
+    /// This proxy, for every request
+    Url(String),
+    /// None, even if the environment names one
+    Direct,
+}
+
+/// The [`RpcTransport`] that POSTs requests to an HTTP endpoint.
+pub struct HttpTransport {
+    http: reqwest::Client,
+    timeout: Duration,
+    proxy: Proxy,
+    endpoint: String,
+    auth: Option<(HeaderName, HeaderValue)>,
+}
+
+/// An HTTP client whose requests go through `proxy` and fail, retryably, when they take longer
+/// than `timeout` in total.
+pub(crate) fn http_client(timeout: Duration, proxy: &Proxy) -> Result<reqwest::Client, SolConnectError> {
+    let builder = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+        .timeout(timeout);
+    let builder = match proxy {
+        Proxy::Environment => builder,
+        Proxy::Url(url) => {
+            let proxy = reqwest::Proxy::all(url).map_err(|e| SolConnectError::Config(format!("invalid proxy URL {}: {}", url, e)))?;
+            builder.proxy(proxy)
+        }
+        Proxy::Direct => builder.no_proxy(),
+    };
+    Ok(builder.build()?)
+}
+
+impl HttpTransport {
+    pub fn new(endpoint: String) -> Result<Self, SolConnectError> {
+        let proxy = Proxy::default();
+        Ok(HttpTransport { http: http_client(DEFAULT_TIMEOUT, &proxy)?, timeout: DEFAULT_TIMEOUT, proxy, endpoint, auth: None })
+    }
+
+    /// Gives up on a request after `timeout` instead of the default 30 seconds. A timed-out
+    /// request counts as a transient failure and is retried.
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self, SolConnectError> {
+        self.http = http_client(timeout, &self.proxy)?;
+        self.timeout = timeout;
+        Ok(self)
+    }
+
+    /// Sends requests through `proxy` instead of the one the environment names.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Result<Self, SolConnectError> {
+        self.http = http_client(self.timeout, &proxy)?;
+        self.proxy = proxy;
+        Ok(self)
+    }
+
+    /// Sends `key` in the `header` request header, e.g. `x-api-key`. An `Authorization` header
+    /// gets the bearer scheme unless `key` already names a scheme.
+    pub fn with_auth_header(mut self, header: &str, key: &str) -> Result<Self, SolConnectError> {
+        let name = HeaderName::from_bytes(header.as_bytes())
+            .map_err(|e| SolConnectError::Config(format!("invalid auth header name {:?}: {}", header, e)))?;
+        let value = if name == AUTHORIZATION && !key.contains(' ') { format!("Bearer {}", key) } else { key.to_string() };
+        let mut value = HeaderValue::from_str(&value)
+            .map_err(|e| SolConnectError::Config(format!("invalid API key for header {}: {}", header, e)))?;
+        value.set_sensitive(true);
+        self.auth = Some((name, value));
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl RpcTransport for HttpTransport {
+    async fn send(&self, body: &Value) -> Result<Value, SolConnectError> {
+        let method = match body {
+            Value::Array(requests) => format!("batch of {}", requests.len()),
+            _ => body.get("method").and_then(|m| m.as_str()).unwrap_or("unknown").to_string(),
+        };
+        debug!("RPC {} -> {}", method, self.endpoint);
+        trace!("RPC request body: {}", body);
+
+        let payload = serde_json::to_vec(body)?;
+        let sent = payload.len();
+        let mut request = self.http.post(&self.endpoint).header(CONTENT_TYPE, "application/json").body(payload);
+        if let Some((ref name, ref value)) = self.auth {
+            request = request.header(name, value);
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                METRICS.record_request(sent, 0);
+                return Err(e.into());
+            }
+        };
+
+        let status = response.status();
+        debug!("RPC {} <- HTTP {}", method, status);
+        let bytes = response.bytes().await;
+        METRICS.record_request(sent, bytes.as_ref().map_or(0, |bytes| bytes.len()));
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            METRICS.record_rate_limited();
+        }
+        if !status.is_success() {
+            return Err(SolConnectError::Http(status));
+        }
+
+        let response = serde_json::from_slice::<Value>(&bytes?)?;
+        trace!("RPC response body: {}", response);
+        Ok(response)
+    }
+}
+
+/// Shared by every RPC call so connections and TLS sessions are pooled across the run.
+/// Its [`Throttle`] is the single budget for requests in flight, whichever phase sends them.
+///
+/// Requests go to the current transport. When one keeps failing after every retry, the client
+/// fails over to the next fallback, round-robin, and stays there for all later requests.
+pub struct RpcClient {
+    transports: Vec<Box<dyn RpcTransport>>,
+    current: AtomicUsize,
+    max_retries: u32,
+    commitment: Commitment,
+    encoding: Encoding,
+    in_flight: Throttle,
+}
+
+/// The in-flight budget of an [`RpcClient`], which adapts to an endpoint that rate-limits. Every
+/// HTTP 429 response takes one request off the limit, down to a single request at a time, and
+/// every [`RATE_LIMIT_RECOVERY`] successful responses in a row give one back, up to the limit the
+/// client was configured with.
+struct Throttle {
+    permits: Semaphore,
+    max: usize,
+    /// Requests currently allowed at once: the permits available plus those on the wire
+    limit: AtomicUsize,
+    successes: AtomicUsize,
+    rate_limited: AtomicUsize,
+}
+
+impl Throttle {
+    fn new(max: usize) -> Self {
+        Throttle {
+            permits: Semaphore::new(max),
+            max,
+            limit: AtomicUsize::new(max),
+            successes: AtomicUsize::new(0),
+            rate_limited: AtomicUsize::new(0),
+        }
+    }
+
+    async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.permits.acquire().await.expect("semaphore is never closed")
+    }
+
+    /// Adjusts the limit to the outcome of a request that was sent under `permit`, and releases
+    /// the permit, or keeps it out of circulation when the limit goes down.
+    fn record(&self, result: &Result<Value, SolConnectError>, permit: SemaphorePermit<'_>) {
+        match result {
+            Err(error) if error.is_rate_limited() => {
+                self.successes.store(0, Ordering::Relaxed);
+                if let Ok(limit) = self.limit.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |limit| (limit > 1).then(|| limit - 1)) {
+                    permit.forget();
+                    debug!("Rate-limited by the RPC endpoint; sending at most {} request(s) at once", limit - 1);
+                }
+                if self.rate_limited.fetch_add(1, Ordering::Relaxed) + 1 == RATE_LIMIT_WARNING {
+                    warn!(
+                        "The RPC endpoint keeps rate-limiting requests (HTTP 429), so they are now sent at most {} at a time. Public \
+                         endpoints such as api.mainnet-beta.solana.com only allow a few requests per second; for crawls of any size, \
+                         use a dedicated RPC provider such as Helius or QuickNode with --rpc-url or SOLANA_RPC_ENDPOINT",
+                        self.current_limit()
+                    );
+                }
+            }
+            Ok(_) => {
+                if self.successes.fetch_add(1, Ordering::Relaxed) + 1 >= RATE_LIMIT_RECOVERY {
+                    self.successes.store(0, Ordering::Relaxed);
+                    if self.limit.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |limit| (limit < self.max).then(|| limit + 1)).is_ok() {
+                        self.permits.add_permits(1);
+                    }
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn current_limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+}
+
+impl RpcClient {
+    /// A client for the HTTP endpoint with default settings. Use [`RpcClient::with_transport`]
+    /// to configure the [`HttpTransport`] or to send requests some other way.
+    pub fn new(endpoint: String, max_retries: u32) -> Result<Self, SolConnectError> {
+        Ok(RpcClient::with_transport(HttpTransport::new(endpoint)?, max_retries))
+    }
+
+    pub fn with_transport(transport: impl RpcTransport + 'static, max_retries: u32) -> Self {
+        RpcClient {
+            transports: vec![Box::new(transport)],
+            current: AtomicUsize::new(0),
+            max_retries,
+            commitment: Commitment::default(),
+            encoding: Encoding::default(),
+            in_flight: Throttle::new(DEFAULT_MAX_IN_FLIGHT),
+        }
+    }
+
+    /// Adds a transport to fail over to when the ones before it keep failing.
+    pub fn with_fallback(mut self, transport: impl RpcTransport + 'static) -> Self {
+        self.transports.push(Box::new(transport));
+        self
+    }
+
+    /// Allows at most `max_in_flight` requests at once across all callers (default 10). An
+    /// endpoint that answers with HTTP 429 gets fewer for a while.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.in_flight = Throttle::new(max_in_flight.max(1));
+        self
+    }
+
+    /// How many requests may currently be in flight at once, which is below the configured limit
+    /// while the endpoint is rate-limiting.
+    pub fn current_max_in_flight(&self) -> usize {
+        self.in_flight.current_limit()
+    }
+
+    /// Requests data at `commitment` instead of the default, `finalized`.
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    /// Requests transactions in `encoding` instead of the default, `json`.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// `getTransaction` rejects `processed`, so the closest level it accepts is used instead.
+    fn transaction_commitment(&self) -> &'static str {
+        match self.commitment {
+            Commitment::Processed => Commitment::Confirmed.as_str(),
+            commitment => commitment.as_str(),
+        }
+    }
+}
+
+/// Exponential backoff with "equal jitter": half the delay is fixed, the other half random.
+pub(crate) fn backoff_delay(attempt: u32) -> Duration {
+    let delay = INITIAL_BACKOFF_MS.saturating_mul(1 << attempt.min(16)).min(MAX_BACKOFF_MS);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos() as u64).unwrap_or(0);
+    Duration::from_millis(delay / 2 + nanos % (delay / 2 + 1))
+}
+
+/// Sends a single request. JSON-RPC error objects are only turned into errors when they are retryable.
+async fn attempt_rpc_request(rpc: &RpcClient, transport: usize, body: &Value) -> Result<Value, SolConnectError> {
+    let response = rpc.transports[transport].send(body).await?;
+    if let Some(error) = response.get("error").map(rpc_error).filter(SolConnectError::is_retryable) {
+        return Err(error);
+    }
+
+    Ok(response)
+}
+
+/// Converts a JSON-RPC error object into [`SolConnectError::Rpc`].
+pub(crate) fn rpc_error(error: &Value) -> SolConnectError {
+    SolConnectError::Rpc {
+        code: error.get("code").and_then(|c| c.as_i64()).unwrap_or_default(),
+        message: error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error").to_string(),
+    }
+}
+
+/// Sends a JSON-RPC request, retrying transient failures with exponential backoff.
+/// Non-retryable JSON-RPC error objects are returned as part of the response for the caller to inspect.
+///
+/// Every attempt, including each retry, holds a permit of the client's throttle while it is
+/// on the wire. The permit is released during the backoff sleep, so a request waiting to retry
+/// doesn't hold back others, and a retry queues for a permit like any new request.
+///
+/// Once the retries are used up on one transport, the request moves on to the next one with a
+/// fresh set of retries, until every transport has been tried.
+async fn send_rpc_request(rpc: &RpcClient, body: &Value) -> Result<Value, SolConnectError> {
+    let count = rpc.transports.len();
+    let mut transport = rpc.current.load(Ordering::Relaxed);
+    let mut tried = 1;
+    let mut attempt = 0;
+    loop {
+        let result = {
+            let permit = rpc.in_flight.acquire().await;
+            let result = attempt_rpc_request(rpc, transport, body).await;
+            rpc.in_flight.record(&result, permit);
+            result
+        };
+        match result {
+            Ok(response) => return Ok(response),
+            Err(error) if error.is_retryable() && attempt < rpc.max_retries => {
+                let delay = backoff_delay(attempt);
+                warn!("RPC request failed ({}), retrying in {:.1}s", error, delay.as_secs_f64());
+                METRICS.record_retry();
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(error) if error.is_retryable() && tried < count => {
+                let next = (transport + 1) % count;
+                // Concurrent requests fail together; only the first one to move on announces it
+                if rpc.current.compare_exchange(transport, next, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+                    warn!("RPC endpoint {} of {} keeps failing ({}); switching to endpoint {}", transport + 1, count, error, next + 1);
+                }
+                transport = next;
+                tried += 1;
+                attempt = 0;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Calls `method` and returns its `result`, turning a JSON-RPC error object into an error.
+pub(crate) async fn rpc_call(rpc: &RpcClient, method: &str, params: Value) -> Result<Value, SolConnectError> {
+    let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response = send_rpc_request(rpc, &body).await?;
+    if let Some(error) = response.get("error") {
+        return Err(rpc_error(error));
+    }
+    response.get("result").cloned().ok_or_else(|| SolConnectError::Parse(format!("{} response has no result: {}", method, response)))
+}
+
+/// The version of the Solana software the node runs, such as `1.18.22`, from `getVersion`.
+pub async fn get_node_version(rpc: &RpcClient) -> Result<String, SolConnectError> {
+    let result = rpc_call(rpc, "getVersion", serde_json::json!([])).await?;
+    result.get("solana-core").and_then(|version| version.as_str()).map(str::to_string)
+        .ok_or_else(|| SolConnectError::Parse(format!("getVersion response has no solana-core version: {}", result)))
+}
+
+/// Asks the node whether it is healthy with `getHealth`. An unhealthy node answers with a
+/// JSON-RPC error, typically that it is behind the cluster by some number of slots.
+pub async fn get_health(rpc: &RpcClient) -> Result<(), SolConnectError> {
+    rpc_call(rpc, "getHealth", serde_json::json!([])).await.map(|_| ())
+}
+
+/// Controls how far back `get_transaction_history` paginates.
+#[derive(Clone, Debug)]
+pub struct HistoryOptions {
+    /// Stop after this many signatures; 0 paginates until the node runs out of history
+    pub max_signatures: usize,
+    /// Only return signatures newer than this one, e.g. the newest seen by a previous run
+    pub until: Option<String>,
+    /// Inclusive slot bounds; signatures outside them are left out, and the node must have
+    /// reached `max_slot` (or `min_slot`) to answer at all
+    pub min_slot: Option<u64>,
+    pub max_slot: Option<u64>,
+    /// Signatures requested per page, at most [`MAX_SIGNATURE_PAGE`]
+    pub page_limit: usize,
+}
+
+/// The most signatures `getSignaturesForAddress` returns in one page.
+pub const MAX_SIGNATURE_PAGE: usize = 1000;
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        HistoryOptions { max_signatures: 10_000, until: None, min_slot: None, max_slot: None, page_limit: MAX_SIGNATURE_PAGE }
+    }
+}
+
+/// Fetches the signatures of the most recent transactions involving `address`, newest first.
+/// With slot bounds, newer signatures are skipped and paging stops at the first older one.
+pub async fn get_transaction_history(rpc: &RpcClient, address: &str, options: &HistoryOptions) -> Result<Vec<String>, SolConnectError> {
+    get_transaction_history_with_progress(rpc, address, options, &|_| {}).await
+}
+
+/// Like [`get_transaction_history`], calling `on_page` with the running signature count after each page.
+pub async fn get_transaction_history_with_progress(
+    rpc: &RpcClient,
+    address: &str,
+    options: &HistoryOptions,
+    on_page: &dyn Fn(usize),
+) -> Result<Vec<String>, SolConnectError> {
+    if !is_valid_pubkey(address) {
+        return Err(SolConnectError::InvalidAddress(address.to_string()));
+    }
+
+    let page_limit = options.page_limit.clamp(1, MAX_SIGNATURE_PAGE);
+    let mut signatures = Vec::new();
+    let mut before: Option<String> = None;
+
+    loop {
+        let remaining = match options.max_signatures {
+            0 => page_limit,
+            max => max - signatures.len(),
+        };
+        let limit = remaining.min(page_limit);
+        let mut params = serde_json::json!([address, { "limit": limit, "commitment": rpc.commitment.as_str() }]);
+        if let Some(ref before_signature) = before {
+            params[1]["before"] = serde_json::Value::String(before_signature.clone());
+        }
+        if let Some(ref until) = options.until {
+            params[1]["until"] = serde_json::Value::String(until.clone());
+        }
+        if let Some(slot) = options.max_slot.or(options.min_slot) {
+            params[1]["minContextSlot"] = serde_json::Value::from(slot);
+        }
+
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getSignaturesForAddress",
+            "params": params
+        });
+
+        let response = send_rpc_request(rpc, &body).await?;
+        if let Some(error) = response.get("error") {
+            return Err(rpc_error(error));
+        }
+
+        let page = match response.get("result").and_then(|r| r.as_array()) {
+            Some(page) if !page.is_empty() => page,
+            _ => break,
+        };
+
+        let mut before_min_slot = false;
+        for tx in page {
+            let slot = tx.get("slot").and_then(|s| s.as_u64());
+            if slot.is_some_and(|slot| options.min_slot.is_some_and(|min| slot < min)) {
+                before_min_slot = true;
+                break;
+            }
+            if slot.is_some_and(|slot| options.max_slot.is_some_and(|max| slot > max)) {
+                continue;
+            }
+            if let Some(sig) = tx.get("signature").and_then(|s| s.as_str()) {
+                signatures.push(sig.to_string());
+            }
+        }
+
+        on_page(signatures.len());
+        // A short page is the end of the history, so stop without asking for one more page.
+        // Everything after a signature older than the slot range is older still.
+        if page.len() < limit || before_min_slot {
+            break;
+        }
+        before = page.last().and_then(|tx| tx.get("signature").and_then(|sig| sig.as_str()).map(String::from));
+        if before.is_none() {
+            return Err(SolConnectError::Parse(format!("getSignaturesForAddress page for {} ends without a signature", address)));
+        }
+
+        // A full page at the cap means there is probably older history that won't be seen
+        if options.max_signatures != 0 && signatures.len() >= options.max_signatures {
+            if page.len() == limit {
+                warn!(
+                    "Stopped after {} signatures for {}; older transactions were not fetched and may hide connections",
+                    signatures.len(), address
+                );
+            }
+            break;
+        }
+    }
+
+    Ok(signatures)
+}
+
+/// Fetches a transaction with `getTransaction`, serving it from `cache` when possible.
+pub async fn get_transaction_details(rpc: &RpcClient, cache: Option<&TransactionCache>, signature: &str) -> Result<Value, SolConnectError> {
+    if let Some(cache) = cache {
+        let cached = cache.get(signature);
+        METRICS.record_cache_lookup(cached.is_some());
+        if let Some(transaction) = cached {
+            return Ok(transaction);
+        }
+    }
+
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getTransaction",
+        "params": [
+            signature,
+            {
+                "encoding": rpc.encoding.as_str(),
+                "commitment": rpc.transaction_commitment(),
+                "maxSupportedTransactionVersion": 0
+            }
+        ]
+    });
+
+    let response = send_rpc_request(rpc, &body).await?;
+    let transaction = parse_transaction_response(&response, signature)?;
+    store_in_cache(rpc, cache, signature, &transaction);
+    Ok(transaction)
+}
+
+/// Fetches many transactions with one JSON-RPC batch request, returning a result per signature
+/// in the same order. Cached transactions are served locally and left out of the batch.
+pub async fn get_transaction_details_batch(rpc: &RpcClient, cache: Option<&TransactionCache>, signatures: &[String]) -> Vec<Result<Value, SolConnectError>> {
+    let mut results: Vec<Option<Result<Value, SolConnectError>>> = signatures.iter()
+        .map(|signature| cache.and_then(|c| c.get(signature)).map(Ok))
+        .collect();
+    let missing: Vec<usize> = (0..signatures.len()).filter(|i| results[*i].is_none()).collect();
+    if cache.is_some() {
+        results.iter().for_each(|result| METRICS.record_cache_lookup(result.is_some()));
+    }
+
+    // A single request goes out on its own, since some providers reject batch bodies entirely.
+    // The cache was already consulted above, so only the store is left to do here.
+    if missing.len() == 1 {
+        let i = missing[0];
+        let transaction = get_transaction_details(rpc, None, &signatures[i]).await;
+        if let Ok(ref transaction) = transaction {
+            store_in_cache(rpc, cache, &signatures[i], transaction);
+        }
+        results[i] = Some(transaction);
+    } else if !missing.is_empty() {
+        let body: Vec<Value> = missing.iter().map(|i| serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": i,
+            "method": "getTransaction",
+            "params": [
+                signatures[*i],
+                {
+                    "encoding": rpc.encoding.as_str(),
+                    "commitment": rpc.transaction_commitment(),
+                    "maxSupportedTransactionVersion": 0
+                }
+            ]
+        })).collect();
+
+        match send_rpc_request(rpc, &Value::Array(body)).await {
+            Ok(Value::Array(responses)) => {
+                for response in &responses {
+                    let index = response.get("id").and_then(|id| id.as_u64()).map(|id| id as usize);
+                    if let Some(i) = index.filter(|i| *i < signatures.len() && results[*i].is_none()) {
+                        let transaction = parse_transaction_response(response, &signatures[i]);
+                        if let Ok(ref transaction) = transaction {
+                            store_in_cache(rpc, cache, &signatures[i], transaction);
+                        }
+                        results[i] = Some(transaction);
+                    }
+                }
+            }
+            Ok(response) => {
+                // A provider that rejects the whole batch answers with a single error object
+                let error = match response.get("error") {
+                    Some(error) => rpc_error(error),
+                    None => SolConnectError::Parse(format!("unexpected batch response: {}", response)),
+                };
+                for i in &missing {
+                    results[*i] = Some(Err(error.clone()));
+                }
+            }
+            Err(error) => {
+                for i in &missing {
+                    results[*i] = Some(Err(error.clone()));
+                }
+            }
+        }
+    }
+
+    results.into_iter().enumerate()
+        .map(|(i, result)| result.unwrap_or_else(|| Err(SolConnectError::Parse(format!("no response for transaction {}", signatures[i])))))
+        .collect()
+}
+
+/// Extracts the transaction from a single `getTransaction` response object.
+fn parse_transaction_response(response: &Value, signature: &str) -> Result<Value, SolConnectError> {
+    if let Some(error) = response.get("error") {
+        return Err(rpc_error(error));
+    }
+
+    match response.get("result") {
+        Some(result) if !result.is_null() => Ok(result.clone()),
+        _ => Err(SolConnectError::TransactionNotFound(signature.to_string())),
+    }
+}
+
+/// The JSON-RPC implementation of [`TransactionSource`], fetching several transactions with one batch request.
+#[async_trait(?Send)]
+impl TransactionSource for RpcClient {
+    async fn signatures_for_address(&self, address: &str, options: &HistoryOptions, on_page: &dyn Fn(usize)) -> Result<Vec<String>, SolConnectError> {
+        get_transaction_history_with_progress(self, address, options, on_page).await
+    }
+
+    async fn transaction(&self, cache: Option<&TransactionCache>, signature: &str) -> Result<Value, SolConnectError> {
+        get_transaction_details(self, cache, signature).await
+    }
+
+    async fn transactions(&self, cache: Option<&TransactionCache>, signatures: &[String]) -> Vec<Result<Value, SolConnectError>> {
+        get_transaction_details_batch(self, cache, signatures).await
+    }
+}
+
+/// Only finalized transactions are cached, since anything less settled may still be dropped.
+fn store_in_cache(rpc: &RpcClient, cache: Option<&TransactionCache>, signature: &str, transaction: &Value) {
+    if rpc.commitment != Commitment::Finalized {
+        return;
+    }
+    if let Some(cache) = cache {
+        if let Err(e) = cache.put(signature, transaction) {
+            warn!("Failed to cache transaction {}: {}", signature, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use super::*;
+
+    /// Replays canned responses in order and records every request it was given.
+    struct MockTransport {
+        responses: Mutex<VecDeque<Value>>,
+        requests: Mutex<Vec<Value>>,
+    }
+
+    impl MockTransport {
+        fn new(responses: &[&str]) -> Self {
+            let responses = responses.iter().map(|r| serde_json::from_str(r).expect("fixture is valid JSON")).collect();
+            MockTransport { responses: Mutex::new(responses), requests: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl RpcTransport for Arc<MockTransport> {
+        async fn send(&self, body: &Value) -> Result<Value, SolConnectError> {
+            self.requests.lock().unwrap().push(body.clone());
+            self.responses.lock().unwrap().pop_front()
+                .ok_or_else(|| SolConnectError::Parse(format!("unexpected request: {}", body)))
+        }
+    }
+
+    // The mock is shared with the client so the test can inspect its requests afterwards
+    fn mock_client(responses: &[&str], max_retries: u32) -> (Arc<MockTransport>, RpcClient) {
+        let mock = Arc::new(MockTransport::new(responses));
+        (mock.clone(), RpcClient::with_transport(mock, max_retries))
+    }
+
+    const ADDRESS: &str = "11111111111111111111111111111111";
+
+    #[tokio::test]
+    async fn rate_limiting_lowers_the_in_flight_limit_until_requests_succeed_again() {
+        let throttle = Throttle::new(3);
+        let rate_limited = Err(SolConnectError::Http(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        for _ in 0..4 {
+            throttle.record(&rate_limited, throttle.acquire().await);
+        }
+        // Never below one request at a time, and the permits follow the limit
+        assert_eq!(throttle.current_limit(), 1);
+        assert_eq!(throttle.permits.available_permits(), 1);
+
+        throttle.record(&Err(SolConnectError::Http(reqwest::StatusCode::BAD_GATEWAY)), throttle.acquire().await);
+        assert_eq!(throttle.current_limit(), 1);
+
+        for _ in 0..RATE_LIMIT_RECOVERY * 5 {
+            throttle.record(&Ok(Value::Null), throttle.acquire().await);
+        }
+        assert_eq!(throttle.current_limit(), 3);
+        assert_eq!(throttle.permits.available_permits(), 3);
+    }
+
+    #[tokio::test]
+    async fn history_pages_backwards_with_the_before_cursor() {
+        let (mock, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/signatures_page1.json"),
+            include_str!("../tests/fixtures/rpc/signatures_page2.json"),
+        ], 0);
+
+        // The second page is short, which ends the history without a request for a third
+        let options = HistoryOptions { max_signatures: 0, page_limit: 2, ..HistoryOptions::default() };
+        let signatures = get_transaction_history(&rpc, ADDRESS, &options).await.unwrap();
+        assert_eq!(signatures, ["sigC", "sigB", "sigA"]);
+
+        let requests = mock.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]["params"][1].get("before").is_none());
+        assert_eq!(requests[1]["params"][1]["before"], "sigB");
+    }
+
+    #[tokio::test]
+    async fn a_short_first_page_is_the_whole_history() {
+        let (mock, rpc) = mock_client(&[include_str!("../tests/fixtures/rpc/signatures_page1.json")], 0);
+
+        let options = HistoryOptions { max_signatures: 0, ..HistoryOptions::default() };
+        let signatures = get_transaction_history(&rpc, ADDRESS, &options).await.unwrap();
+        assert_eq!(signatures, ["sigC", "sigB"]);
+        assert_eq!(mock.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn an_empty_page_after_a_full_one_ends_the_history() {
+        let (mock, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/signatures_page1.json"),
+            include_str!("../tests/fixtures/rpc/signatures_empty.json"),
+        ], 0);
+
+        let options = HistoryOptions { max_signatures: 0, page_limit: 2, ..HistoryOptions::default() };
+        let signatures = get_transaction_history(&rpc, ADDRESS, &options).await.unwrap();
+        assert_eq!(signatures, ["sigC", "sigB"]);
+        assert_eq!(mock.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn history_stops_at_max_signatures() {
+        let (mock, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/signatures_page1.json"),
+            include_str!("../tests/fixtures/rpc/signatures_page2.json"),
+        ], 0);
+
+        let options = HistoryOptions { max_signatures: 3, until: Some("sigOld".to_string()), page_limit: 2, ..HistoryOptions::default() };
+        let signatures = get_transaction_history(&rpc, ADDRESS, &options).await.unwrap();
+        assert_eq!(signatures.len(), 3);
+
+        let requests = mock.requests.lock().unwrap();
+        assert_eq!(requests[0]["params"][1]["limit"], 2);
+        assert_eq!(requests[1]["params"][1]["limit"], 1);
+        assert_eq!(requests[1]["params"][1]["until"], "sigOld");
+    }
+
+    #[tokio::test]
+    async fn history_keeps_only_the_signatures_within_the_slot_range() {
+        let (mock, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/signatures_page1.json"),
+            include_str!("../tests/fixtures/rpc/signatures_page2.json"),
+            include_str!("../tests/fixtures/rpc/signatures_page1.json"),
+        ], 0);
+
+        let options = HistoryOptions { max_signatures: 0, min_slot: Some(250000001), max_slot: Some(250000002), page_limit: 2, ..HistoryOptions::default() };
+        let signatures = get_transaction_history(&rpc, ADDRESS, &options).await.unwrap();
+        assert_eq!(signatures, ["sigB", "sigA"]);
+
+        // sigB is older than the range, so the rest of the history isn't asked for
+        let options = HistoryOptions { max_signatures: 0, min_slot: Some(250000003), page_limit: 2, ..HistoryOptions::default() };
+        let signatures = get_transaction_history(&rpc, ADDRESS, &options).await.unwrap();
+        assert_eq!(signatures, ["sigC"]);
+
+        let requests = mock.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0]["params"][1]["minContextSlot"], 250000002);
+        assert_eq!(requests[2]["params"][1]["minContextSlot"], 250000003);
+    }
+
+    #[tokio::test]
+    async fn node_version_and_health_are_read_from_their_results() {
+        let (mock, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/version.json"),
+            include_str!("../tests/fixtures/rpc/health_ok.json"),
+            include_str!("../tests/fixtures/rpc/error_node_behind.json"),
+        ], 0);
+
+        assert_eq!(get_node_version(&rpc).await.unwrap(), "1.18.22");
+        get_health(&rpc).await.unwrap();
+        let error = get_health(&rpc).await.unwrap_err();
+        assert!(matches!(error, SolConnectError::Rpc { code: -32005, .. }));
+
+        let requests = mock.requests.lock().unwrap();
+        assert_eq!(requests[0]["method"], "getVersion");
+        assert_eq!(requests[1]["method"], "getHealth");
+    }
+
+    #[tokio::test]
+    async fn history_rejects_invalid_addresses_without_a_request() {
+        let (mock, rpc) = mock_client(&[], 0);
+        let error = get_transaction_history(&rpc, "not-an-address", &HistoryOptions::default()).await.unwrap_err();
+        assert!(matches!(error, SolConnectError::InvalidAddress(_)));
+        assert!(mock.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn transaction_details_are_parsed_from_the_result() {
+        let (mock, rpc) = mock_client(&[include_str!("../tests/fixtures/rpc/transaction.json")], 0);
+        let transaction = get_transaction_details(&rpc, None, "sigA").await.unwrap();
+        assert_eq!(transaction["blockTime"], 1_700_000_000);
+
+        {
+            let requests = mock.requests.lock().unwrap();
+            assert_eq!(requests[0]["method"], "getTransaction");
+            assert_eq!(requests[0]["params"][0], "sigA");
+            assert_eq!(requests[0]["params"][1]["maxSupportedTransactionVersion"], 0);
+            assert_eq!(requests[0]["params"][1]["encoding"], "json");
+        }
+
+        let (mock, rpc) = mock_client(&[include_str!("../tests/fixtures/rpc/transaction.json")], 0);
+        let rpc = rpc.with_encoding(Encoding::JsonParsed);
+        get_transaction_details(&rpc, None, "sigA").await.unwrap();
+        assert_eq!(mock.requests.lock().unwrap()[0]["params"][1]["encoding"], "jsonParsed");
+    }
+
+    #[tokio::test]
+    async fn missing_transactions_and_rpc_errors_are_reported() {
+        let (_, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/transaction_null.json"),
+            include_str!("../tests/fixtures/rpc/error_invalid_params.json"),
+        ], 3);
+
+        let error = get_transaction_details(&rpc, None, "sigMissing").await.unwrap_err();
+        assert!(matches!(error, SolConnectError::TransactionNotFound(ref signature) if signature == "sigMissing"));
+
+        // Not retryable, so the single remaining response is enough even with retries allowed
+        let error = get_transaction_details(&rpc, None, "sigBad").await.unwrap_err();
+        assert!(matches!(error, SolConnectError::Rpc { code: -32602, .. }));
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_are_surfaced_by_history_and_batch_requests() {
+        let (_, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/error_invalid_params.json"),
+            include_str!("../tests/fixtures/rpc/error_invalid_params.json"),
+        ], 0);
+
+        // An error page used to end the history silently, as if there were no more signatures
+        let error = get_transaction_history(&rpc, "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU", &HistoryOptions::default()).await.unwrap_err();
+        assert!(matches!(error, SolConnectError::Rpc { code: -32602, ref message } if message == "Invalid param: Invalid signature"));
+
+        let signatures = ["sigA".to_string(), "sigB".to_string()];
+        let results = get_transaction_details_batch(&rpc, None, &signatures).await;
+        assert!(results.iter().all(|result| matches!(result, Err(SolConnectError::Rpc { code: -32602, .. }))));
+    }
+
+    #[tokio::test]
+    async fn retryable_rpc_errors_are_retried() {
+        let (mock, rpc) = mock_client(&[
+            include_str!("../tests/fixtures/rpc/error_node_behind.json"),
+            include_str!("../tests/fixtures/rpc/transaction.json"),
+        ], 1);
+
+        assert!(get_transaction_details(&rpc, None, "sigA").await.is_ok());
+        assert_eq!(mock.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn failing_endpoints_fail_over_to_the_next_one() {
+        let failing = Arc::new(MockTransport::new(&[include_str!("../tests/fixtures/rpc/error_node_behind.json")]));
+        let healthy = Arc::new(MockTransport::new(&[
+            include_str!("../tests/fixtures/rpc/transaction.json"),
+            include_str!("../tests/fixtures/rpc/transaction.json"),
+        ]));
+        let rpc = RpcClient::with_transport(failing.clone(), 0).with_fallback(healthy.clone());
+
+        assert!(get_transaction_details(&rpc, None, "sigA").await.is_ok());
+        assert!(get_transaction_details(&rpc, None, "sigA").await.is_ok());
+        assert_eq!(failing.requests.lock().unwrap().len(), 1);
+        assert_eq!(healthy.requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn auth_header_defaults_authorization_to_bearer() {
+        let client = || HttpTransport::new("http://localhost:8899".to_string()).unwrap();
+
+        let rpc = client().with_auth_header("Authorization", "secret").unwrap();
+        assert_eq!(rpc.auth.as_ref().map(|(_, value)| value.to_str().unwrap()), Some("Bearer secret"));
+
+        let rpc = client().with_auth_header("x-api-key", "secret").unwrap();
+        assert_eq!(rpc.auth.as_ref().map(|(name, value)| (name.as_str(), value.to_str().unwrap())), Some(("x-api-key", "secret")));
+
+        assert!(matches!(client().with_auth_header("bad header", "secret"), Err(SolConnectError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn timed_out_requests_are_retryable() {
+        // Accepts connections but never answers them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+
+        let transport = HttpTransport::new(endpoint).unwrap().with_timeout(Duration::from_millis(100)).unwrap();
+        let rpc = RpcClient::with_transport(transport, 0);
+        let error = send_rpc_request(&rpc, &serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "getHealth" })).await.unwrap_err();
+        assert!(matches!(error, SolConnectError::Network(ref e) if e.is_timeout()));
+        assert!(error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_the_configured_proxy() {
+        // Answers every request it is sent, reporting the request line it saw
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut connection, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let read = connection.read(&mut request).await.unwrap_or_default();
+                let line = String::from_utf8_lossy(&request[..read]).lines().next().unwrap_or_default().to_string();
+                let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": line }).to_string();
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+                let _ = connection.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let transport = HttpTransport::new("http://rpc.solconnect.invalid".to_string()).unwrap().with_proxy(Proxy::Url(proxy)).unwrap();
+        let rpc = RpcClient::with_transport(transport, 0);
+        let response = send_rpc_request(&rpc, &serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "getHealth" })).await.unwrap();
+        assert_eq!(response["result"], "POST http://rpc.solconnect.invalid/ HTTP/1.1");
+
+        let invalid = HttpTransport::new("http://127.0.0.1:8899".to_string()).unwrap().with_proxy(Proxy::Url("not a url".to_string()));
+        assert!(matches!(invalid, Err(SolConnectError::Config(_))));
+    }
+}
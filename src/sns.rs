@@ -0,0 +1,138 @@
+//! Resolution between wallets and their Solana Name Service (`.sol`) domains.
+//!
+//! A `.sol` domain is a name-registry account whose parent is the `.sol` root and whose owner is
+//! the wallet; its address is derived from the hashed name. The readable name is stored separately,
+//! in a reverse-lookup account derived from the domain account's address.
+
+use base64::Engine;
+use serde_json::Value;
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::SolConnectError;
+use crate::rpc::{rpc_call, RpcClient};
+
+const NAME_PROGRAM_ID: &str = "namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX";
+const ROOT_DOMAIN_ACCOUNT: &str = "58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JPkx";
+const REVERSE_LOOKUP_CLASS: &str = "33m47vH6Eav6jr5Ry86XjhRft2jRBLDnDgPSHoquXi2Z";
+const HASH_PREFIX: &str = "SPL Name Service";
+/// Every name-registry account starts with its parent, owner and class.
+const REGISTRY_HEADER_LEN: usize = 96;
+
+fn pubkey(address: &str) -> Pubkey {
+    address.parse().expect("constant is a valid pubkey")
+}
+
+/// The address of the name-registry account for `name` under `class` and `parent`.
+fn name_account_key(name: &str, class: &Pubkey, parent: &Pubkey) -> Pubkey {
+    let hashed_name = hashv(&[HASH_PREFIX.as_bytes(), name.as_bytes()]);
+    Pubkey::find_program_address(&[hashed_name.as_ref(), class.as_ref(), parent.as_ref()], &pubkey(NAME_PROGRAM_ID)).0
+}
+
+/// The reverse-lookup account holding the name of the domain account `domain`.
+fn reverse_lookup_key(domain: &Pubkey) -> Pubkey {
+    name_account_key(&domain.to_string(), &pubkey(REVERSE_LOOKUP_CLASS), &Pubkey::default())
+}
+
+/// The domain account of `domain`, such as `bonfida.sol` or the subdomain `dex.bonfida.sol`.
+/// Subdomain names are hashed with a leading NUL byte under their parent domain's account.
+fn domain_key(domain: &str) -> Pubkey {
+    let mut key = pubkey(ROOT_DOMAIN_ACCOUNT);
+    let labels = domain.trim_end_matches(".sol").split('.').rev();
+    for (depth, label) in labels.enumerate() {
+        let name = if depth == 0 { label.to_string() } else { format!("\0{}", label) };
+        key = name_account_key(&name, &Pubkey::default(), &key);
+    }
+    key
+}
+
+/// Whether `input` looks like a `.sol` domain rather than an address.
+pub fn is_sol_domain(input: &str) -> bool {
+    input.strip_suffix(".sol").is_some_and(|name| {
+        !name.is_empty() && name.split('.').all(|label| !label.is_empty() && !label.contains(char::is_whitespace))
+    })
+}
+
+/// The domain name stored in a reverse-lookup account: a length-prefixed string after the registry header.
+fn parse_reverse_lookup(data: &[u8]) -> Option<String> {
+    let body = data.get(REGISTRY_HEADER_LEN..)?;
+    let len = u32::from_le_bytes(body.get(..4)?.try_into().ok()?) as usize;
+    let name = std::str::from_utf8(body.get(4..4 + len)?).ok()?;
+    Some(name.to_string())
+}
+
+fn account_data(account: &Value) -> Option<Vec<u8>> {
+    let encoded = account.get("data")?.get(0)?.as_str()?;
+    base64::engine::general_purpose::STANDARD.decode(encoded).ok()
+}
+
+/// The wallet that owns `domain`, such as `bonfida.sol`.
+pub async fn resolve_domain(rpc: &RpcClient, domain: &str) -> Result<String, SolConnectError> {
+    let params = serde_json::json!([domain_key(&domain.to_lowercase()).to_string(), { "encoding": "base64" }]);
+    let info = rpc_call(rpc, "getAccountInfo", params).await?;
+    let data = info.get("value").and_then(account_data).ok_or_else(|| SolConnectError::DomainNotFound(domain.to_string()))?;
+    let owner = data.get(32..64).and_then(|owner| Pubkey::try_from(owner).ok())
+        .ok_or_else(|| SolConnectError::Parse(format!("name registry account of {} is too short", domain)))?;
+    Ok(owner.to_string())
+}
+
+/// One `.sol` domain owned by `owner`, such as `bonfida.sol`, or `None` when it owns none.
+/// A wallet owning several domains gets the one whose account address sorts first, so the
+/// answer is stable between runs.
+pub async fn primary_domain(rpc: &RpcClient, owner: &str) -> Result<Option<String>, SolConnectError> {
+    let owner: Pubkey = owner.parse().map_err(|_| SolConnectError::InvalidAddress(owner.to_string()))?;
+
+    let params = serde_json::json!([NAME_PROGRAM_ID, {
+        "encoding": "base64",
+        "dataSlice": { "offset": 0, "length": 0 },
+        "filters": [
+            { "memcmp": { "offset": 0, "bytes": ROOT_DOMAIN_ACCOUNT } },
+            { "memcmp": { "offset": 32, "bytes": owner.to_string() } }
+        ]
+    }]);
+    let accounts = rpc_call(rpc, "getProgramAccounts", params).await?;
+    let mut domains: Vec<Pubkey> = accounts.as_array().into_iter().flatten()
+        .filter_map(|account| account.get("pubkey")?.as_str()?.parse().ok())
+        .collect();
+    domains.sort();
+    let Some(domain) = domains.first() else {
+        return Ok(None);
+    };
+
+    let params = serde_json::json!([reverse_lookup_key(domain).to_string(), { "encoding": "base64" }]);
+    let info = rpc_call(rpc, "getAccountInfo", params).await?;
+    let name = info.get("value").and_then(account_data).and_then(|data| parse_reverse_lookup(&data));
+    Ok(name.map(|name| format!("{}.sol", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domains_are_told_apart_from_addresses() {
+        assert!(is_sol_domain("bonfida.sol"));
+        assert!(is_sol_domain("dex.bonfida.sol"));
+        assert!(!is_sol_domain(".sol"));
+        assert!(!is_sol_domain("bad..sol"));
+        assert!(!is_sol_domain(ROOT_DOMAIN_ACCOUNT));
+    }
+
+    #[test]
+    fn subdomains_derive_from_their_parent_domain() {
+        assert_ne!(domain_key("bonfida.sol"), domain_key("dex.bonfida.sol"));
+        assert_eq!(domain_key("bonfida.sol"), name_account_key("bonfida", &Pubkey::default(), &pubkey(ROOT_DOMAIN_ACCOUNT)));
+        assert_eq!(domain_key("dex.bonfida.sol"), name_account_key("\0dex", &Pubkey::default(), &domain_key("bonfida.sol")));
+    }
+
+    #[test]
+    fn reverse_lookup_data_holds_a_length_prefixed_name() {
+        let mut data = vec![0u8; REGISTRY_HEADER_LEN];
+        data.extend(7u32.to_le_bytes());
+        data.extend(b"bonfida");
+        assert_eq!(parse_reverse_lookup(&data), Some("bonfida".to_string()));
+
+        data.truncate(REGISTRY_HEADER_LEN + 6);
+        assert_eq!(parse_reverse_lookup(&data), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Summary statistics computed over a built graph.
+
+use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+
+use crate::graph::Graph;
+
+/// A node together with the number of distinct addresses it is linked to.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct NodeDegree {
+    pub address: String,
+    pub degree: usize,
+}
+
+/// How many nodes have a degree in `min..=max`, or `min` and up when there's no `max`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DegreeBucket {
+    pub min: usize,
+    pub max: Option<usize>,
+    pub count: usize,
+}
+
+impl DegreeBucket {
+    /// The range of degrees, as in `2-5` or `101+`.
+    pub fn range(&self) -> String {
+        match self.max {
+            Some(max) if max == self.min => max.to_string(),
+            Some(max) => format!("{}-{}", self.min, max),
+            None => format!("{}+", self.min),
+        }
+    }
+}
+
+/// The spread of node degrees over a graph, which shows how much of it hangs off a few hubs.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DegreeDistribution {
+    pub buckets: Vec<DegreeBucket>,
+    pub mean: f64,
+    pub median: f64,
+    pub max: usize,
+}
+
+/// The upper ends of the [`DegreeDistribution`] buckets; the last one has none.
+const DEGREE_BUCKETS: [usize; 4] = [1, 5, 20, 100];
+
+/// Each node's degree. In a directed graph this is the number of distinct addresses it has an
+/// edge to or from; otherwise every edge is stored both ways and the neighbor count is enough.
+pub fn degrees(graph: &Graph, directed: bool) -> HashMap<&str, usize> {
+    if !directed {
+        return graph.iter().map(|(node, neighbors)| (node.as_str(), neighbors.len())).collect();
+    }
+    let mut linked: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (node, neighbors) in graph {
+        for neighbor in neighbors.keys() {
+            linked.entry(node).or_default().push(neighbor);
+            linked.entry(neighbor).or_default().push(node);
+        }
+    }
+    graph.keys()
+        .map(|node| {
+            let mut neighbors = linked.remove(node.as_str()).unwrap_or_default();
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            (node.as_str(), neighbors.len())
+        })
+        .collect()
+}
+
+/// A copy of `graph` without the nodes whose degree exceeds `max_degree`, so that path searches can't
+/// route through them, together with the sorted list of removed nodes. Addresses in `keep` are never
+/// removed, since the query addresses must remain reachable as endpoints.
+pub fn without_hubs(graph: &Graph, directed: bool, max_degree: usize, keep: &[&str]) -> (Graph, Vec<String>) {
+    let mut hubs: Vec<String> = degrees(graph, directed).into_iter()
+        .filter(|(node, degree)| *degree > max_degree && !keep.contains(node))
+        .map(|(node, _)| node.to_string())
+        .collect();
+    hubs.sort_unstable();
+    (without_nodes(graph, &hubs), hubs)
+}
+
+/// A copy of `graph` without the nodes in `removed`, which must be sorted, or the edges that touch them.
+fn without_nodes(graph: &Graph, removed: &[String]) -> Graph {
+    graph.iter()
+        .filter(|(node, _)| removed.binary_search(node).is_err())
+        .map(|(node, neighbors)| {
+            let neighbors = neighbors.iter()
+                .filter(|(neighbor, _)| removed.binary_search(neighbor).is_err())
+                .map(|(neighbor, edge)| (neighbor.clone(), edge.clone()))
+                .collect();
+            (node.clone(), neighbors)
+        })
+        .collect()
+}
+
+/// A copy of `graph` with its dead ends peeled away: nodes linked to at most one address are
+/// removed, then the nodes that left with a single link, and so on until every remaining node has
+/// at least two. No simple path between two other addresses can pass through a dead end, so what
+/// is left is the core that path searches work on. Addresses in `keep` are never removed. The
+/// removed nodes are returned sorted.
+pub fn without_leaves(graph: &Graph, keep: &[&str]) -> (Graph, Vec<String>) {
+    let mut linked: HashMap<&str, HashSet<&str>> = graph.keys().map(|node| (node.as_str(), HashSet::new())).collect();
+    for (node, neighbors) in graph {
+        for neighbor in neighbors.keys() {
+            linked.entry(node).or_default().insert(neighbor);
+            linked.entry(neighbor).or_default().insert(node);
+        }
+    }
+
+    let mut leaves: Vec<&str> = linked.iter()
+        .filter(|(node, neighbors)| neighbors.len() <= 1 && !keep.contains(node))
+        .map(|(node, _)| *node)
+        .collect();
+    let mut removed: HashSet<&str> = leaves.iter().copied().collect();
+    while let Some(leaf) = leaves.pop() {
+        for neighbor in linked.remove(leaf).unwrap_or_default() {
+            let Some(links) = linked.get_mut(neighbor) else {
+                continue;
+            };
+            links.remove(leaf);
+            if links.len() <= 1 && !keep.contains(&neighbor) && removed.insert(neighbor) {
+                leaves.push(neighbor);
+            }
+        }
+    }
+
+    let mut removed: Vec<String> = removed.into_iter().map(str::to_string).collect();
+    removed.sort_unstable();
+    (without_nodes(graph, &removed), removed)
+}
+
+/// The number of edges in `graph`, counting each undirected edge once rather than once per direction.
+pub fn edge_count(graph: &Graph, directed: bool) -> usize {
+    let stored: usize = graph.values().map(|neighbors| neighbors.len()).sum();
+    if directed { stored } else { stored / 2 }
+}
+
+/// The connected components of `graph`, with edges followed in both directions, so a directed
+/// graph is split into its weakly connected parts: two addresses in different components can't
+/// be linked by any path. Components are listed largest first, ties broken by their first
+/// address, and the addresses of each are sorted.
+pub fn connected_components(graph: &Graph) -> Vec<Vec<String>> {
+    let nodes: Vec<&str> = graph.keys().map(String::as_str).collect();
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, node)| (*node, i)).collect();
+    let mut parent: Vec<usize> = (0..nodes.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (node, neighbors) in graph {
+        for neighbor in neighbors.keys() {
+            // A directed edge's target is always a node of its own, but don't rely on it
+            let Some(&j) = index.get(neighbor.as_str()) else {
+                continue;
+            };
+            let (a, b) = (root(&mut parent, index[node.as_str()]), root(&mut parent, j));
+            parent[a] = b;
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        groups.entry(root(&mut parent, i)).or_default().push(node.to_string());
+    }
+    let mut components: Vec<Vec<String>> = groups.into_values().collect();
+    for component in &mut components {
+        component.sort_unstable();
+    }
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+    components
+}
+
+/// The `n` highest-degree nodes, ties broken by address so the order is stable between runs.
+pub fn top_degrees(graph: &Graph, directed: bool, n: usize) -> Vec<NodeDegree> {
+    let mut nodes: Vec<NodeDegree> = degrees(graph, directed).into_iter()
+        .map(|(address, degree)| NodeDegree { address: address.to_string(), degree })
+        .collect();
+    nodes.sort_by(|a, b| b.degree.cmp(&a.degree).then_with(|| a.address.cmp(&b.address)));
+    nodes.truncate(n);
+    nodes
+}
+
+/// How the nodes of `graph` spread over degrees of 1, 2-5, 6-20, 21-100 and more than 100,
+/// with the mean, median and largest degree. All zero for an empty graph.
+pub fn degree_distribution(graph: &Graph, directed: bool) -> DegreeDistribution {
+    let mut sorted: Vec<usize> = degrees(graph, directed).into_values().collect();
+    sorted.sort_unstable();
+
+    let mut buckets: Vec<DegreeBucket> = DEGREE_BUCKETS.iter()
+        .map(|max| Some(*max))
+        .chain([None])
+        .scan(1, |min, max| {
+            let bucket = DegreeBucket { min: *min, max, count: 0 };
+            *min = max.map_or(*min, |max| max + 1);
+            Some(bucket)
+        })
+        .collect();
+    // Isolated nodes, left behind by pruning, count toward the lowest bucket
+    for degree in &sorted {
+        let index = DEGREE_BUCKETS.iter().position(|max| degree <= max).unwrap_or(DEGREE_BUCKETS.len());
+        buckets[index].count += 1;
+    }
+
+    let median = match sorted.len() {
+        0 => 0.0,
+        len if len % 2 == 0 => (sorted[len / 2 - 1] + sorted[len / 2]) as f64 / 2.0,
+        len => sorted[len / 2] as f64,
+    };
+    DegreeDistribution {
+        buckets,
+        mean: if sorted.is_empty() { 0.0 } else { sorted.iter().sum::<usize>() as f64 / sorted.len() as f64 },
+        median,
+        max: sorted.last().copied().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{add_edge, Edge};
+
+    fn graph(edges: &[(&str, &str)], directed: bool) -> Graph {
+        let mut graph = Graph::new();
+        for (from, to) in edges {
+            add_edge(&mut graph, from, to, &Edge { count: 1, ..Edge::default() }, directed);
+        }
+        graph
+    }
+
+    #[test]
+    fn degree_distribution_buckets_nodes_and_summarizes_degrees() {
+        // A hub with 25 spokes, two of which are also linked to each other
+        let spokes: Vec<String> = (0..25).map(|n| format!("spoke{}", n)).collect();
+        let mut edges: Vec<(&str, &str)> = spokes.iter().map(|spoke| ("Hub", spoke.as_str())).collect();
+        edges.push(("spoke0", "spoke1"));
+        let distribution = degree_distribution(&graph(&edges, false), false);
+
+        let counts: Vec<(String, usize)> = distribution.buckets.iter().map(|bucket| (bucket.range(), bucket.count)).collect();
+        let expected = [("1", 23), ("2-5", 2), ("6-20", 0), ("21-100", 1), ("101+", 0)];
+        assert_eq!(counts, expected.map(|(range, count)| (range.to_string(), count)));
+        assert_eq!((distribution.median, distribution.max), (1.0, 25));
+        assert_eq!(distribution.mean, 52.0 / 26.0);
+
+        assert_eq!(degree_distribution(&Graph::new(), false).max, 0);
+    }
+
+    #[test]
+    fn top_degrees_are_sorted_by_degree_then_address() {
+        let graph = graph(&[("Hub", "A"), ("Hub", "B"), ("Hub", "C"), ("A", "B")], false);
+        let top = top_degrees(&graph, false, 3);
+        assert_eq!(top, vec![
+            NodeDegree { address: "Hub".to_string(), degree: 3 },
+            NodeDegree { address: "A".to_string(), degree: 2 },
+            NodeDegree { address: "B".to_string(), degree: 2 },
+        ]);
+    }
+
+    #[test]
+    fn hubs_are_removed_unless_kept() {
+        let graph = graph(&[("Hub", "A"), ("Hub", "B"), ("Hub", "C"), ("A", "D"), ("D", "B")], false);
+        let (pruned, hubs) = without_hubs(&graph, false, 2, &["A"]);
+        assert_eq!(hubs, vec!["Hub".to_string()]);
+        assert!(!pruned.contains_key("Hub"));
+        assert!(pruned.values().all(|neighbors| !neighbors.contains_key("Hub")));
+        assert_eq!(pruned["D"].len(), 2);
+
+        let (_, hubs) = without_hubs(&graph, false, 2, &["Hub"]);
+        assert!(hubs.is_empty());
+    }
+
+    #[test]
+    fn dead_ends_are_peeled_down_to_the_core() {
+        // A - B - C - A is a triangle; D hangs off C with a tail D - E - F, and G is isolated
+        let mut graph = graph(&[("A", "B"), ("B", "C"), ("C", "A"), ("C", "D"), ("D", "E"), ("E", "F")], false);
+        graph.insert("G".to_string(), Default::default());
+        let (pruned, removed) = without_leaves(&graph, &[]);
+        assert_eq!(removed, vec!["D", "E", "F", "G"]);
+        assert_eq!(edge_count(&pruned, false), 3);
+
+        // A kept endpoint holds its tail in place
+        let (pruned, removed) = without_leaves(&graph, &["E"]);
+        assert_eq!(removed, vec!["F", "G"]);
+        assert_eq!(edge_count(&pruned, false), 5);
+    }
+
+    #[test]
+    fn components_are_ordered_by_size_and_ignore_direction() {
+        let mut graph = graph(&[("A", "B"), ("C", "B"), ("D", "E"), ("F", "G"), ("G", "H")], true);
+        graph.insert("I".to_string(), Default::default());
+        let components = connected_components(&graph);
+        assert_eq!(components, vec![
+            vec!["A".to_string(), "B".to_string(), "C".to_string()],
+            vec!["F".to_string(), "G".to_string(), "H".to_string()],
+            vec!["D".to_string(), "E".to_string()],
+            vec!["I".to_string()],
+        ]);
+        assert!(connected_components(&Graph::new()).is_empty());
+    }
+
+    #[test]
+    fn directed_degree_counts_each_neighbor_once() {
+        let graph = graph(&[("A", "B"), ("B", "A"), ("C", "A")], true);
+        let degrees = degrees(&graph, true);
+        assert_eq!(degrees["A"], 2);
+        assert_eq!(degrees["B"], 1);
+        assert_eq!(degrees["C"], 1);
+    }
+}
@@ -0,0 +1,236 @@
+//! SQLite storage for a whole crawl: signature histories, transaction details and the derived
+//! graph in one file that can be resumed and queried with SQL. Requires the `sqlite` feature.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use crate::export::graph_document;
+use crate::graph::{add_edge, Edge, Graph};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS signatures (
+        address TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        signature TEXT NOT NULL,
+        PRIMARY KEY (address, position)
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        signature TEXT PRIMARY KEY,
+        slot INTEGER,
+        block_time INTEGER,
+        json TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS domains (
+        address TEXT PRIMARY KEY,
+        domain TEXT
+    );
+    CREATE TABLE IF NOT EXISTS nodes (
+        address TEXT PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS edges (
+        source TEXT NOT NULL,
+        target TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        lamports INTEGER NOT NULL,
+        json TEXT NOT NULL,
+        PRIMARY KEY (source, target)
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// A crawl database. `signatures` holds each address's history newest first (position 0),
+/// `transactions` the raw getTransaction results, and `nodes`/`edges` the graph built from them
+/// by the last analysis, with `meta.directed` telling how its edges are stored. Edge count and
+/// lamports have their own columns for querying; the full edge is kept as JSON.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    path: PathBuf,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        // WAL keeps the per-transaction writes of a crawl cheap
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection: Mutex::new(connection), path: path.to_path_buf() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The stored signatures of `address`, newest first.
+    pub fn get_history(&self, address: &str) -> rusqlite::Result<Option<Vec<String>>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached("SELECT signature FROM signatures WHERE address = ?1 ORDER BY position")?;
+        let signatures = statement.query_map([address], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok((!signatures.is_empty()).then_some(signatures))
+    }
+
+    /// Adds newly fetched signatures, newest first, in front of those already stored for `address`.
+    pub fn merge_history(&self, address: &str, signatures: &[String]) -> rusqlite::Result<()> {
+        let mut merged = signatures.to_vec();
+        let fetched: HashSet<&String> = signatures.iter().collect();
+        if let Some(stored) = self.get_history(address)? {
+            merged.extend(stored.into_iter().filter(|signature| !fetched.contains(signature)));
+        }
+
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM signatures WHERE address = ?1", [address])?;
+        {
+            let mut insert = transaction.prepare("INSERT INTO signatures (address, position, signature) VALUES (?1, ?2, ?3)")?;
+            for (position, signature) in merged.iter().enumerate() {
+                insert.execute(params![address, position as i64, signature])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    pub fn get(&self, signature: &str) -> rusqlite::Result<Option<Value>> {
+        let json: Option<String> = self.connection()
+            .query_row("SELECT json FROM transactions WHERE signature = ?1", [signature], |row| row.get(0))
+            .optional()?;
+        Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    pub fn put(&self, signature: &str, transaction: &Value) -> rusqlite::Result<()> {
+        self.connection().execute(
+            "INSERT OR REPLACE INTO transactions (signature, slot, block_time, json) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                signature,
+                transaction.get("slot").and_then(|slot| slot.as_i64()),
+                transaction.get("blockTime").and_then(|time| time.as_i64()),
+                transaction.to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The stored `.sol` domain of `address`: `Some(None)` when it is known to have none.
+    pub fn get_domain(&self, address: &str) -> rusqlite::Result<Option<Option<String>>> {
+        self.connection()
+            .query_row("SELECT domain FROM domains WHERE address = ?1", [address], |row| row.get(0))
+            .optional()
+    }
+
+    pub fn put_domain(&self, address: &str, domain: Option<&str>) -> rusqlite::Result<()> {
+        self.connection().execute("INSERT OR REPLACE INTO domains (address, domain) VALUES (?1, ?2)", params![address, domain])?;
+        Ok(())
+    }
+
+    /// Replaces the stored graph. Undirected edges are stored once, from the smaller endpoint.
+    pub fn save_graph(&self, graph: &Graph, directed: bool) -> rusqlite::Result<()> {
+        let document = graph_document(graph, directed);
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute_batch("DELETE FROM nodes; DELETE FROM edges;")?;
+        {
+            let mut insert_node = transaction.prepare("INSERT INTO nodes (address) VALUES (?1)")?;
+            for node in &document.nodes {
+                insert_node.execute([node])?;
+            }
+            let mut insert_edge = transaction.prepare("INSERT INTO edges (source, target, count, lamports, json) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+            for edge in &document.edges {
+                let json = serde_json::to_string(edge.edge).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                insert_edge.execute(params![edge.source, edge.target, edge.edge.count, edge.edge.lamports as i64, json])?;
+            }
+        }
+        transaction.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('directed', ?1)", [directed.to_string()])?;
+        transaction.commit()
+    }
+
+    /// The graph stored by [`save_graph`](Self::save_graph) with whether it is directed, or `None`
+    /// if no graph has been stored yet.
+    pub fn load_graph(&self) -> rusqlite::Result<Option<(Graph, bool)>> {
+        let connection = self.connection();
+        let directed: Option<String> = connection
+            .query_row("SELECT value FROM meta WHERE key = 'directed'", [], |row| row.get(0))
+            .optional()?;
+        let Some(directed) = directed.map(|value| value == "true") else {
+            return Ok(None);
+        };
+
+        let mut graph = Graph::new();
+        let mut nodes = connection.prepare("SELECT address FROM nodes")?;
+        for node in nodes.query_map([], |row| row.get::<_, String>(0))? {
+            graph.entry(node?).or_default();
+        }
+        let mut edges = connection.prepare("SELECT source, target, json FROM edges")?;
+        let rows = edges.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+        for row in rows {
+            let (source, target, json) = row?;
+            let edge: Edge = serde_json::from_str(&json)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+            add_edge(&mut graph, &source, &target, &edge, directed);
+        }
+        Ok(Some((graph, directed)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> (SqliteStore, PathBuf) {
+        let path = std::env::temp_dir().join(format!("solconnect-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        (SqliteStore::open(&path).unwrap(), path)
+    }
+
+    #[test]
+    fn stores_histories_transactions_and_domains() {
+        let (store, path) = temp_store("store");
+        let signatures = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        store.merge_history("A", &signatures(&["s2", "s1"])).unwrap();
+        store.merge_history("A", &signatures(&["s3", "s2"])).unwrap();
+        assert_eq!(store.get_history("A").unwrap(), Some(signatures(&["s3", "s2", "s1"])));
+        assert_eq!(store.get_history("B").unwrap(), None);
+
+        let transaction = serde_json::json!({ "slot": 7, "blockTime": 1_700_000_000, "meta": null });
+        store.put("s1", &transaction).unwrap();
+        assert_eq!(store.get("s1").unwrap(), Some(transaction));
+        assert_eq!(store.get("s2").unwrap(), None);
+
+        store.put_domain("A", None).unwrap();
+        store.put_domain("B", Some("bonfida.sol")).unwrap();
+        assert_eq!(store.get_domain("A").unwrap(), Some(None));
+        assert_eq!(store.get_domain("B").unwrap(), Some(Some("bonfida.sol".to_string())));
+        assert_eq!(store.get_domain("C").unwrap(), None);
+
+        // Everything survives reopening the file
+        drop(store);
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get_history("A").unwrap().map(|history| history.len()), Some(3));
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn saved_graphs_load_back_unchanged() {
+        let (store, path) = temp_store("store-graph");
+        assert_eq!(store.load_graph().unwrap(), None);
+
+        let mut graph = Graph::new();
+        add_edge(&mut graph, "A", "B", &Edge { count: 2, lamports: 5, signatures: vec!["sig".to_string()], ..Edge::default() }, false);
+        add_edge(&mut graph, "B", "C", &Edge { count: 1, ..Edge::default() }, false);
+        graph.entry("Lonely".to_string()).or_default();
+
+        store.save_graph(&graph, false).unwrap();
+        assert_eq!(store.load_graph().unwrap(), Some((graph, false)));
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Live notifications of new transactions from a node's WebSocket (PubSub) endpoint.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use futures::{SinkExt, StreamExt};
+use reqwest::Url;
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::time::{interval, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, trace};
+
+use crate::error::SolConnectError;
+use crate::rpc::{rpc_error, Commitment};
+
+/// Providers close WebSocket connections that stay silent for a few minutes, so an idle
+/// subscription pings the node this often.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The PubSub URL that goes with an HTTP RPC endpoint: `http` becomes `ws` and `https` becomes
+/// `wss`. A local validator serves PubSub on the port after its RPC port, so 8899 becomes 8900.
+pub fn websocket_url(endpoint: &str) -> Result<String, SolConnectError> {
+    let mut url = Url::parse(endpoint).map_err(|e| SolConnectError::Config(format!("invalid RPC URL {}: {}", endpoint, e)))?;
+    let scheme = match url.scheme() {
+        "http" | "ws" => "ws",
+        "https" | "wss" => "wss",
+        other => return Err(SolConnectError::Config(format!("can't derive a WebSocket URL from a {} URL", other))),
+    };
+    url.set_scheme(scheme).expect("http(s) and ws(s) are interchangeable");
+    if url.port() == Some(8899) {
+        url.set_port(Some(8900)).expect("the URL has a host");
+    }
+    Ok(url.to_string())
+}
+
+/// A transaction that mentioned one of the subscribed addresses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogNotification {
+    pub address: String,
+    pub signature: String,
+    /// Whether the transaction failed, leaving only its fee behind
+    pub failed: bool,
+}
+
+/// One `logsSubscribe` subscription per address over a single WebSocket connection. A
+/// transaction that mentions several of the addresses is reported once for each of them.
+pub struct LogSubscription {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// The address behind each subscription id
+    subscriptions: HashMap<u64, String>,
+    /// Notifications that arrived while the subscriptions were still being confirmed
+    pending: VecDeque<Value>,
+    ping: Interval,
+}
+
+impl LogSubscription {
+    /// Connects to `url` and subscribes to the transactions mentioning each of `addresses`,
+    /// returning once the node has confirmed every subscription.
+    pub async fn connect(url: &str, addresses: &[String], commitment: Commitment) -> Result<Self, SolConnectError> {
+        debug!("Connecting to {}", url);
+        let (socket, _) = connect_async(url).await?;
+        let mut ping = interval(PING_INTERVAL);
+        ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut subscription = LogSubscription { socket, subscriptions: HashMap::new(), pending: VecDeque::new(), ping };
+
+        for (id, address) in addresses.iter().enumerate() {
+            let request = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "logsSubscribe",
+                "params": [{ "mentions": [address] }, { "commitment": commitment.as_str() }]
+            });
+            subscription.socket.send(Message::Text(request.to_string())).await?;
+        }
+        while subscription.subscriptions.len() < addresses.len() {
+            let message = subscription.next_message().await?;
+            if message.get("method").is_some() {
+                subscription.pending.push_back(message);
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(rpc_error(error));
+            }
+            let id = message.get("id").and_then(|id| id.as_u64()).map(|id| id as usize);
+            match (id.and_then(|id| addresses.get(id)), message.get("result").and_then(|r| r.as_u64())) {
+                (Some(address), Some(subscription_id)) => {
+                    subscription.subscriptions.insert(subscription_id, address.clone());
+                }
+                _ => return Err(SolConnectError::Parse(format!("unexpected logsSubscribe response: {}", message))),
+            }
+        }
+        Ok(subscription)
+    }
+
+    /// Waits for the next transaction that mentions a subscribed address. Fails, retryably, when
+    /// the node closes the connection.
+    pub async fn next(&mut self) -> Result<LogNotification, SolConnectError> {
+        loop {
+            let message = match self.pending.pop_front() {
+                Some(message) => message,
+                None => self.next_message().await?,
+            };
+            if let Some(notification) = parse_notification(&message, &self.subscriptions) {
+                return Ok(notification);
+            }
+        }
+    }
+
+    /// The next JSON message from the node, pinging it whenever the connection has been idle for
+    /// [`PING_INTERVAL`]. Pongs to the node's pings are sent while reading.
+    async fn next_message(&mut self) -> Result<Value, SolConnectError> {
+        loop {
+            let message = tokio::select! {
+                message = self.socket.next() => message,
+                _ = self.ping.tick() => {
+                    self.socket.send(Message::Ping(Vec::new())).await?;
+                    continue;
+                }
+            };
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Binary(bytes))) => String::from_utf8_lossy(&bytes).into_owned(),
+                Some(Ok(Message::Close(_))) | None => return Err(WsError::ConnectionClosed.into()),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+            };
+            trace!("WebSocket message: {}", text);
+            return Ok(serde_json::from_str(&text)?);
+        }
+    }
+}
+
+/// Reads a `logsNotification` for one of `subscriptions`; anything else is `None`.
+fn parse_notification(message: &Value, subscriptions: &HashMap<u64, String>) -> Option<LogNotification> {
+    if message.get("method").and_then(|m| m.as_str()) != Some("logsNotification") {
+        return None;
+    }
+    let params = message.get("params")?;
+    let address = subscriptions.get(&params.get("subscription")?.as_u64()?)?;
+    let value = params.get("result")?.get("value")?;
+    Some(LogNotification {
+        address: address.clone(),
+        signature: value.get("signature")?.as_str()?.to_string(),
+        failed: value.get("err").is_some_and(|err| !err.is_null()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocket_urls_follow_the_rpc_endpoint() {
+        assert_eq!(websocket_url("https://api.mainnet-beta.solana.com").unwrap(), "wss://api.mainnet-beta.solana.com/");
+        assert_eq!(websocket_url("http://127.0.0.1:8899").unwrap(), "ws://127.0.0.1:8900/");
+        assert_eq!(websocket_url("https://rpc.example.com/?api-key=abc").unwrap(), "wss://rpc.example.com/?api-key=abc");
+        assert!(websocket_url("ftp://example.com").is_err());
+    }
+
+    #[test]
+    fn notifications_are_matched_to_their_subscription() {
+        let subscriptions = HashMap::from([(24040, "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU".to_string())]);
+        let notification: Value = serde_json::from_str(r#"{
+            "jsonrpc": "2.0",
+            "method": "logsNotification",
+            "params": {
+                "result": {
+                    "context": { "slot": 5208469 },
+                    "value": {
+                        "signature": "5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXFSDwt8GFXM7W5Ncn16wmqokgpiKRLuS83KUxyZyv2sUYv",
+                        "err": null,
+                        "logs": ["Program 11111111111111111111111111111111 invoke [1]"]
+                    }
+                },
+                "subscription": 24040
+            }
+        }"#).unwrap();
+        assert_eq!(parse_notification(&notification, &subscriptions), Some(LogNotification {
+            address: "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU".to_string(),
+            signature: "5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXFSDwt8GFXM7W5Ncn16wmqokgpiKRLuS83KUxyZyv2sUYv".to_string(),
+            failed: false,
+        }));
+
+        assert_eq!(parse_notification(&notification, &HashMap::new()), None);
+        assert_eq!(parse_notification(&serde_json::json!({ "jsonrpc": "2.0", "result": 24040, "id": 0 }), &subscriptions), None);
+    }
+}